@@ -0,0 +1,30 @@
+-- Vector store collections backed by pgvector HNSW indexes
+-- Skipped gracefully when pgvector is unavailable (same as migration 009).
+-- Each collection gets its own partial HNSW index, created by the backend
+-- in vs_create_collection, over `embedding::vector(<dimension>)`.
+DO $$
+BEGIN
+    CREATE EXTENSION IF NOT EXISTS vector;
+
+    CREATE TABLE IF NOT EXISTS gh_vector_collections (
+        id         SERIAL PRIMARY KEY,
+        name       TEXT NOT NULL UNIQUE,
+        dimension  INTEGER NOT NULL CHECK (dimension BETWEEN 1 AND 2000),
+        metric     TEXT NOT NULL DEFAULT 'cosine' CHECK (metric IN ('cosine', 'l2', 'ip')),
+        model      TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+    CREATE TABLE IF NOT EXISTS gh_vector_items (
+        collection_id INTEGER NOT NULL REFERENCES gh_vector_collections(id) ON DELETE CASCADE,
+        id            TEXT NOT NULL,
+        embedding     vector NOT NULL,
+        content       TEXT NOT NULL DEFAULT '',
+        metadata      JSONB NOT NULL DEFAULT '{}',
+        updated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (collection_id, id)
+    );
+EXCEPTION WHEN OTHERS THEN
+    RAISE NOTICE 'pgvector not available — skipping vector store tables';
+END;
+$$;
@@ -0,0 +1,11 @@
+-- Knowledge graph: arbitrary properties, edge weights and timestamps
+ALTER TABLE gh_knowledge_nodes ADD COLUMN IF NOT EXISTS properties JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE gh_knowledge_nodes ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE gh_knowledge_nodes ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+ALTER TABLE gh_knowledge_edges ADD COLUMN IF NOT EXISTS properties JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE gh_knowledge_edges ADD COLUMN IF NOT EXISTS weight DOUBLE PRECISION NOT NULL DEFAULT 1.0;
+ALTER TABLE gh_knowledge_edges ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE gh_knowledge_edges ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS idx_gh_kg_edges_target ON gh_knowledge_edges (target);
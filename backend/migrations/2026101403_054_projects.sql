@@ -0,0 +1,42 @@
+-- Projects: named workspaces that scope chats, memories and standing context
+CREATE TABLE IF NOT EXISTS gh_projects (
+    id            UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name          TEXT NOT NULL,
+    root_path     TEXT NOT NULL UNIQUE,
+    default_model TEXT,
+    system_prompt TEXT NOT NULL DEFAULT '',
+    is_active     BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- At most one active project at a time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_gh_projects_active ON gh_projects (is_active) WHERE is_active;
+
+ALTER TABLE gh_sessions ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES gh_projects(id) ON DELETE SET NULL;
+ALTER TABLE gh_memories ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES gh_projects(id) ON DELETE SET NULL;
+ALTER TABLE gh_a2a_tasks ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES gh_projects(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_gh_sessions_project ON gh_sessions (project_id);
+CREATE INDEX IF NOT EXISTS idx_gh_memories_project ON gh_memories (project_id);
+CREATE INDEX IF NOT EXISTS idx_gh_a2a_tasks_project ON gh_a2a_tasks (project_id);
+
+-- Rows created by the shared crates (new chats, memories, swarm tasks) don't
+-- know about projects — tag them with the active project on insert.
+CREATE OR REPLACE FUNCTION gh_tag_active_project() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.project_id IS NULL THEN
+        SELECT id INTO NEW.project_id FROM gh_projects WHERE is_active LIMIT 1;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_gh_sessions_project ON gh_sessions;
+CREATE TRIGGER trg_gh_sessions_project BEFORE INSERT ON gh_sessions
+    FOR EACH ROW EXECUTE FUNCTION gh_tag_active_project();
+DROP TRIGGER IF EXISTS trg_gh_memories_project ON gh_memories;
+CREATE TRIGGER trg_gh_memories_project BEFORE INSERT ON gh_memories
+    FOR EACH ROW EXECUTE FUNCTION gh_tag_active_project();
+DROP TRIGGER IF EXISTS trg_gh_a2a_tasks_project ON gh_a2a_tasks;
+CREATE TRIGGER trg_gh_a2a_tasks_project BEFORE INSERT ON gh_a2a_tasks
+    FOR EACH ROW EXECUTE FUNCTION gh_tag_active_project();
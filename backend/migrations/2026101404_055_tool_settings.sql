@@ -0,0 +1,7 @@
+-- Per-tool enable/disable switches for the agent tool registry
+-- Tools without a row are enabled; only explicit overrides are stored.
+CREATE TABLE IF NOT EXISTS gh_tool_settings (
+    name       TEXT PRIMARY KEY,
+    enabled    BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- Named system prompt templates with {{variable}} placeholders
+-- `default_for_agent` makes a template the standing prompt of one agent (at most one per agent).
+CREATE TABLE IF NOT EXISTS gh_prompt_templates (
+    name              TEXT PRIMARY KEY,
+    description       TEXT NOT NULL DEFAULT '',
+    content           TEXT NOT NULL,
+    default_for_agent TEXT UNIQUE,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
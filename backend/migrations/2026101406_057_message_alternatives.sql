@@ -0,0 +1,14 @@
+-- Regenerated alternatives for assistant messages ("try again")
+-- The original reply is kept as alternative 0 so every version can be re-selected.
+CREATE TABLE IF NOT EXISTS gh_message_alternatives (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id  UUID NOT NULL REFERENCES gh_chat_messages(id) ON DELETE CASCADE,
+    idx         INTEGER NOT NULL,
+    content     TEXT NOT NULL,
+    model       TEXT,
+    temperature DOUBLE PRECISION,
+    seed        BIGINT,
+    selected    BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (message_id, idx)
+);
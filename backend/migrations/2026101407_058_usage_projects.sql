@@ -0,0 +1,7 @@
+-- Usage ledger: attribute token usage to projects (same trigger as migration 054)
+ALTER TABLE gh_agent_usage ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES gh_projects(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_gh_agent_usage_project ON gh_agent_usage (project_id);
+
+DROP TRIGGER IF EXISTS trg_gh_agent_usage_project ON gh_agent_usage;
+CREATE TRIGGER trg_gh_agent_usage_project BEFORE INSERT ON gh_agent_usage
+    FOR EACH ROW EXECUTE FUNCTION gh_tag_active_project();
@@ -0,0 +1,2 @@
+-- Toggle for injecting remembered facts into new chats (see memory_recall.rs)
+ALTER TABLE gh_settings ADD COLUMN IF NOT EXISTS memory_injection BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,17 @@
+-- Knowledge-graph provenance: which chat messages an extracted node/edge came from
+CREATE TABLE IF NOT EXISTS gh_knowledge_provenance (
+    id BIGSERIAL PRIMARY KEY,
+    node_id TEXT NOT NULL REFERENCES gh_knowledge_nodes(id) ON DELETE CASCADE,
+    -- Set for edges (node_id is the edge source); NULL for node provenance.
+    edge_target TEXT REFERENCES gh_knowledge_nodes(id) ON DELETE CASCADE,
+    edge_label TEXT,
+    session_id UUID NOT NULL REFERENCES gh_sessions(id) ON DELETE CASCADE,
+    message_id UUID NOT NULL REFERENCES gh_chat_messages(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_gh_knowledge_provenance_unique
+    ON gh_knowledge_provenance (node_id, COALESCE(edge_target, ''), COALESCE(edge_label, ''), message_id);
+CREATE INDEX IF NOT EXISTS idx_gh_knowledge_provenance_message ON gh_knowledge_provenance (message_id);
+
+-- Sessions already run through the extraction job
+ALTER TABLE gh_sessions ADD COLUMN IF NOT EXISTS kg_extracted_at TIMESTAMPTZ;
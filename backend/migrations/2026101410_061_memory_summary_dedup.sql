@@ -0,0 +1,11 @@
+-- Memories distilled from a session (memory_summary.rs): summarising the same
+-- session again updates its facts instead of inserting duplicates.
+ALTER TABLE gh_memories ADD COLUMN IF NOT EXISTS session_id UUID REFERENCES gh_sessions(id) ON DELETE SET NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_gh_memories_session_content
+    ON gh_memories (session_id, md5(lower(btrim(content)))) WHERE session_id IS NOT NULL;
+
+-- Sessions already summarised by the idle-session job. Sessions that predate
+-- the job are marked as done so upgrading doesn't send every old transcript
+-- to Gemini; they can still be summarised on demand.
+ALTER TABLE gh_sessions ADD COLUMN IF NOT EXISTS memory_summarized_at TIMESTAMPTZ;
+UPDATE gh_sessions SET memory_summarized_at = NOW() WHERE memory_summarized_at IS NULL;
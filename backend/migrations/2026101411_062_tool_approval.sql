@@ -0,0 +1,10 @@
+-- Per-tool approval rules (tool_registry.rs): 'ask' tools only run after the
+-- user approved the call via POST /api/tools/approvals/{id}/approve.
+ALTER TABLE gh_tool_settings ADD COLUMN IF NOT EXISTS approval TEXT NOT NULL DEFAULT 'auto';
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'gh_tool_settings_approval_check') THEN
+        ALTER TABLE gh_tool_settings ADD CONSTRAINT gh_tool_settings_approval_check
+            CHECK (approval IN ('auto', 'ask'));
+    END IF;
+END $$;
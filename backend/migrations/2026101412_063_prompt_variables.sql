@@ -0,0 +1,7 @@
+-- User-defined prompt variables (prompt_templates.rs): filled into agent
+-- default templates at chat time and used as defaults by /render.
+CREATE TABLE IF NOT EXISTS gh_prompt_variables (
+    name       TEXT PRIMARY KEY,
+    value      TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
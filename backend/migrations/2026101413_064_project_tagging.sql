@@ -0,0 +1,48 @@
+-- Project tagging at the write sites (supersedes the blanket triggers of 054)
+--
+-- Memories are tagged explicitly by memory_summary.rs (the session's
+-- project) and the `remember` tool (the execution's project), so they no
+-- longer pick up whatever project happens to be active.
+DROP TRIGGER IF EXISTS trg_gh_memories_project ON gh_memories;
+
+-- Memories distilled from a session follow that session, including sessions
+-- detached from every project.
+UPDATE gh_memories m SET project_id = s.project_id
+  FROM gh_sessions s
+ WHERE m.session_id = s.id AND m.project_id IS DISTINCT FROM s.project_id;
+
+-- Sessions are created by the shared crate. Those with a working directory
+-- are tagged on their first chat turn by projects.rs, which normalises paths
+-- the same way as root_path; without one the execution runs in the active
+-- project's root, so that project is used right away.
+CREATE OR REPLACE FUNCTION gh_tag_session_project() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.project_id IS NULL AND btrim(COALESCE(NEW.working_directory, '')) = '' THEN
+        SELECT id INTO NEW.project_id FROM gh_projects WHERE is_active LIMIT 1;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_gh_sessions_project ON gh_sessions;
+CREATE TRIGGER trg_gh_sessions_project BEFORE INSERT ON gh_sessions
+    FOR EACH ROW EXECUTE FUNCTION gh_tag_session_project();
+
+-- Delegated swarm tasks inherit their parent's project; root tasks run in
+-- the active project's root and are tagged with it.
+CREATE OR REPLACE FUNCTION gh_tag_a2a_task_project() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.project_id IS NULL THEN
+        IF NEW.parent_task_id IS NOT NULL THEN
+            SELECT project_id INTO NEW.project_id FROM gh_a2a_tasks WHERE id = NEW.parent_task_id;
+        ELSE
+            SELECT id INTO NEW.project_id FROM gh_projects WHERE is_active LIMIT 1;
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_gh_a2a_tasks_project ON gh_a2a_tasks;
+CREATE TRIGGER trg_gh_a2a_tasks_project BEFORE INSERT ON gh_a2a_tasks
+    FOR EACH ROW EXECUTE FUNCTION gh_tag_a2a_task_project();
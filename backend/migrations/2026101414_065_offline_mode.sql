@@ -0,0 +1,3 @@
+-- Persisted offline switch (offline.rs): loaded at startup so a restart
+-- never silently brings the backend back online.
+ALTER TABLE gh_settings ADD COLUMN IF NOT EXISTS offline_mode BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,158 @@
+// ---------------------------------------------------------------------------
+// bin/hydra.rs — Headless CLI for a running GeminiHydra backend
+//
+// Thin HTTP client for scripts and CI: every subcommand maps to one API call
+// against `HYDRA_URL` (default `http://127.0.0.1:$PORT`, port 8081) with
+// `HYDRA_TOKEN` sent as a bearer token. Responses are printed as JSON, except
+// `generate`, which prints the answer text when the response has one.
+// ---------------------------------------------------------------------------
+
+use std::process::ExitCode;
+
+use anyhow::{Context, bail};
+use serde_json::{Value, json};
+
+const USAGE: &str = "\
+Usage: hydra <command> [options]
+
+Commands:
+  generate -p <prompt> [-m <model>] [--agent <mode>] [--json]
+                             Run a prompt through the agent loop
+  models                     List available Gemini models
+  capabilities               Show backend version and features
+  usage [--period <day|week|month|all>] [--group-by <day|model|agent|tier|project>]
+                             Token and cost summary
+  summarize <session-id>     Summarize a session into memory
+
+Environment:
+  HYDRA_URL    Backend base URL (default http://127.0.0.1:$PORT, PORT=8081)
+  HYDRA_TOKEN  Bearer token for authenticated endpoints";
+
+struct Client {
+    http: reqwest::Client,
+    base: String,
+    token: Option<String>,
+}
+
+impl Client {
+    fn from_env() -> Self {
+        let base = std::env::var("HYDRA_URL").unwrap_or_else(|_| {
+            let port = std::env::var("PORT").unwrap_or_else(|_| "8081".to_string());
+            format!("http://127.0.0.1:{}", port)
+        });
+        Self {
+            http: reqwest::Client::new(),
+            base: base.trim_end_matches('/').to_string(),
+            token: std::env::var("HYDRA_TOKEN").ok().filter(|t| !t.is_empty()),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> anyhow::Result<Value> {
+        let mut req = self.http.request(method, format!("{}{}", self.base, path));
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let res = req
+            .send()
+            .await
+            .with_context(|| format!("cannot reach backend at {}", self.base))?;
+        let status = res.status();
+        let body: Value = res.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let msg = body
+                .get("error")
+                .map(|e| e.as_str().map_or_else(|| e.to_string(), str::to_string))
+                .unwrap_or_else(|| status.to_string());
+            bail!("HTTP {}: {}", status.as_u16(), msg);
+        }
+        Ok(body)
+    }
+}
+
+/// Value following `flag`, e.g. `-p hello` → `Some("hello")`.
+fn option(args: &[String], flags: &[&str]) -> Option<String> {
+    args.iter()
+        .position(|a| flags.contains(&a.as_str()))
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+    );
+}
+
+async fn run(args: &[String]) -> anyhow::Result<()> {
+    let Some(command) = args.first() else {
+        bail!("missing command\n\n{}", USAGE);
+    };
+    let rest = &args[1..];
+    let client = Client::from_env();
+
+    match command.as_str() {
+        "generate" => {
+            let prompt =
+                option(rest, &["-p", "--prompt"]).context("generate requires -p <prompt>")?;
+            let mut body = json!({
+                "prompt": prompt,
+                "mode": option(rest, &["--agent"]).unwrap_or_else(|| "auto".to_string()),
+            });
+            if let Some(model) = option(rest, &["-m", "--model"]) {
+                body["model"] = json!(model);
+            }
+            let res = client
+                .send(reqwest::Method::POST, "/api/execute", Some(body))
+                .await?;
+            match res.get("result").and_then(|r| r.as_str()) {
+                Some(text) if !rest.iter().any(|a| a == "--json") => println!("{}", text),
+                _ => print_json(&res),
+            }
+        }
+        "models" => print_json(
+            &client
+                .send(reqwest::Method::GET, "/api/gemini/models", None)
+                .await?,
+        ),
+        "capabilities" => print_json(
+            &client
+                .send(reqwest::Method::GET, "/api/capabilities", None)
+                .await?,
+        ),
+        "usage" => {
+            let period = option(rest, &["--period"]).unwrap_or_else(|| "month".to_string());
+            let group_by = option(rest, &["--group-by"]).unwrap_or_else(|| "day".to_string());
+            let path = format!("/api/usage/summary?period={}&group_by={}", period, group_by);
+            print_json(&client.send(reqwest::Method::GET, &path, None).await?);
+        }
+        "summarize" => {
+            let id = rest.first().context("summarize requires <session-id>")?;
+            let path = format!("/api/sessions/{}/summarize", id);
+            print_json(&client.send(reqwest::Method::POST, &path, None).await?);
+        }
+        "help" | "-h" | "--help" => println!("{}", USAGE),
+        other => bail!("unknown command '{}'\n\n{}", other, USAGE),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("hydra: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,213 @@
+// ---------------------------------------------------------------------------
+// capabilities.rs — Backend version and feature discovery
+//
+// Lets the frontend and scripts check what this backend offers before
+// calling it: runtime-dependent features (Vertex, offline, quotas, MCP),
+// the app-specific API groups with their routes and the event names pushed
+// over the swarm channel and SSE streams. Bump `API_VERSION` when an
+// existing endpoint changes incompatibly.
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::State;
+use serde_json::{Value, json};
+
+use crate::state::AppState;
+
+pub const API_VERSION: u32 = 1;
+
+/// App-specific API groups: `(feature, routes)`. `advertised_routes_are_routed`
+/// in tests/api_tests.rs checks every entry against the real router.
+const API_GROUPS: &[(&str, &[&str])] = &[
+    (
+        "admin",
+        &[
+            "/api/admin/rotate-key",
+            "/api/admin/support-bundle",
+            "/api/admin/crash-reports",
+            "/api/admin/crash-reports/{file}",
+        ],
+    ),
+    (
+        "agents",
+        &[
+            "/api/agents",
+            "/api/agents/profiles",
+            "/api/agents/classify",
+            "/api/agents/delegations",
+            "/api/agents/delegations/stream",
+            "/api/agents/{id}",
+        ],
+    ),
+    (
+        "code_index",
+        &["/api/code-index/rebuild", "/api/code-index/lookup"],
+    ),
+    (
+        "files",
+        &[
+            "/api/files/read",
+            "/api/files/list",
+            "/api/files/browse",
+            "/api/files/brief",
+        ],
+    ),
+    (
+        "git",
+        &[
+            "/api/git/status",
+            "/api/git/diff",
+            "/api/git/stage",
+            "/api/git/commit",
+            "/api/git/branch",
+        ],
+    ),
+    (
+        "image_generation",
+        &["/api/images/generate", "/api/images/generated/{file}"],
+    ),
+    (
+        "knowledge_extraction",
+        &[
+            "/api/sessions/{id}/extract-knowledge",
+            "/api/knowledge/nodes/{id}/provenance",
+        ],
+    ),
+    (
+        "knowledge_graph",
+        &[
+            "/api/knowledge/export",
+            "/api/knowledge/import",
+            "/api/knowledge/nodes/{id}",
+            "/api/knowledge/edges",
+            "/api/knowledge/view",
+        ],
+    ),
+    (
+        "memory",
+        &["/api/sessions/{id}/summarize", "/api/memory/injection"],
+    ),
+    (
+        "message_alternatives",
+        &[
+            "/api/sessions/{id}/messages/{message_id}/regenerate",
+            "/api/sessions/{id}/messages/{message_id}/regenerate/stream",
+            "/api/messages/{id}/alternatives",
+            "/api/messages/{id}/alternatives/{alternative_id}/select",
+        ],
+    ),
+    ("models", &["/api/gemini/models"]),
+    ("offline_mode", &["/api/offline"]),
+    (
+        "patch",
+        &["/api/files/diff-preview", "/api/files/apply-patch"],
+    ),
+    (
+        "projects",
+        &[
+            "/api/projects",
+            "/api/projects/deactivate",
+            "/api/projects/{id}",
+            "/api/projects/{id}/activate",
+            "/api/projects/{id}/sessions",
+            "/api/projects/{id}/memories",
+            "/api/sessions/{id}/project",
+        ],
+    ),
+    (
+        "prompt_templates",
+        &[
+            "/api/prompts",
+            "/api/prompts/{name}",
+            "/api/prompts/{name}/render",
+            "/api/prompt-variables",
+            "/api/prompt-variables/{name}",
+        ],
+    ),
+    ("structured_output", &["/api/generate/structured"]),
+    (
+        "system",
+        &[
+            "/api/capabilities",
+            "/api/system/stats",
+            "/api/system/audit",
+            "/api/metrics",
+        ],
+    ),
+    (
+        "token_counting",
+        &["/api/tokens/count", "/api/tokens/estimate-fit"],
+    ),
+    (
+        "tool_registry",
+        &[
+            "/api/tools",
+            "/api/tools/{name}",
+            "/api/tools/approvals",
+            "/api/tools/approvals/{id}/approve",
+        ],
+    ),
+    ("usage", &["/api/usage/summary", "/api/usage/quota"]),
+    (
+        "vector_store",
+        &[
+            "/api/vectors/collections",
+            "/api/vectors/collections/{name}",
+            "/api/vectors/collections/{name}/upsert",
+            "/api/vectors/collections/{name}/query",
+            "/api/vectors/collections/{name}/items/{id}",
+            "/api/vectors/collections/{name}/invalidate-stale",
+        ],
+    ),
+];
+
+/// Events pushed to clients: `(channel, event name)`.
+const EVENTS: &[(&str, &str)] = &[
+    ("swarm", "quota-warning"),
+    ("swarm", "tool-approval"),
+    ("regenerate_stream", "started"),
+    ("regenerate_stream", "alternative"),
+    ("regenerate_stream", "failed"),
+    ("regenerate_stream", "done"),
+];
+
+const CODE_INDEX_LANGUAGES: &[&str] = &["rust", "typescript", "javascript", "python", "go"];
+
+/// GET /api/capabilities — Version, enabled features and API groups.
+pub async fn capabilities(State(state): State<AppState>) -> Json<Value> {
+    let vertex = crate::vertex::config().is_some();
+    let mcp_tools = state
+        .mcp_client
+        .build_gemini_tool_declarations()
+        .await
+        .len();
+    let api: Vec<Value> = API_GROUPS
+        .iter()
+        .map(|(feature, routes)| json!({ "feature": feature, "routes": routes }))
+        .collect();
+    let events: Vec<Value> = EVENTS
+        .iter()
+        .map(|(channel, name)| json!({ "channel": channel, "name": name }))
+        .collect();
+
+    Json(json!({
+        "name": "GeminiHydra",
+        "version": env!("CARGO_PKG_VERSION"),
+        "api_version": API_VERSION,
+        "features": {
+            "gemini": vertex || crate::gemini_api::api_key().is_some(),
+            "vertex": vertex,
+            "offline": crate::offline::is_offline(),
+            "quota_limits": crate::quota::QuotaLimits::from_env() != Default::default(),
+            "safety_overrides": crate::gemini_api::safety_settings().is_some(),
+            "mcp_tools": mcp_tools,
+            "code_index_languages": CODE_INDEX_LANGUAGES,
+        },
+        "api": api,
+        "events": events,
+        "streaming": {
+            "websocket": "/ws/execute",
+            "sse": "/api/v1/swarm/stream",
+        },
+    }))
+}
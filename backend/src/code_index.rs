@@ -0,0 +1,598 @@
+// ---------------------------------------------------------------------------
+// code_index.rs — Symbol index of a workspace for "where is X defined?"
+//
+// Walks a workspace, parses Rust, TypeScript/JavaScript, Python and Go files
+// with tree-sitter and records every definition (functions, types, traits,
+// classes, methods, ...) with its file and line span. Indexes are cached per
+// workspace root (at most MAX_CACHED_INDEXES, oldest evicted first) and
+// rebuilt when older than INDEX_TTL; concurrent requests for the same root
+// share one build. Exposed to agents as the app-local `code_lookup` tool and
+// to the UI under /api/code-index.
+//
+// Lookups rank definitions by name against the identifiers in the query.
+// Nothing is embedded, so a question that names no symbol ("where do we
+// parse hunks?") finds nothing; `search_files` covers searching by content.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use streaming_iterator::StreamingIterator;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+/// Indexes keyed by canonical workspace root, plus one build lock per root
+/// so a burst of lookups triggers a single rebuild.
+#[derive(Default)]
+pub struct CodeIndexState {
+    indexes: RwLock<HashMap<PathBuf, Arc<CodeIndex>>>,
+    builds: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+pub type CodeIndexCache = Arc<CodeIndexState>;
+
+pub const TOOL_NAME: &str = "code_lookup";
+
+const INDEX_TTL: Duration = Duration::from_secs(600);
+const MAX_CACHED_INDEXES: usize = 8;
+const MAX_FILES: usize = 5000;
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+const MAX_SIGNATURE_CHARS: usize = 160;
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+const SKIP_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "__pycache__",
+    "vendor",
+];
+
+const RUST_QUERY: &str = r#"
+(function_item name: (identifier) @name) @definition.function
+(struct_item name: (type_identifier) @name) @definition.struct
+(enum_item name: (type_identifier) @name) @definition.enum
+(trait_item name: (type_identifier) @name) @definition.trait
+(type_item name: (type_identifier) @name) @definition.type
+(const_item name: (identifier) @name) @definition.const
+(static_item name: (identifier) @name) @definition.static
+(mod_item name: (identifier) @name) @definition.module
+(macro_definition name: (identifier) @name) @definition.macro
+"#;
+
+const PYTHON_QUERY: &str = r#"
+(function_definition name: (identifier) @name) @definition.function
+(class_definition name: (identifier) @name) @definition.class
+"#;
+
+const GO_QUERY: &str = r#"
+(function_declaration name: (identifier) @name) @definition.function
+(method_declaration name: (field_identifier) @name) @definition.method
+(type_spec name: (type_identifier) @name) @definition.type
+"#;
+
+const JS_QUERY: &str = r#"
+(function_declaration name: (_) @name) @definition.function
+(class_declaration name: (_) @name) @definition.class
+(method_definition name: (_) @name) @definition.method
+(variable_declarator name: (identifier) @name value: (arrow_function)) @definition.function
+"#;
+
+const TS_EXTRA_QUERY: &str = r#"
+(interface_declaration name: (_) @name) @definition.interface
+(type_alias_declaration name: (_) @name) @definition.type
+(enum_declaration name: (_) @name) @definition.enum
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Lang {
+    Rust,
+    TypeScript,
+    Tsx,
+    JavaScript,
+    Python,
+    Go,
+}
+
+impl Lang {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "py" => Some(Self::Python),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn language(self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    fn query_source(self) -> String {
+        match self {
+            Self::Rust => RUST_QUERY.to_string(),
+            Self::TypeScript | Self::Tsx => format!("{}{}", JS_QUERY, TS_EXTRA_QUERY),
+            Self::JavaScript => JS_QUERY.to_string(),
+            Self::Python => PYTHON_QUERY.to_string(),
+            Self::Go => GO_QUERY.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    /// Path relative to the workspace root, `/`-separated.
+    pub path: String,
+    pub line: usize,
+    pub end_line: usize,
+    pub signature: String,
+}
+
+#[derive(Debug)]
+pub struct CodeIndex {
+    pub root: PathBuf,
+    pub symbols: Vec<Symbol>,
+    pub files_indexed: usize,
+    pub truncated: bool,
+    built_at: Instant,
+}
+
+/// Parsers and compiled queries, created once per index build.
+struct Extractor {
+    parsers: HashMap<Lang, (tree_sitter::Parser, tree_sitter::Query)>,
+}
+
+impl Extractor {
+    fn new() -> Self {
+        Self {
+            parsers: HashMap::new(),
+        }
+    }
+
+    fn extract(&mut self, lang: Lang, rel_path: &str, source: &str) -> Vec<Symbol> {
+        if let std::collections::hash_map::Entry::Vacant(slot) = self.parsers.entry(lang) {
+            let language = lang.language();
+            let mut parser = tree_sitter::Parser::new();
+            if let Err(e) = parser.set_language(&language) {
+                tracing::warn!("code_index: cannot load {:?} grammar: {}", lang, e);
+                return Vec::new();
+            }
+            match tree_sitter::Query::new(&language, &lang.query_source()) {
+                Ok(query) => {
+                    slot.insert((parser, query));
+                }
+                Err(e) => {
+                    tracing::warn!("code_index: invalid {:?} query: {}", lang, e);
+                    return Vec::new();
+                }
+            }
+        }
+        let Some((parser, query)) = self.parsers.get_mut(&lang) else {
+            return Vec::new();
+        };
+        let Some(tree) = parser.parse(source, None) else {
+            return Vec::new();
+        };
+
+        let names = query.capture_names();
+        let lines: Vec<&str> = source.lines().collect();
+        let mut symbols = Vec::new();
+        let mut cursor = tree_sitter::QueryCursor::new();
+        let mut matches = cursor.matches(query, tree.root_node(), source.as_bytes());
+        while let Some(m) = matches.next() {
+            let mut name = None;
+            let mut def = None;
+            for cap in m.captures {
+                let capture = names[cap.index as usize];
+                if capture == "name" {
+                    name = cap.node.utf8_text(source.as_bytes()).ok();
+                } else if let Some(kind) = capture.strip_prefix("definition.") {
+                    def = Some((kind, cap.node));
+                }
+            }
+            let (Some(name), Some((kind, node))) = (name, def) else {
+                continue;
+            };
+            let start = node.start_position().row;
+            let signature: String = lines
+                .get(start)
+                .copied()
+                .unwrap_or_default()
+                .trim()
+                .chars()
+                .take(MAX_SIGNATURE_CHARS)
+                .collect();
+            symbols.push(Symbol {
+                name: name.to_string(),
+                kind: kind.to_string(),
+                path: rel_path.to_string(),
+                line: start + 1,
+                end_line: node.end_position().row + 1,
+                signature,
+            });
+        }
+        symbols
+    }
+}
+
+/// Collect indexable files below `root` (hidden and build directories skipped).
+fn collect_files(root: &Path) -> (Vec<(PathBuf, Lang)>, bool) {
+    let mut files = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                if !SKIP_DIRS.contains(&name.as_str()) {
+                    stack.push(path);
+                }
+            } else if file_type.is_file()
+                && let Some(lang) = Lang::from_path(&path)
+                && entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES)
+            {
+                if files.len() >= MAX_FILES {
+                    return (files, true);
+                }
+                files.push((path, lang));
+            }
+        }
+    }
+    (files, false)
+}
+
+fn build_index(root: PathBuf) -> CodeIndex {
+    let (files, truncated) = collect_files(&root);
+    let mut extractor = Extractor::new();
+    let mut symbols = Vec::new();
+    for (path, lang) in &files {
+        let Ok(source) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let rel = path
+            .strip_prefix(&root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        symbols.extend(extractor.extract(*lang, &rel, &source));
+    }
+    CodeIndex {
+        root,
+        symbols,
+        files_indexed: files.len(),
+        truncated,
+        built_at: Instant::now(),
+    }
+}
+
+impl CodeIndexState {
+    async fn fresh(&self, root: &Path) -> Option<Arc<CodeIndex>> {
+        self.indexes
+            .read()
+            .await
+            .get(root)
+            .filter(|index| index.built_at.elapsed() < INDEX_TTL)
+            .cloned()
+    }
+
+    fn build_lock(&self, root: &Path) -> Arc<Mutex<()>> {
+        let mut builds = self.builds.lock().unwrap_or_else(|e| e.into_inner());
+        builds.entry(root.to_path_buf()).or_default().clone()
+    }
+
+    /// Store `index`, evicting the oldest entries beyond MAX_CACHED_INDEXES.
+    async fn insert(&self, index: Arc<CodeIndex>) {
+        let mut indexes = self.indexes.write().await;
+        indexes.insert(index.root.clone(), index);
+        while indexes.len() > MAX_CACHED_INDEXES {
+            let Some(oldest) = indexes
+                .iter()
+                .min_by_key(|(_, index)| index.built_at)
+                .map(|(root, _)| root.clone())
+            else {
+                break;
+            };
+            indexes.remove(&oldest);
+        }
+        // Build locks of evicted roots that nobody is waiting on are dropped too.
+        self.builds
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|root, lock| indexes.contains_key(root) || Arc::strong_count(lock) > 1);
+    }
+}
+
+/// Cached index for `root`, rebuilt when missing, stale or `force`d.
+pub async fn index_for(
+    state: &AppState,
+    root: &str,
+    force: bool,
+) -> Result<Arc<CodeIndex>, String> {
+    let root = tokio::fs::canonicalize(root)
+        .await
+        .map_err(|e| format!("cannot open workspace '{}': {}", root, e))?;
+    let cache = &state.code_indexes;
+    if !force && let Some(index) = cache.fresh(&root).await {
+        return Ok(index);
+    }
+
+    let requested = Instant::now();
+    let lock = cache.build_lock(&root);
+    let _build = lock.lock().await;
+    // Whoever held the lock may have just built what we need.
+    if let Some(index) = cache.fresh(&root).await
+        && (!force || index.built_at >= requested)
+    {
+        return Ok(index);
+    }
+
+    let started = Instant::now();
+    let build_root = root.clone();
+    let index = tokio::task::spawn_blocking(move || build_index(build_root))
+        .await
+        .map_err(|e| format!("indexing failed: {}", e))?;
+    tracing::info!(
+        root = %root.display(),
+        files = index.files_indexed,
+        symbols = index.symbols.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "code_index: workspace indexed"
+    );
+    let index = Arc::new(index);
+    cache.insert(index.clone()).await;
+    Ok(index)
+}
+
+/// Identifier-like tokens of a query (a symbol name, or a sentence naming one).
+fn query_terms(query: &str) -> Vec<&str> {
+    query
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| t.len() >= 2)
+        .collect()
+}
+
+fn score(symbol: &Symbol, terms: &[&str]) -> u32 {
+    let name_lower = symbol.name.to_lowercase();
+    terms
+        .iter()
+        .map(|term| {
+            let term_lower = term.to_lowercase();
+            if symbol.name == *term {
+                100
+            } else if name_lower == term_lower {
+                80
+            } else if term_lower.len() >= 3 && name_lower.starts_with(&term_lower) {
+                40
+            } else if term_lower.len() >= 3 && name_lower.contains(&term_lower) {
+                20
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+/// Best-matching symbols, highest score first (ties: shorter name, then path).
+pub fn lookup<'a>(index: &'a CodeIndex, query: &str, limit: usize) -> Vec<&'a Symbol> {
+    let terms = query_terms(query);
+    let mut scored: Vec<(u32, &Symbol)> = index
+        .symbols
+        .iter()
+        .map(|s| (score(s, &terms), s))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(a.1.name.len().cmp(&b.1.name.len()))
+            .then(a.1.path.cmp(&b.1.path))
+            .then(a.1.line.cmp(&b.1.line))
+    });
+    scored.into_iter().take(limit).map(|(_, s)| s).collect()
+}
+
+fn format_matches(matches: &[&Symbol]) -> String {
+    if matches.is_empty() {
+        return "No matching definitions found in the code index.".to_string();
+    }
+    matches
+        .iter()
+        .map(|s| {
+            format!(
+                "{} {} — {}:{}\n    {}",
+                s.kind, s.name, s.path, s.line, s.signature
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Gemini function declaration for the `code_lookup` tool.
+pub fn tool_declaration() -> Value {
+    json!({
+        "name": TOOL_NAME,
+        "description": "Find where a named function, type, class or other symbol is defined in the current workspace using a prebuilt tree-sitter index. Faster than searching files. Pass a symbol name or part of one (e.g. 'AppState', 'parse_patch'); only names are matched, so use search_files to find code by what it does. Returns kind, name, file:line and signature.",
+        "parameters": {
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Symbol name or part of one" },
+                "limit": { "type": "integer", "description": "Maximum results (default 20)" }
+            },
+            "required": ["query"]
+        }
+    })
+}
+
+/// Execute `code_lookup` for the agent loop; the active project wins over `working_directory`.
+pub async fn execute_lookup(
+    state: &AppState,
+    args: &Value,
+    working_directory: &str,
+) -> Result<String, String> {
+    let query = args
+        .get("query")
+        .and_then(|q| q.as_str())
+        .filter(|q| !q.trim().is_empty())
+        .ok_or("Missing required argument: query")?;
+    let limit = args
+        .get("limit")
+        .and_then(|l| l.as_u64())
+        .map_or(DEFAULT_LIMIT, |l| (l as usize).clamp(1, MAX_LIMIT));
+    let root = match crate::projects::active_project(&state.db).await {
+        Some(project) => project.root_path,
+        None if !working_directory.is_empty() => working_directory.to_string(),
+        None => return Err("No active project or working directory to index".to_string()),
+    };
+    let index = index_for(state, &root, false).await?;
+    Ok(format_matches(&lookup(&index, query, limit)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LookupParams {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+async fn project_root(state: &AppState) -> Result<String, (StatusCode, Json<Value>)> {
+    crate::projects::active_project(&state.db)
+        .await
+        .map(|p| p.root_path)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::CONFLICT,
+                "no active project — activate one first",
+            )
+        })
+}
+
+fn index_error(e: String) -> (StatusCode, Json<Value>) {
+    api_error(StatusCode::UNPROCESSABLE_ENTITY, e)
+}
+
+/// POST /api/code-index/rebuild — Re-index the active project now.
+pub async fn rebuild_index(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let root = project_root(&state).await?;
+    let index = index_for(&state, &root, true).await.map_err(index_error)?;
+    Ok(Json(json!({
+        "root": index.root.display().to_string(),
+        "files_indexed": index.files_indexed,
+        "symbols": index.symbols.len(),
+        "truncated": index.truncated,
+    })))
+}
+
+/// GET /api/code-index/lookup?q=AppState — Definitions whose names match `q`.
+pub async fn lookup_symbols(
+    State(state): State<AppState>,
+    Query(params): Query<LookupParams>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let root = project_root(&state).await?;
+    let index = index_for(&state, &root, false).await.map_err(index_error)?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    Ok(Json(json!({
+        "query": params.q,
+        "matches": lookup(&index, &params.q, limit),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_of(lang: Lang, path: &str, source: &str) -> CodeIndex {
+        CodeIndex {
+            root: PathBuf::from("/ws"),
+            symbols: Extractor::new().extract(lang, path, source),
+            files_indexed: 1,
+            truncated: false,
+            built_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn rust_definitions_are_extracted() {
+        let src = "pub struct AppState {\n    db: Pool,\n}\n\nimpl AppState {\n    pub fn new() -> Self {\n        todo!()\n    }\n}\n\nenum Mode { A }\n";
+        let index = index_of(Lang::Rust, "src/state.rs", src);
+        let kinds: Vec<(&str, &str, usize)> = index
+            .symbols
+            .iter()
+            .map(|s| (s.kind.as_str(), s.name.as_str(), s.line))
+            .collect();
+        assert!(kinds.contains(&("struct", "AppState", 1)));
+        assert!(kinds.contains(&("function", "new", 6)));
+        assert!(kinds.contains(&("enum", "Mode", 11)));
+    }
+
+    #[tokio::test]
+    async fn oldest_indexes_are_evicted() {
+        let cache = CodeIndexState::default();
+        for i in 0..=MAX_CACHED_INDEXES {
+            let mut index = index_of(Lang::Rust, "lib.rs", "fn f() {}");
+            index.root = PathBuf::from(format!("/ws{}", i));
+            cache.insert(Arc::new(index)).await;
+        }
+        let indexes = cache.indexes.read().await;
+        assert_eq!(indexes.len(), MAX_CACHED_INDEXES);
+        assert!(!indexes.contains_key(Path::new("/ws0")));
+        assert!(indexes.contains_key(&PathBuf::from(format!("/ws{}", MAX_CACHED_INDEXES))));
+    }
+
+    #[test]
+    fn python_definitions_are_extracted() {
+        let src = "class Agent:\n    def run(self):\n        pass\n";
+        let index = index_of(Lang::Python, "agent.py", src);
+        assert_eq!(index.symbols.len(), 2);
+        assert_eq!(index.symbols[0].signature, "class Agent:");
+    }
+
+    #[test]
+    fn lookup_ranks_exact_matches_first() {
+        let src = "struct AppState;\nstruct AppStateBuilder;\nfn app_state_from_env() {}\n";
+        let index = index_of(Lang::Rust, "src/lib.rs", src);
+        let hits = lookup(&index, "where is AppState defined?", 10);
+        assert_eq!(hits[0].name, "AppState");
+        assert_eq!(hits[1].name, "AppStateBuilder");
+        assert!(lookup(&index, "zzz", 10).is_empty());
+    }
+
+    #[test]
+    fn languages_by_extension() {
+        assert_eq!(Lang::from_path(Path::new("a/b.tsx")), Some(Lang::Tsx));
+        assert_eq!(Lang::from_path(Path::new("main.go")), Some(Lang::Go));
+        assert_eq!(Lang::from_path(Path::new("README.md")), None);
+    }
+}
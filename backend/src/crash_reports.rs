@@ -0,0 +1,220 @@
+// ---------------------------------------------------------------------------
+// crash_reports.rs — Local panic reports for later triage
+//
+// `install()` chains a panic hook that writes one JSON report per panic
+// (message, location, thread, backtrace, OS, version, uptime and the last
+// REPORT_LOG_LINES redacted log lines) to
+// `CRASH_REPORTS_DIR` (default `crashes/` in the data directory). Reports
+// stay on disk: they are only listed and served through the admin API and
+// summarised in the support bundle. Nothing is uploaded automatically.
+// ---------------------------------------------------------------------------
+
+use std::backtrace::Backtrace;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use std::time::Instant;
+
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::LogRingBuffer;
+
+const MAX_LISTED: usize = 100;
+const REPORT_LOG_LINES: usize = 200;
+
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+static FILE_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^crash-\d{8}-\d{6}-[0-9a-f]{8}\.json$").expect("valid regex"));
+
+fn crash_dir() -> PathBuf {
+    crate::data_dir::subdir("CRASH_REPORTS_DIR", "crashes")
+}
+
+fn panic_message(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+/// The most recent log entries, oldest first, with secrets masked.
+fn recent_logs(log_buffer: &LogRingBuffer) -> Vec<Value> {
+    log_buffer
+        .recent(REPORT_LOG_LINES, None, None)
+        .into_iter()
+        .filter_map(|entry| {
+            let mut line = serde_json::to_value(entry).ok()?;
+            crate::support_bundle::redact(&mut line);
+            Some(line)
+        })
+        .collect()
+}
+
+fn build_report(
+    message: &str,
+    location: Option<String>,
+    backtrace: String,
+    logs: Vec<Value>,
+) -> Value {
+    json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "app": { "name": "GeminiHydra", "version": env!("CARGO_PKG_VERSION") },
+        "message": message,
+        "location": location,
+        "thread": std::thread::current().name().unwrap_or("<unnamed>"),
+        "uptime_seconds": STARTED.elapsed().as_secs(),
+        "os": { "family": std::env::consts::OS, "arch": std::env::consts::ARCH },
+        "backtrace": backtrace,
+        "logs": logs,
+    })
+}
+
+fn write_report(report: &Value) -> std::io::Result<PathBuf> {
+    let dir = crash_dir();
+    std::fs::create_dir_all(&dir)?;
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let path = dir.join(format!(
+        "crash-{}-{}.json",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        &id[..8]
+    ));
+    let body = serde_json::to_vec_pretty(report).unwrap_or_default();
+    std::fs::write(&path, body)?;
+    Ok(path)
+}
+
+/// Install the crash-report panic hook in front of the existing one.
+pub fn install(log_buffer: Arc<LogRingBuffer>) {
+    LazyLock::force(&STARTED);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = build_report(
+            &panic_message(info),
+            info.location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            Backtrace::force_capture().to_string(),
+            recent_logs(&log_buffer),
+        );
+        match write_report(&report) {
+            Ok(path) => tracing::error!("panic captured — crash report at {}", path.display()),
+            Err(e) => tracing::error!(
+                "panic captured but crash report could not be written: {}",
+                e
+            ),
+        }
+        previous(info);
+    }));
+}
+
+/// Number of stored reports (used by the metrics endpoint).
+pub async fn count() -> usize {
+    let Ok(mut entries) = tokio::fs::read_dir(crash_dir()).await else {
+        return 0;
+    };
+    let mut n = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if FILE_NAME_RE.is_match(&entry.file_name().to_string_lossy()) {
+            n += 1;
+        }
+    }
+    n
+}
+
+/// Newest-first summaries of stored reports.
+pub async fn summaries() -> Vec<Value> {
+    let Ok(mut entries) = tokio::fs::read_dir(crash_dir()).await else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if FILE_NAME_RE.is_match(&name) {
+            files.push((name, entry.path()));
+        }
+    }
+    // Names embed the UTC timestamp, so lexical order is chronological.
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    files.truncate(MAX_LISTED);
+
+    let mut out = Vec::with_capacity(files.len());
+    for (name, path) in files {
+        let report: Value = tokio::fs::read(&path)
+            .await
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .unwrap_or(Value::Null);
+        out.push(json!({
+            "file": name,
+            "timestamp": report.get("timestamp"),
+            "version": report.pointer("/app/version"),
+            "message": report.get("message"),
+            "location": report.get("location"),
+        }));
+    }
+    out
+}
+
+/// GET /api/admin/crash-reports — List stored crash reports.
+pub async fn list_crash_reports() -> Json<Value> {
+    Json(json!({ "reports": summaries().await }))
+}
+
+/// GET /api/admin/crash-reports/{file} — Full report including backtrace and logs.
+pub async fn get_crash_report(
+    Path(file): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if !FILE_NAME_RE.is_match(&file) {
+        return Err(api_error(StatusCode::BAD_REQUEST, "invalid report name"));
+    }
+    let bytes = tokio::fs::read(crash_dir().join(&file))
+        .await
+        .map_err(|_| api_error(StatusCode::NOT_FOUND, "report not found"))?;
+    serde_json::from_slice(&bytes)
+        .map(Json)
+        .map_err(|_| api_error(StatusCode::UNPROCESSABLE_ENTITY, "report is not valid JSON"))
+}
+
+/// DELETE /api/admin/crash-reports/{file} — Remove a reviewed report.
+pub async fn delete_crash_report(Path(file): Path<String>) -> StatusCode {
+    if !FILE_NAME_RE.is_match(&file) {
+        return StatusCode::BAD_REQUEST;
+    }
+    match tokio::fs::remove_file(crash_dir().join(&file)).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_has_triage_fields() {
+        let report = build_report(
+            "boom",
+            Some("src/lib.rs:1:1".to_string()),
+            "bt".to_string(),
+            vec![json!({ "message": "before" })],
+        );
+        assert_eq!(report["message"], "boom");
+        assert_eq!(report["location"], "src/lib.rs:1:1");
+        assert_eq!(report["os"]["family"], std::env::consts::OS);
+        assert!(report["timestamp"].is_string());
+        assert_eq!(report["logs"][0]["message"], "before");
+    }
+
+    #[test]
+    fn report_names_are_restricted() {
+        assert!(FILE_NAME_RE.is_match("crash-20261014-120000-0f8fad5b.json"));
+        assert!(!FILE_NAME_RE.is_match("../crash-20261014-120000-0f8fad5b.json"));
+        assert!(!FILE_NAME_RE.is_match("crash-20261014-120000-0f8fad5b.txt"));
+    }
+}
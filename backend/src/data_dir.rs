@@ -0,0 +1,123 @@
+// ---------------------------------------------------------------------------
+// data_dir.rs — Location of the backend's on-disk files
+//
+// Generated images and crash reports live below one data directory:
+//   1. `HYDRA_DATA_DIR`, when set;
+//   2. the OS application-data folder when `HYDRA_DATA_MODE=installed`
+//      (`%LOCALAPPDATA%\GeminiHydra`, `~/Library/Application Support/GeminiHydra`,
+//      `$XDG_DATA_HOME/geminihydra` or `~/.local/share/geminihydra`);
+//   3. `data/` next to the executable (portable mode, the default), so the
+//      location does not depend on where the binary was started from.
+// Per-feature overrides such as `GENERATED_IMAGES_DIR` still win.
+// ---------------------------------------------------------------------------
+
+use std::path::{Path, PathBuf};
+
+const PORTABLE_DIR: &str = "data";
+
+fn app_data_dir(get: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return get("LOCALAPPDATA").map(|d| PathBuf::from(d).join("GeminiHydra"));
+    }
+    if cfg!(target_os = "macos") {
+        return get("HOME").map(|h| {
+            PathBuf::from(h)
+                .join("Library")
+                .join("Application Support")
+                .join("GeminiHydra")
+        });
+    }
+    get("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| get("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+        .map(|d| d.join("geminihydra"))
+}
+
+/// Directory of the running executable (`None` if it cannot be determined).
+fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+}
+
+fn resolve_with(get: impl Fn(&str) -> Option<String>, exe_dir: Option<&Path>) -> PathBuf {
+    if let Some(dir) = get("HYDRA_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    let installed = get("HYDRA_DATA_MODE").is_some_and(|m| m.eq_ignore_ascii_case("installed"));
+    if installed && let Some(dir) = app_data_dir(&get) {
+        return dir;
+    }
+    match exe_dir {
+        Some(dir) => dir.join(PORTABLE_DIR),
+        None => PathBuf::from(PORTABLE_DIR),
+    }
+}
+
+/// Base data directory (see module docs for resolution order).
+pub fn base() -> PathBuf {
+    resolve_with(
+        |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty()),
+        exe_dir().as_deref(),
+    )
+}
+
+/// `name` below the base data directory, unless `override_var` points elsewhere.
+pub fn subdir(override_var: &str, name: &str) -> PathBuf {
+    std::env::var(override_var)
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| base().join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn explicit_dir_wins() {
+        let get = env(&[
+            ("HYDRA_DATA_DIR", "/srv/hydra"),
+            ("HYDRA_DATA_MODE", "installed"),
+        ]);
+        assert_eq!(
+            resolve_with(get, Some(Path::new("/opt/hydra"))),
+            PathBuf::from("/srv/hydra")
+        );
+    }
+
+    #[test]
+    fn portable_is_next_to_the_executable() {
+        let exe = Path::new("/opt/hydra");
+        assert_eq!(resolve_with(env(&[]), Some(exe)), exe.join("data"));
+        let get = env(&[("HYDRA_DATA_MODE", "portable"), ("HOME", "/home/u")]);
+        assert_eq!(resolve_with(get, Some(exe)), exe.join("data"));
+        assert_eq!(resolve_with(env(&[]), None), PathBuf::from("data"));
+    }
+
+    #[test]
+    fn installed_uses_app_data() {
+        let get = env(&[
+            ("HYDRA_DATA_MODE", "installed"),
+            ("HOME", "/home/u"),
+            ("LOCALAPPDATA", "C:\\Users\\u\\AppData\\Local"),
+        ]);
+        let exe = Path::new("/opt/hydra");
+        let dir = resolve_with(get, Some(exe));
+        assert_ne!(dir, exe.join("data"));
+        assert!(
+            dir.to_string_lossy()
+                .to_lowercase()
+                .ends_with("geminihydra")
+        );
+    }
+}
@@ -0,0 +1,377 @@
+// ---------------------------------------------------------------------------
+// gemini_api.rs — Google Generative Language API endpoint resolution
+//
+// All app-local Gemini calls build their URLs through here so that tests and
+// local simulators can redirect them with `GEMINI_API_BASE_URL`
+// (same convention as `GITHUB_API_BASE_URL` / `VERCEL_API_BASE_URL`).
+//
+// Configured `safetySettings` go into the bodies built here and by the
+// `/api/execute` handler; WebSocket streaming bodies are built by the shared
+// crate and keep Google's defaults.
+// ---------------------------------------------------------------------------
+
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::state::AppState;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+const GENERATE_TIMEOUT: Duration = Duration::from_secs(60);
+const META_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Base URL of the Generative Language API, without trailing slash.
+pub fn api_base() -> String {
+    std::env::var("GEMINI_API_BASE_URL")
+        .ok()
+        .filter(|u| !u.is_empty())
+        .map(|u| u.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+}
+
+/// `{base}/v1beta/models`
+pub fn models_url() -> String {
+    format!("{}/v1beta/models", api_base())
+}
+
+/// Model listing: `{base}/v1beta/models`, or the Vertex publisher-model
+/// listing when Vertex is configured.
+pub fn list_models_url() -> String {
+    match crate::vertex::config() {
+        Some(cfg) => crate::vertex::models_url(&cfg),
+        None => models_url(),
+    }
+}
+
+/// `{base}/v1beta/models/{model}`, or the Vertex publisher-model URL.
+pub fn model_url(model: &str) -> String {
+    match crate::vertex::config() {
+        Some(cfg) => crate::vertex::model_url(&cfg, model),
+        None => format!("{}/{}", models_url(), model),
+    }
+}
+
+/// `{base}/v1beta/models/{model}:{method}` (e.g. `generateContent`), or the
+/// Vertex AI publisher-model URL when Vertex is configured.
+pub fn model_method_url(model: &str, method: &str) -> String {
+    match crate::vertex::config() {
+        Some(cfg) => crate::vertex::model_method_url(&cfg, model, method),
+        None => format!("{}/v1beta/models/{}:{}", api_base(), model, method),
+    }
+}
+
+/// Credentials may only travel over HTTPS — plain HTTP is accepted for
+/// loopback hosts only (mock servers in tests, local simulators).
+pub fn is_allowed_url(url: &reqwest::Url) -> bool {
+    match url.scheme() {
+        "https" => true,
+        "http" => matches!(
+            url.host_str(),
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+        ),
+        _ => false,
+    }
+}
+
+/// Attach credentials: a Vertex OAuth2 bearer token when Vertex is
+/// configured, otherwise the API key header.
+pub async fn authorize(
+    state: &AppState,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::RequestBuilder, String> {
+    crate::offline::ensure_online().map_err(|e| e.to_string())?;
+    match crate::vertex::config() {
+        Some(cfg) => {
+            let token = crate::vertex::access_token(state, &cfg).await?;
+            Ok(request.bearer_auth(token))
+        }
+        None => {
+            let key = api_key().ok_or("no Google API credential configured")?;
+            Ok(request.header("x-goog-api-key", key))
+        }
+    }
+}
+
+/// Google API key from the environment (B13: Vault sets these), if configured.
+pub fn api_key() -> Option<String> {
+    std::env::var("GOOGLE_API_KEY")
+        .or_else(|_| std::env::var("GEMINI_API_KEY"))
+        .ok()
+        .filter(|k| !k.is_empty())
+}
+
+const HARM_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+/// Finish reasons that mean the candidate was filtered rather than completed.
+const BLOCKED_FINISH_REASONS: [&str; 5] = [
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+
+/// `safetySettings` for request bodies. `GEMINI_SAFETY_SETTINGS` takes a raw
+/// JSON array; otherwise `GEMINI_SAFETY_THRESHOLD` (e.g. `BLOCK_ONLY_HIGH`)
+/// is applied to every harm category. `None` keeps Google's defaults.
+pub fn safety_settings() -> Option<Value> {
+    if let Some(raw) = std::env::var("GEMINI_SAFETY_SETTINGS")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+    {
+        match serde_json::from_str::<Value>(&raw) {
+            Ok(v) if v.is_array() => return Some(v),
+            _ => tracing::warn!("gemini_api: GEMINI_SAFETY_SETTINGS is not a JSON array, ignored"),
+        }
+    }
+    let threshold = std::env::var("GEMINI_SAFETY_THRESHOLD")
+        .ok()
+        .map(|t| t.trim().to_ascii_uppercase())
+        .filter(|t| !t.is_empty())?;
+    Some(Value::Array(
+        HARM_CATEGORIES
+            .iter()
+            .map(|category| json!({ "category": category, "threshold": threshold }))
+            .collect(),
+    ))
+}
+
+/// Add the configured `safetySettings` to a request body (no-op when unset).
+pub fn apply_safety_settings(body: &mut Value) {
+    if let Some(settings) = safety_settings() {
+        body["safetySettings"] = settings;
+    }
+}
+
+/// Why a `generateContent` response was filtered: `promptFeedback.blockReason`,
+/// or a filtering `finishReason` of the first candidate.
+pub fn block_reason(body: &Value) -> Option<String> {
+    body.pointer("/promptFeedback/blockReason")
+        .or_else(|| {
+            body.pointer("/candidates/0/finishReason").filter(|r| {
+                r.as_str()
+                    .is_some_and(|r| BLOCKED_FINISH_REASONS.contains(&r))
+            })
+        })
+        .and_then(|r| r.as_str())
+        .map(str::to_string)
+}
+
+/// Extract the concatenated text parts of the first candidate.
+pub fn response_text(body: &Value) -> Option<String> {
+    let parts = body.pointer("/candidates/0/content/parts")?.as_array()?;
+    let text: String = parts
+        .iter()
+        .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// One-shot `generateContent` call for background jobs (briefs, summaries, extraction).
+/// `json_output` sets `responseMimeType: application/json`.
+pub async fn generate_content(
+    state: &AppState,
+    prompt: &str,
+    temperature: f64,
+    json_output: bool,
+) -> Result<String, String> {
+    let mut generation_config = json!({ "temperature": temperature, "maxOutputTokens": 8192 });
+    if json_output {
+        generation_config["responseMimeType"] = json!("application/json");
+    }
+    let body = json!({
+        "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        "generationConfig": generation_config,
+    });
+    generate(state, body).await
+}
+
+/// Multi-turn `generateContent` call: `contents` are already in Gemini
+/// `{role, parts}` form. `seed` makes sampling reproducible per alternative.
+pub async fn generate_contents(
+    state: &AppState,
+    contents: &[Value],
+    temperature: f64,
+    seed: Option<i64>,
+) -> Result<String, String> {
+    let mut generation_config = json!({ "temperature": temperature, "maxOutputTokens": 8192 });
+    if let Some(seed) = seed {
+        generation_config["seed"] = json!(seed);
+    }
+    let body = json!({
+        "contents": contents,
+        "generationConfig": generation_config,
+    });
+    generate(state, body).await
+}
+
+async fn generate(state: &AppState, body: Value) -> Result<String, String> {
+    let model = crate::model_registry::get_model_id(state, "chat").await;
+    let json = generate_raw(state, &model, body, "background").await?;
+    response_text(&json).ok_or_else(|| "Gemini returned no text".to_string())
+}
+
+/// `generateContent` against `model`, returning the full response body.
+/// Applies safety settings and the quota guard, records usage under `kind`
+/// (the usage tier, e.g. `background` or `image`), and maps HTTP errors and
+/// filtered responses to `Err`. Credentials only go to allowed URLs.
+pub async fn generate_raw(
+    state: &AppState,
+    model: &str,
+    mut body: Value,
+    kind: &str,
+) -> Result<Value, String> {
+    apply_safety_settings(&mut body);
+    let url = reqwest::Url::parse(&model_method_url(model, "generateContent"))
+        .ok()
+        .filter(is_allowed_url)
+        .ok_or_else(|| "API credentials require HTTPS".to_string())?;
+    let request = authorize(state, state.client.post(url)).await?;
+    crate::quota::check(state).await?;
+
+    let started = std::time::Instant::now();
+    let res = tokio::time::timeout(GENERATE_TIMEOUT, request.json(&body).send())
+        .await
+        .map_err(|_| {
+            format!(
+                "Gemini request timed out after {}s",
+                GENERATE_TIMEOUT.as_secs()
+            )
+        })?
+        .map_err(|e| format!("Gemini request failed: {}", e))?;
+
+    let status = res.status();
+    let json: Value = res
+        .json()
+        .await
+        .map_err(|e| format!("invalid Gemini response: {}", e))?;
+    crate::usage::record_usage(
+        state,
+        model,
+        kind,
+        json.get("usageMetadata"),
+        started.elapsed().as_millis(),
+        status.is_success(),
+    )
+    .await;
+    if !status.is_success() {
+        return Err(format!("Gemini returned HTTP {}", status.as_u16()));
+    }
+    if let Some(reason) = response_meta(&json).block_reason {
+        return Err(format!("gemini-blocked: response filtered ({})", reason));
+    }
+    Ok(json)
+}
+
+/// `countTokens` for already-built `contents`.
+pub async fn count_tokens(
+    state: &AppState,
+    model: &str,
+    contents: &[Value],
+) -> Result<u64, String> {
+    let body = json!({ "contents": contents });
+    let json = get_or_post(state, model_method_url(model, "countTokens"), Some(&body)).await?;
+    json.get("totalTokens")
+        .and_then(|t| t.as_u64())
+        .ok_or_else(|| "Gemini returned no token count".to_string())
+}
+
+/// `inputTokenLimit` from the model's metadata (`GET .../models/{model}`).
+pub async fn input_token_limit(state: &AppState, model: &str) -> Result<u64, String> {
+    let json = get_or_post(state, model_url(model), None).await?;
+    json.get("inputTokenLimit")
+        .and_then(|t| t.as_u64())
+        .ok_or_else(|| format!("no inputTokenLimit reported for {}", model))
+}
+
+async fn get_or_post(state: &AppState, url: String, body: Option<&Value>) -> Result<Value, String> {
+    let request = match body {
+        Some(body) => state.client.post(url).json(body),
+        None => state.client.get(url),
+    };
+    let request = authorize(state, request).await?;
+    let res = tokio::time::timeout(META_TIMEOUT, request.send())
+        .await
+        .map_err(|_| format!("Gemini request timed out after {}s", META_TIMEOUT.as_secs()))?
+        .map_err(|e| format!("Gemini request failed: {}", e))?;
+    let status = res.status();
+    if !status.is_success() {
+        return Err(format!("Gemini returned HTTP {}", status.as_u16()));
+    }
+    res.json()
+        .await
+        .map_err(|e| format!("invalid Gemini response: {}", e))
+}
+
+/// Whether an error from the calls above is worth retrying later: timeouts,
+/// transport failures, HTTP 429/5xx, an exhausted quota or offline mode.
+pub fn is_transient_error(error: &str) -> bool {
+    let retryable_status = error
+        .strip_prefix("Gemini returned HTTP ")
+        .and_then(|code| code.parse::<u16>().ok())
+        .is_some_and(|code| code == 429 || code >= 500);
+    retryable_status
+        || error.starts_with("Gemini request timed out")
+        || error.starts_with("Gemini request failed")
+        || error.starts_with("quota exceeded")
+        || error == crate::offline::OfflineMode.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transient_errors_are_recognised() {
+        assert!(is_transient_error("Gemini returned HTTP 503"));
+        assert!(is_transient_error("Gemini returned HTTP 429"));
+        assert!(is_transient_error(
+            "quota exceeded: requests_per_minute (61.00/60.00)"
+        ));
+        assert!(!is_transient_error("Gemini returned HTTP 400"));
+        assert!(!is_transient_error("model returned invalid JSON: EOF"));
+    }
+
+    #[test]
+    fn response_text_joins_parts() {
+        let body = json!({ "candidates": [{ "content": { "parts": [{ "text": "a" }, { "text": "b" }] } }] });
+        assert_eq!(response_text(&body).as_deref(), Some("ab"));
+        assert_eq!(response_text(&json!({ "candidates": [] })), None);
+    }
+
+    #[test]
+    fn block_reason_covers_prompt_and_candidate_filters() {
+        let blocked = json!({ "promptFeedback": { "blockReason": "SAFETY" } });
+        assert_eq!(block_reason(&blocked).as_deref(), Some("SAFETY"));
+
+        let done = json!({ "candidates": [{
+            "finishReason": "STOP",
+            "content": { "parts": [{ "text": "x" }] }
+        }] });
+        assert_eq!(block_reason(&done), None);
+
+        let recited = json!({ "candidates": [{ "finishReason": "RECITATION" }] });
+        assert_eq!(block_reason(&recited).as_deref(), Some("RECITATION"));
+    }
+
+    #[test]
+    fn https_is_allowed() {
+        let url = reqwest::Url::parse("https://generativelanguage.googleapis.com/v1beta")
+            .expect("valid url");
+        assert!(is_allowed_url(&url));
+    }
+
+    #[test]
+    fn plain_http_only_for_loopback() {
+        let local = reqwest::Url::parse("http://127.0.0.1:4010/v1beta").expect("valid url");
+        let remote = reqwest::Url::parse("http://example.com/v1beta").expect("valid url");
+        assert!(is_allowed_url(&local));
+        assert!(!is_allowed_url(&remote));
+    }
+}
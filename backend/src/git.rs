@@ -0,0 +1,491 @@
+// ---------------------------------------------------------------------------
+// git.rs — Git endpoints scoped to the active project root
+//
+// Lets the UI review what agents changed and turn it into real commits:
+// status, diff, stage, commit and branch creation. The same operations are
+// registered as agent tools (`git_project_status`, `git_stage`, ...) so swarm
+// agents can produce reviewable commits; the tools act on the project of the
+// calling execution's working directory, not whichever project the UI has
+// active at the time. Everything shells out to the `git`
+// binary with `-C <project root>`; user-supplied paths always go after `--`
+// and are rejected when they escape the root or use pathspec magic. Pushing
+// is deliberately not offered (same policy as the shared git tools).
+// ---------------------------------------------------------------------------
+
+use std::path::{Component, Path as FsPath};
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+type GitError = (StatusCode, Json<Value>);
+type GitResult = Result<Json<Value>, GitError>;
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_DIFF_BYTES: usize = 512 * 1024;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StatusEntry {
+    pub path: String,
+    /// Index (staged) status letter from `git status --porcelain`, `' '` when unchanged.
+    pub index: char,
+    /// Work-tree (unstaged) status letter, `'?'` for untracked files.
+    pub worktree: char,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffParams {
+    #[serde(default)]
+    pub staged: bool,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StageRequest {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Stage every change (`git add --all`); `paths` is ignored.
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommitRequest {
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BranchRequest {
+    pub name: String,
+    #[serde(default = "default_checkout")]
+    pub checkout: bool,
+}
+
+fn default_checkout() -> bool {
+    true
+}
+
+/// Root of the project containing an execution's working directory (the
+/// active project without one), so agent tools never follow a project switch
+/// made mid-run.
+async fn execution_root(state: &AppState, working_directory: &str) -> Result<String, String> {
+    crate::projects::execution_project(&state.db, working_directory)
+        .await
+        .map(|p| p.root_path)
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not inside a project — git tools only work in project workspaces",
+                working_directory
+            )
+        })
+}
+
+/// Root of the active project, or 409 when no project is active.
+async fn project_root(state: &AppState) -> Result<String, (StatusCode, Json<Value>)> {
+    crate::projects::active_project(&state.db)
+        .await
+        .map(|p| p.root_path)
+        .ok_or_else(|| {
+            api_error(
+                StatusCode::CONFLICT,
+                "no active project — activate one first",
+            )
+        })
+}
+
+/// Run `git -C <root> <args>` and return stdout; non-zero exit maps to 422 with stderr.
+async fn run_git(root: &str, args: &[&str]) -> Result<String, (StatusCode, Json<Value>)> {
+    let mut cmd = tokio::process::Command::new("git");
+    cmd.arg("-C")
+        .arg(root)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .kill_on_drop(true);
+
+    let output = tokio::time::timeout(GIT_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| {
+            api_error(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("git {} timed out", args[0]),
+            )
+        })?
+        .map_err(|e| {
+            tracing::error!("git: failed to spawn: {}", e);
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "git is not available on this host",
+            )
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": stderr, "command": format!("git {}", args[0]) })),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reject paths that are absolute, climb out of the project root or start
+/// with `:` (pathspec magic such as `:(top)` or `:!` would widen the match).
+fn validate_repo_path(path: &str) -> Result<(), (StatusCode, Json<Value>)> {
+    let p = FsPath::new(path);
+    let escapes = p.is_absolute()
+        || path.starts_with(':')
+        || p.components()
+            .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)));
+    if path.trim().is_empty() || escapes {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("'{}' must be a path relative to the project root", path),
+        ));
+    }
+    Ok(())
+}
+
+/// Conservative subset of `git check-ref-format --branch`.
+fn is_valid_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 200
+        && !name.starts_with(['-', '/', '.'])
+        && !name.ends_with(['/', '.'])
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+/// Parse `git status --porcelain=v1 -z -b` output into (branch, entries).
+/// Records are NUL-terminated and unquoted; renames and copies are followed
+/// by an extra record holding the original path, which is skipped.
+fn parse_status(raw: &str) -> (Option<String>, Vec<StatusEntry>) {
+    let mut branch = None;
+    let mut entries = Vec::new();
+    let mut records = raw.split('\0');
+    while let Some(record) = records.next() {
+        if let Some(head) = record.strip_prefix("## ") {
+            let name = head.split("...").next().unwrap_or(head);
+            let name = name.strip_prefix("No commits yet on ").unwrap_or(name);
+            branch = Some(name.trim().to_string());
+            continue;
+        }
+        let mut chars = record.chars();
+        let (Some(index), Some(worktree)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        if matches!(index, 'R' | 'C') || matches!(worktree, 'R' | 'C') {
+            records.next();
+        }
+        let path = record.get(3..).unwrap_or_default();
+        if !path.is_empty() {
+            entries.push(StatusEntry {
+                path: path.to_string(),
+                index,
+                worktree,
+            });
+        }
+    }
+    (branch, entries)
+}
+
+const STATUS_ARGS: &[&str] = &["status", "--porcelain=v1", "-z", "-b"];
+
+async fn status_of(root: &str) -> Result<Value, GitError> {
+    let raw = run_git(root, STATUS_ARGS).await?;
+    let (branch, entries) = parse_status(&raw);
+    Ok(json!({
+        "root": root,
+        "branch": branch,
+        "clean": entries.is_empty(),
+        "entries": entries,
+    }))
+}
+
+async fn stage(root: &str, paths: &[String], all: bool) -> Result<Value, GitError> {
+    if all {
+        run_git(root, &["add", "--all"]).await?;
+    } else {
+        if paths.is_empty() {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "either 'paths' or 'all' is required",
+            ));
+        }
+        for path in paths {
+            validate_repo_path(path)?;
+        }
+        let mut args = vec!["add", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        run_git(root, &args).await?;
+    }
+    let raw = run_git(root, STATUS_ARGS).await?;
+    let (_, entries) = parse_status(&raw);
+    let staged: Vec<&StatusEntry> = entries
+        .iter()
+        .filter(|e| e.index != ' ' && e.index != '?')
+        .collect();
+    Ok(json!({ "staged": staged }))
+}
+
+async fn commit(state: &AppState, root: &str, message: &str) -> Result<Value, GitError> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "commit message is required",
+        ));
+    }
+    run_git(root, &["commit", "-m", message]).await?;
+    let sha = run_git(root, &["rev-parse", "HEAD"]).await?;
+    let sha = sha.trim();
+
+    crate::audit::log_audit(
+        &state.db,
+        "git_commit",
+        json!({ "root": root, "sha": sha, "message": message }),
+        None,
+    )
+    .await;
+    Ok(json!({ "sha": sha, "message": message }))
+}
+
+async fn create_branch(root: &str, name: &str, checkout: bool) -> Result<Value, GitError> {
+    let name = name.trim();
+    if !is_valid_branch_name(name) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("'{}' is not a valid branch name", name),
+        ));
+    }
+    if checkout {
+        run_git(root, &["switch", "-c", name]).await?;
+    } else {
+        run_git(root, &["branch", name]).await?;
+    }
+    Ok(json!({ "branch": name, "checked_out": checkout }))
+}
+
+// ── Agent tools ──────────────────────────────────────────────────────────────
+
+pub const STATUS_TOOL: &str = "git_project_status";
+pub const STAGE_TOOL: &str = "git_stage";
+pub const COMMIT_TOOL: &str = "git_commit_with_message";
+pub const BRANCH_TOOL: &str = "git_create_branch";
+
+/// Names of the project-scoped git tools handled by `execute_tool`.
+pub const TOOL_NAMES: &[&str] = &[STATUS_TOOL, STAGE_TOOL, COMMIT_TOOL, BRANCH_TOOL];
+
+/// Gemini function declarations for the project-scoped git tools.
+pub fn tool_declarations() -> Vec<Value> {
+    vec![
+        json!({
+            "name": STATUS_TOOL,
+            "description": "Show the branch and changed files of the current project's git repository.",
+            "parameters": { "type": "object", "properties": {} }
+        }),
+        json!({
+            "name": STAGE_TOOL,
+            "description": "Stage files in the current project's git repository so they can be committed.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "paths": { "type": "array", "items": { "type": "string" }, "description": "Paths relative to the project root" },
+                    "all": { "type": "boolean", "description": "Stage every change instead of 'paths'" }
+                }
+            }
+        }),
+        json!({
+            "name": COMMIT_TOOL,
+            "description": "Commit the staged changes of the current project with a descriptive message. Nothing is pushed.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string", "description": "Commit message: a short summary line, optionally followed by a body" }
+                },
+                "required": ["message"]
+            }
+        }),
+        json!({
+            "name": BRANCH_TOOL,
+            "description": "Create a branch from HEAD in the current project (checked out unless 'checkout' is false).",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Branch name, e.g. agent/fix-login" },
+                    "checkout": { "type": "boolean", "description": "Switch to the new branch, default true" }
+                },
+                "required": ["name"]
+            }
+        }),
+    ]
+}
+
+/// Execute one of `TOOL_NAMES` for the agent loop in the project of the
+/// calling execution's `working_directory`; errors carry git's message.
+pub async fn execute_tool(
+    state: &AppState,
+    name: &str,
+    args: &Value,
+    working_directory: &str,
+) -> Result<String, String> {
+    let root = execution_root(state, working_directory).await?;
+    let str_arg = |key: &str| args.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+    let result = match name {
+        STATUS_TOOL => status_of(&root).await,
+        STAGE_TOOL => {
+            let paths: Vec<String> = args
+                .get("paths")
+                .and_then(|p| p.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect();
+            let all = args.get("all").and_then(|a| a.as_bool()).unwrap_or(false);
+            stage(&root, &paths, all).await
+        }
+        COMMIT_TOOL => commit(state, &root, str_arg("message")).await,
+        BRANCH_TOOL => {
+            let checkout = args
+                .get("checkout")
+                .and_then(|c| c.as_bool())
+                .unwrap_or(true);
+            create_branch(&root, str_arg("name"), checkout).await
+        }
+        other => return Err(format!("unknown git tool '{}'", other)),
+    };
+    result
+        .map(|value| serde_json::to_string_pretty(&value).unwrap_or_default())
+        .map_err(error_text)
+}
+
+fn error_text((_, Json(body)): GitError) -> String {
+    body.get("error")
+        .and_then(|e| e.as_str())
+        .unwrap_or("git command failed")
+        .to_string()
+}
+
+// ── Handlers ─────────────────────────────────────────────────────────────────
+
+/// GET /api/git/status — Branch and changed files of the active project.
+pub async fn git_status(State(state): State<AppState>) -> GitResult {
+    let root = project_root(&state).await?;
+    status_of(&root).await.map(Json)
+}
+
+/// GET /api/git/diff?staged=true&path=src/lib.rs — Unified diff of the work tree or index.
+pub async fn git_diff(
+    State(state): State<AppState>,
+    Query(params): Query<DiffParams>,
+) -> GitResult {
+    let root = project_root(&state).await?;
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+    if params.staged {
+        args.push("--cached");
+    }
+    if let Some(path) = params.path.as_deref() {
+        validate_repo_path(path)?;
+        args.extend(["--", path]);
+    }
+    let mut diff = run_git(&root, &args).await?;
+    let truncated = diff.len() > MAX_DIFF_BYTES;
+    if truncated {
+        let cut = (0..=MAX_DIFF_BYTES)
+            .rev()
+            .find(|i| diff.is_char_boundary(*i))
+            .unwrap_or(0);
+        diff.truncate(cut);
+    }
+    Ok(Json(json!({
+        "staged": params.staged,
+        "diff": diff,
+        "truncated": truncated,
+    })))
+}
+
+/// POST /api/git/stage — Stage specific paths or everything.
+pub async fn git_stage(State(state): State<AppState>, Json(req): Json<StageRequest>) -> GitResult {
+    let root = project_root(&state).await?;
+    stage(&root, &req.paths, req.all).await.map(Json)
+}
+
+/// POST /api/git/commit — Commit the index with the given message.
+pub async fn git_commit(
+    State(state): State<AppState>,
+    Json(req): Json<CommitRequest>,
+) -> GitResult {
+    let root = project_root(&state).await?;
+    commit(&state, &root, &req.message).await.map(Json)
+}
+
+/// POST /api/git/branch — Create a branch from HEAD (and check it out by default).
+pub async fn git_create_branch(
+    State(state): State<AppState>,
+    Json(req): Json<BranchRequest>,
+) -> GitResult {
+    let root = project_root(&state).await?;
+    create_branch(&root, &req.name, req.checkout)
+        .await
+        .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_parses_branch_and_entries() {
+        let raw = "## main...origin/main [ahead 1]\0 M src/lib.rs\0A  new.rs\0?? notes.txt\0R  renamed.rs\0old.rs\0 M with -> arrow.txt\0";
+        let (branch, entries) = parse_status(raw);
+        assert_eq!(branch.as_deref(), Some("main"));
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            entries[0],
+            StatusEntry {
+                path: "src/lib.rs".to_string(),
+                index: ' ',
+                worktree: 'M'
+            }
+        );
+        assert_eq!(entries[2].worktree, '?');
+        assert_eq!(entries[3].path, "renamed.rs");
+        assert_eq!(entries[4].path, "with -> arrow.txt");
+    }
+
+    #[test]
+    fn status_handles_unborn_branch() {
+        let (branch, entries) = parse_status("## No commits yet on master\0");
+        assert_eq!(branch.as_deref(), Some("master"));
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn branch_names_are_validated() {
+        assert!(is_valid_branch_name("feature/agent-refactor"));
+        assert!(!is_valid_branch_name("-f"));
+        assert!(!is_valid_branch_name("a..b"));
+        assert!(!is_valid_branch_name("topic.lock"));
+        assert!(!is_valid_branch_name("with space"));
+    }
+
+    #[test]
+    fn repo_paths_stay_inside_root() {
+        assert!(validate_repo_path("src/lib.rs").is_ok());
+        assert!(validate_repo_path("../secrets").is_err());
+        assert!(validate_repo_path("/etc/passwd").is_err());
+        assert!(validate_repo_path("").is_err());
+        assert!(validate_repo_path(":(top)secrets").is_err());
+        assert!(validate_repo_path(":!src").is_err());
+    }
+}
@@ -39,5 +39,19 @@ pub async fn execute(
     state: State<AppState>,
     body: Json<ExecuteRequest>,
 ) -> (StatusCode, Json<Value>) {
-    jaskier_core::handlers::execute::execute::<AppState>(state, body).await
+    // Refuse here what the pipeline would refuse anyway, before counting
+    // the request against the quota window.
+    if let Err(e) = crate::shutdown::ensure_running() {
+        return crate::handlers::api_error(StatusCode::SERVICE_UNAVAILABLE, e);
+    }
+    if let Err(e) = crate::offline::ensure_online() {
+        return crate::handlers::api_error(StatusCode::SERVICE_UNAVAILABLE, e.to_string());
+    }
+    if let Err(e) = crate::quota::check(&state).await {
+        return crate::handlers::api_error(StatusCode::TOO_MANY_REQUESTS, e);
+    }
+    crate::tool_registry::execution_scope(jaskier_core::handlers::execute::execute::<AppState>(
+        state, body,
+    ))
+    .await
 }
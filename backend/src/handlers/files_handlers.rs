@@ -5,9 +5,13 @@
 // ---------------------------------------------------------------------------
 
 use axum::Json;
-use serde_json::Value;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde_json::{Value, json};
 
+use crate::handlers::api_error;
 use crate::models::{FileListRequest, FileListResponse, FileReadRequest, FileReadResponse};
+use crate::state::AppState;
 
 #[utoipa::path(post, path = "/api/files/read", tag = "files",
     request_body = FileReadRequest,
@@ -28,3 +32,24 @@ pub async fn list_files(body: Json<FileListRequest>) -> Json<Value> {
 pub async fn browse_directory(body: Json<Value>) -> Json<Value> {
     jaskier_tools::handlers::files_handlers::browse_directory(body).await
 }
+
+// ── App-specific: workspace project brief ────────────────────────────────────
+
+/// POST /api/files/brief — Generate (or return cached) project brief for a workspace.
+/// Body: `{ "path": "C:/Users/me/project" }`
+pub async fn project_brief(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> (StatusCode, Json<Value>) {
+    let path = body
+        .get("path")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    match crate::project_brief::generate_project_brief(&state, path).await {
+        Some(brief) => (StatusCode::OK, Json(json!(*brief))),
+        None => api_error(
+            StatusCode::BAD_REQUEST,
+            "path must be an existing directory",
+        ),
+    }
+}
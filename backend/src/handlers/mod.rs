@@ -6,9 +6,12 @@
 
 use crate::state::AppState;
 use axum::{
-    Router, middleware,
-    routing::{get, post},
+    Json, Router,
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, patch, post, put},
 };
+use serde_json::{Value, json};
 
 pub(crate) mod agents;
 pub(crate) mod execute;
@@ -19,6 +22,11 @@ pub(crate) mod system;
 #[cfg(test)]
 mod tests;
 
+/// Error response of the GeminiHydra-specific handlers: `{ "error": message }`.
+pub fn api_error(status: StatusCode, message: impl Into<String>) -> (StatusCode, Json<Value>) {
+    (status, Json(json!({ "error": message.into() })))
+}
+
 // â”€â”€ Router Factories â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€â”€
 
 pub fn agents_router(state: AppState) -> Router<AppState> {
@@ -57,7 +65,37 @@ pub fn system_router(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/api/system/stats", get(system::system_stats))
         .route("/api/system/audit", get(system::system_audit))
+        .route("/api/capabilities", get(crate::capabilities::capabilities))
         .route("/api/admin/rotate-key", post(system::rotate_key))
+        .route(
+            "/api/admin/support-bundle",
+            get(crate::support_bundle::support_bundle),
+        )
+        .route(
+            "/api/admin/crash-reports",
+            get(crate::crash_reports::list_crash_reports),
+        )
+        .route(
+            "/api/admin/crash-reports/{file}",
+            get(crate::crash_reports::get_crash_report)
+                .delete(crate::crash_reports::delete_crash_report),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn code_index_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/code-index/rebuild",
+            post(crate::code_index::rebuild_index),
+        )
+        .route(
+            "/api/code-index/lookup",
+            get(crate::code_index::lookup_symbols),
+        )
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
@@ -69,6 +107,287 @@ pub fn files_router(state: AppState) -> Router<AppState> {
         .route("/api/files/read", post(files_handlers::read_file))
         .route("/api/files/list", post(files_handlers::list_files))
         .route("/api/files/browse", post(files_handlers::browse_directory))
+        .route("/api/files/brief", post(files_handlers::project_brief))
+        .route("/api/files/diff-preview", post(crate::patch::diff_preview))
+        .route("/api/files/apply-patch", post(crate::patch::apply_patch))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn git_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/git/status", get(crate::git::git_status))
+        .route("/api/git/diff", get(crate::git::git_diff))
+        .route("/api/git/stage", post(crate::git::git_stage))
+        .route("/api/git/commit", post(crate::git::git_commit))
+        .route("/api/git/branch", post(crate::git::git_create_branch))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn images_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/images/generate",
+            post(crate::image_gen::generate_image_with_gemini),
+        )
+        .route(
+            "/api/images/generated/{file}",
+            get(crate::image_gen::get_generated_image),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn knowledge_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/knowledge/export",
+            get(crate::knowledge_graph::export_knowledge_graph),
+        )
+        .route(
+            "/api/knowledge/import",
+            post(crate::knowledge_graph::import_knowledge_graph),
+        )
+        .route(
+            "/api/knowledge/nodes/{id}",
+            get(crate::knowledge_graph::get_knowledge_node)
+                .patch(crate::knowledge_graph::update_knowledge_node)
+                .delete(crate::knowledge_graph::delete_knowledge_node),
+        )
+        .route(
+            "/api/knowledge/view",
+            get(crate::knowledge_layout::knowledge_graph_view),
+        )
+        .route(
+            "/api/knowledge/nodes/{id}/provenance",
+            get(crate::knowledge_extraction::node_provenance),
+        )
+        .route(
+            "/api/sessions/{id}/extract-knowledge",
+            post(crate::knowledge_extraction::extract_session_knowledge),
+        )
+        .route(
+            "/api/knowledge/edges",
+            post(crate::knowledge_graph::upsert_knowledge_edge),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn memory_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/sessions/{id}/summarize",
+            post(crate::memory_summary::summarize_session),
+        )
+        .route(
+            "/api/memory/injection",
+            get(crate::memory_recall::inspect_injection).put(crate::memory_recall::set_injection),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn offline_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/offline",
+            get(crate::offline::get_offline).put(crate::offline::set_offline_handler),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn projects_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/projects",
+            get(crate::projects::list_projects).post(crate::projects::create_project),
+        )
+        .route(
+            "/api/projects/deactivate",
+            post(crate::projects::deactivate_projects),
+        )
+        .route(
+            "/api/projects/{id}",
+            patch(crate::projects::update_project).delete(crate::projects::delete_project),
+        )
+        .route(
+            "/api/projects/{id}/activate",
+            post(crate::projects::activate_project),
+        )
+        .route(
+            "/api/projects/{id}/sessions",
+            get(crate::projects::list_project_sessions),
+        )
+        .route(
+            "/api/projects/{id}/memories",
+            get(crate::projects::list_project_memories),
+        )
+        .route(
+            "/api/sessions/{id}/project",
+            put(crate::projects::assign_session_project),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn prompts_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/prompts",
+            get(crate::prompt_templates::list_templates)
+                .post(crate::prompt_templates::create_template),
+        )
+        .route(
+            "/api/prompts/{name}",
+            get(crate::prompt_templates::get_template)
+                .patch(crate::prompt_templates::update_template)
+                .delete(crate::prompt_templates::delete_template),
+        )
+        .route(
+            "/api/prompts/{name}/render",
+            post(crate::prompt_templates::render_template),
+        )
+        .route(
+            "/api/prompt-variables",
+            get(crate::prompt_templates::list_variables),
+        )
+        .route(
+            "/api/prompt-variables/{name}",
+            put(crate::prompt_templates::set_variable)
+                .delete(crate::prompt_templates::delete_variable),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn regenerate_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/sessions/{id}/messages/{message_id}/regenerate",
+            post(crate::regenerate::regenerate_message),
+        )
+        .route(
+            "/api/sessions/{id}/messages/{message_id}/regenerate/stream",
+            post(crate::regenerate::regenerate_message_stream),
+        )
+        .route(
+            "/api/messages/{id}/alternatives",
+            get(crate::regenerate::list_alternatives),
+        )
+        .route(
+            "/api/messages/{id}/alternatives/{alternative_id}/select",
+            post(crate::regenerate::select_alternative),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn structured_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/generate/structured",
+            post(crate::structured::generate_structured_handler),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn tokens_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/tokens/count",
+            post(crate::token_budget::count_text_tokens),
+        )
+        .route(
+            "/api/tokens/estimate-fit",
+            post(crate::token_budget::estimate_prompt_fit),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn tools_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/tools", get(crate::tool_registry::list_tools))
+        .route(
+            "/api/tools/{name}",
+            patch(crate::tool_registry::update_tool),
+        )
+        .route(
+            "/api/tools/approvals",
+            get(crate::tool_registry::list_approvals),
+        )
+        .route(
+            "/api/tools/approvals/{id}/approve",
+            post(crate::tool_registry::approve_tool),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn usage_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/usage/summary", get(crate::usage::usage_summary))
+        .route("/api/usage/quota", get(crate::quota::quota_status))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+pub fn vector_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/vectors/collections",
+            get(crate::vector_store::list_collections).post(crate::vector_store::create_collection),
+        )
+        .route(
+            "/api/vectors/collections/{name}",
+            delete(crate::vector_store::delete_collection),
+        )
+        .route(
+            "/api/vectors/collections/{name}/upsert",
+            post(crate::vector_store::upsert),
+        )
+        .route(
+            "/api/vectors/collections/{name}/query",
+            post(crate::vector_store::query),
+        )
+        .route(
+            "/api/vectors/collections/{name}/invalidate-stale",
+            post(crate::vector_store::invalidate_stale),
+        )
+        .route(
+            "/api/vectors/collections/{name}/items/{id}",
+            delete(crate::vector_store::delete_item),
+        )
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             jaskier_core::auth::jaskier_auth_require_auth::<AppState>,
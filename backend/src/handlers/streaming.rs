@@ -27,6 +27,9 @@ pub use jaskier_core::handlers::gemini_streaming::{
 
 // ── HasGeminiStreamingState trait impl for GeminiHydra ──────────────────────
 
+/// Routing reason for turns of a session whose agent is already locked.
+const LOCKED_REASON: &str = "Locked";
+
 impl HasGeminiStreamingState for AppState {
     fn db(&self) -> &sqlx::PgPool {
         &self.db
@@ -45,7 +48,15 @@ impl HasGeminiStreamingState for AppState {
     }
 
     async fn circuit_check(&self) -> Result<(), String> {
-        self.gemini_circuit.check().await
+        crate::shutdown::ensure_running()?;
+        crate::offline::ensure_online().map_err(|e| e.to_string())?;
+        crate::vertex::ensure_api_key_route()?;
+        crate::quota::check_provider(self, crate::quota::Provider::Gemini).await?;
+        let admitted = self.gemini_circuit.check().await;
+        if admitted.is_err() {
+            crate::quota::refund(crate::quota::Provider::Gemini).await;
+        }
+        admitted
     }
     async fn circuit_record_success(&self) {
         self.gemini_circuit.record_success().await;
@@ -85,8 +96,21 @@ impl HasGeminiStreamingState for AppState {
         agent_info: Option<(String, f64, String)>,
         session_wd: &str,
     ) -> jaskier_core::context::ExecuteContext {
-        crate::context::prepare_execution(self, prompt, model_override, agent_info, session_wd)
-            .await
+        let model_overridden = model_override.is_some();
+        // Continued chats (agent already locked) got their facts on the first turn.
+        let continued = matches!(&agent_info, Some((_, _, reason)) if reason == LOCKED_REASON);
+        let mut ctx =
+            crate::context::prepare_execution(self, prompt, model_override, agent_info, session_wd)
+                .await;
+        crate::vertex::use_api_key_credential(&mut ctx);
+        crate::projects::apply_to_context(self, &mut ctx, model_overridden).await;
+        crate::project_brief::inject_into_context(self, &mut ctx).await;
+        if !continued {
+            crate::memory_recall::inject_into_context(self, &mut ctx).await;
+        }
+        crate::prompt_templates::apply_agent_default(self, &mut ctx).await;
+        crate::tool_registry::bind_execution_agent(&ctx.agent_id);
+        ctx
     }
 
     async fn build_tools_json(&self) -> Value {
@@ -103,7 +127,15 @@ impl HasGeminiStreamingState for AppState {
         args: &Value,
         working_directory: &str,
     ) -> Result<GeminiToolOutput, String> {
-        match crate::tools::execute_tool(name, args, self, working_directory).await {
+        match crate::tool_registry::execute_tool(
+            name,
+            args,
+            self,
+            working_directory,
+            crate::tool_registry::execution_agent().as_deref(),
+        )
+        .await
+        {
             Ok(output) => Ok(GeminiToolOutput {
                 text: output.text,
                 inline_data: output.inline_data.map(|d| GeminiInlineData {
@@ -116,7 +148,11 @@ impl HasGeminiStreamingState for AppState {
     }
 
     async fn execute_agent_call(&self, args: &Value, call_depth: u32) -> Result<String, String> {
-        crate::a2a::execute_agent_call(self, args, call_depth).await
+        // The delegate binds its own agent; keep it out of this execution.
+        crate::tool_registry::execution_scope(crate::a2a::execute_agent_call(
+            self, args, call_depth,
+        ))
+        .await
     }
 
     async fn resolve_session_agent(
@@ -124,6 +160,8 @@ impl HasGeminiStreamingState for AppState {
         session_id: &Uuid,
         prompt: &str,
     ) -> (String, f64, String) {
+        crate::projects::tag_session(&self.db, session_id).await;
+
         // Check if session has a locked agent
         if let Some(aid) =
             sqlx::query_as::<_, (Option<String>,)>("SELECT agent_id FROM gh_sessions WHERE id = $1")
@@ -136,7 +174,7 @@ impl HasGeminiStreamingState for AppState {
                 .and_then(|(a,)| a)
                 .filter(|s| !s.is_empty())
         {
-            return (aid, 0.95, "Locked".into());
+            return (aid, 0.95, LOCKED_REASON.into());
         }
 
         // Classify the prompt
@@ -100,37 +100,48 @@ pub async fn rotate_key(
 pub async fn gemini_models(State(state): State<AppState>) -> Json<Value> {
     let mut models = Vec::new();
 
-    // B13: credentials from env vars (Vault sets these)
-    let api_key = std::env::var("GOOGLE_API_KEY")
-        .or_else(|_| std::env::var("GEMINI_API_KEY"))
-        .ok()
-        .filter(|k| !k.is_empty());
-
-    if let Some(key) = api_key {
-        let url = "https://generativelanguage.googleapis.com/v1beta/models";
-        if let Ok(res) = state
-            .client
-            .get(url)
-            .header("x-goog-api-key", &key)
-            .send()
-            .await
-            && res.status().is_success()
-            && let Ok(body) = res.json::<Value>().await
-            && let Some(list) = body["models"].as_array()
-        {
-            models.extend(list.iter().filter_map(|m| {
-                let info: GeminiModelInfo = serde_json::from_value(m.clone()).ok()?;
-                if info
-                    .supported_generation_methods
-                    .contains(&"generateContent".to_string())
-                {
-                    Some(info)
-                } else {
-                    None
-                }
-            }));
-        }
+    // B13: credentials from env vars (Vault sets these); with Vertex configured
+    // the listing goes to the publisher-model endpoint with a bearer token.
+    let request = state.client.get(crate::gemini_api::list_models_url());
+    if let Ok(request) = crate::gemini_api::authorize(&state, request).await
+        && let Ok(res) = request.send().await
+        && res.status().is_success()
+        && let Ok(body) = res.json::<Value>().await
+    {
+        let list = body["models"]
+            .as_array()
+            .cloned()
+            .or_else(|| {
+                body["publisherModels"]
+                    .as_array()
+                    .map(|list| list.iter().filter_map(vertex_model_info).collect())
+            })
+            .unwrap_or_default();
+        models.extend(list.iter().filter_map(|m| {
+            let info: GeminiModelInfo = serde_json::from_value(m.clone()).ok()?;
+            if info
+                .supported_generation_methods
+                .contains(&"generateContent".to_string())
+            {
+                Some(info)
+            } else {
+                None
+            }
+        }));
     }
 
     Json(json!(GeminiModelsResponse { models }))
 }
+
+/// Vertex `publisherModels` entries in the Generative Language `models` shape.
+/// Only Gemini models are kept; they all support `generateContent`.
+fn vertex_model_info(m: &Value) -> Option<Value> {
+    let id = m["name"].as_str()?.rsplit('/').next()?;
+    id.starts_with("gemini").then(|| {
+        json!({
+            "name": format!("models/{}", id),
+            "displayName": id,
+            "supportedGenerationMethods": ["generateContent", "countTokens"],
+        })
+    })
+}
@@ -0,0 +1,104 @@
+// ---------------------------------------------------------------------------
+// idle_sessions.rs — Background jobs over chats that went idle
+//
+// memory_summary.rs and knowledge_extraction.rs both process a chat once it
+// has had no new message for IDLE_MINUTES, and again after it gets new
+// messages. Each job names the gh_sessions column that records its last run
+// and a per-session handler; `spawn` runs it every TICK_INTERVAL (skipped
+// while offline), SESSIONS_PER_TICK sessions at a time. A transient Gemini
+// error (quota, timeout, 5xx) ends the tick so the remaining sessions are
+// retried next time; a permanent failure marks the session done so it is not
+// retried forever.
+// ---------------------------------------------------------------------------
+
+use std::future::Future;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+const TICK_INTERVAL: Duration = Duration::from_secs(300);
+const IDLE_MINUTES: i32 = 15;
+const SESSIONS_PER_TICK: i64 = 5;
+
+/// One idle-session job.
+pub struct IdleSessionJob {
+    /// Log prefix, e.g. `memory_summary`.
+    pub name: &'static str,
+    /// `gh_sessions` timestamp column set when a session has been processed.
+    pub done_column: &'static str,
+}
+
+impl IdleSessionJob {
+    /// Sessions idle for IDLE_MINUTES with messages newer than the job's last run.
+    async fn pending(&self, state: &AppState) -> Vec<Uuid> {
+        let sql = format!(
+            "SELECT s.id FROM gh_sessions s \
+             JOIN LATERAL (SELECT MAX(created_at) AS last_at FROM gh_chat_messages m \
+                           WHERE m.session_id = s.id) m ON m.last_at IS NOT NULL \
+             WHERE m.last_at < NOW() - make_interval(mins => $1) \
+               AND (s.{col} IS NULL OR s.{col} < m.last_at) \
+             ORDER BY m.last_at DESC LIMIT $2",
+            col = self.done_column
+        );
+        sqlx::query_scalar(&sql)
+            .bind(IDLE_MINUTES)
+            .bind(SESSIONS_PER_TICK)
+            .fetch_all(&state.db)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("{}: failed to list sessions: {}", self.name, e);
+                Vec::new()
+            })
+    }
+
+    /// Record `session_id` as processed (also used for permanent failures).
+    async fn mark_done(&self, state: &AppState, session_id: Uuid) {
+        let sql = format!(
+            "UPDATE gh_sessions SET {} = NOW() WHERE id = $1",
+            self.done_column
+        );
+        if let Err(e) = sqlx::query(&sql).bind(session_id).execute(&state.db).await {
+            tracing::warn!(
+                "{}: failed to mark session {}: {}",
+                self.name,
+                session_id,
+                e
+            );
+        }
+    }
+}
+
+/// Run `job` in the background, calling `handle` for every pending session.
+pub fn spawn<F, Fut>(state: AppState, job: IdleSessionJob, handle: F) -> tokio::task::JoinHandle<()>
+where
+    F: Fn(AppState, Uuid) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send,
+{
+    tokio::spawn(async move {
+        tracing::info!(
+            "{}: background job started (interval={}s)",
+            job.name,
+            TICK_INTERVAL.as_secs()
+        );
+        loop {
+            tokio::time::sleep(TICK_INTERVAL).await;
+            if crate::offline::is_offline() {
+                continue;
+            }
+            for session_id in job.pending(&state).await {
+                let Err(e) = handle(state.clone(), session_id).await else {
+                    continue;
+                };
+                tracing::warn!("{}: session {} failed: {}", job.name, session_id, e);
+                if crate::gemini_api::is_transient_error(&e) {
+                    // Quota, timeouts and 5xx: retry the remaining sessions next tick.
+                    break;
+                }
+                // Permanent failures (no messages, unusable output) are not retried.
+                job.mark_done(&state, session_id).await;
+            }
+        }
+    })
+}
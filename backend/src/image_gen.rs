@@ -0,0 +1,251 @@
+// ---------------------------------------------------------------------------
+// image_gen.rs — Image generation through image-capable Gemini models
+//
+// Calls `generateContent` with `responseModalities: [TEXT, IMAGE]` on the
+// direct API (the `generate_image` agent tool goes through the browser
+// proxy instead), decodes the inline base64 parts and stores them under
+// `GENERATED_IMAGES_DIR` (default `generated/` in the data directory, see
+// data_dir.rs). Stored files are served back by name so the UI can render
+// them.
+// ---------------------------------------------------------------------------
+
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+const DEFAULT_IMAGE_MODEL: &str = "gemini-2.5-flash-image";
+const MAX_PROMPT_CHARS: usize = 8000;
+
+static FILE_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\.(png|jpg|webp)$")
+        .expect("valid regex")
+});
+/// Model ids are spliced into the request URL, so only plain ids pass.
+static MODEL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9.-]{1,100}$").expect("valid regex"));
+
+#[derive(Debug, Deserialize)]
+pub struct ImageRequest {
+    pub prompt: String,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct InlineImage {
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+fn output_dir() -> PathBuf {
+    crate::data_dir::subdir("GENERATED_IMAGES_DIR", "generated")
+}
+
+fn extension_for(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+/// Decoded image parts and concatenated text of the first candidate.
+fn extract_parts(body: &Value) -> (Vec<InlineImage>, String) {
+    let mut images = Vec::new();
+    let mut text = String::new();
+    let parts = body
+        .pointer("/candidates/0/content/parts")
+        .and_then(|p| p.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for part in &parts {
+        if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+            text.push_str(t);
+        }
+        let Some(inline) = part.get("inlineData").or_else(|| part.get("inline_data")) else {
+            continue;
+        };
+        let mime_type = inline
+            .get("mimeType")
+            .or_else(|| inline.get("mime_type"))
+            .and_then(|m| m.as_str())
+            .unwrap_or("image/png");
+        let Some(data) = inline
+            .get("data")
+            .and_then(|d| d.as_str())
+            .and_then(|d| STANDARD.decode(d).ok())
+        else {
+            continue;
+        };
+        images.push(InlineImage {
+            mime_type: mime_type.to_string(),
+            data,
+        });
+    }
+    (images, text.trim().to_string())
+}
+
+/// POST /api/images/generate — Generate images and store them on disk.
+pub async fn generate_image_with_gemini(
+    State(state): State<AppState>,
+    Json(req): Json<ImageRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let prompt = req.prompt.trim();
+    if prompt.is_empty() || prompt.chars().count() > MAX_PROMPT_CHARS {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("prompt must be 1..{} characters", MAX_PROMPT_CHARS),
+        ));
+    }
+    let model = req
+        .model
+        .filter(|m| !m.is_empty())
+        .or_else(|| {
+            std::env::var("GEMINI_IMAGE_MODEL")
+                .ok()
+                .filter(|m| !m.is_empty())
+        })
+        .unwrap_or_else(|| DEFAULT_IMAGE_MODEL.to_string());
+    if !MODEL_RE.is_match(&model) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("invalid model id '{}'", model),
+        ));
+    }
+
+    let body = json!({
+        "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        "generationConfig": { "responseModalities": ["TEXT", "IMAGE"] },
+    });
+    let response = crate::gemini_api::generate_raw(&state, &model, body, "image")
+        .await
+        .map_err(|e| api_error(StatusCode::BAD_GATEWAY, e))?;
+    let (images, text) = extract_parts(&response);
+    let images: Vec<(InlineImage, &str)> = images
+        .into_iter()
+        .filter_map(|image| match extension_for(&image.mime_type) {
+            Some(ext) => Some((image, ext)),
+            None => {
+                tracing::warn!(
+                    "image_gen: skipping unsupported image type {}",
+                    image.mime_type
+                );
+                None
+            }
+        })
+        .collect();
+    if images.is_empty() {
+        return Err(api_error(
+            StatusCode::BAD_GATEWAY,
+            format!("model '{}' returned no supported image", model),
+        ));
+    }
+
+    let dir = output_dir();
+    tokio::fs::create_dir_all(&dir).await.map_err(|e| {
+        tracing::error!("image_gen: cannot create {}: {}", dir.display(), e);
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "cannot create output directory",
+        )
+    })?;
+
+    let mut saved = Vec::new();
+    for (image, ext) in images {
+        let file_name = format!("{}.{}", Uuid::new_v4(), ext);
+        let path = dir.join(&file_name);
+        tokio::fs::write(&path, &image.data).await.map_err(|e| {
+            tracing::error!("image_gen: failed to write {}: {}", path.display(), e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "failed to store image")
+        })?;
+        saved.push(json!({
+            "file": file_name,
+            "path": path.display().to_string(),
+            "url": format!("/api/images/generated/{}", file_name),
+            "mime_type": image.mime_type,
+            "bytes": image.data.len(),
+        }));
+    }
+
+    crate::audit::log_audit(
+        &state.db,
+        "generate_image",
+        json!({ "model": model, "images": saved.len() }),
+        None,
+    )
+    .await;
+    Ok(Json(
+        json!({ "model": model, "text": text, "images": saved }),
+    ))
+}
+
+/// GET /api/images/generated/{file} — Serve a stored image.
+pub async fn get_generated_image(Path(file): Path<String>) -> Response {
+    if !FILE_NAME_RE.is_match(&file) {
+        return api_error(StatusCode::BAD_REQUEST, "invalid file name").into_response();
+    }
+    let mime = match file.rsplit('.').next() {
+        Some("jpg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    };
+    match tokio::fs::read(output_dir().join(&file)).await {
+        Ok(bytes) => (
+            [(header::CONTENT_TYPE, HeaderValue::from_static(mime))],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => api_error(StatusCode::NOT_FOUND, "image not found").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_images_are_decoded() {
+        let body = json!({ "candidates": [{ "content": { "parts": [
+            { "text": "Here is a cat." },
+            { "inlineData": { "mimeType": "image/png", "data": STANDARD.encode(b"png-bytes") } },
+            { "inlineData": { "mimeType": "image/png", "data": "not base64!" } }
+        ] } }] });
+        let (images, text) = extract_parts(&body);
+        assert_eq!(text, "Here is a cat.");
+        assert_eq!(
+            images,
+            vec![InlineImage {
+                mime_type: "image/png".to_string(),
+                data: b"png-bytes".to_vec()
+            }]
+        );
+    }
+
+    #[test]
+    fn served_file_names_are_restricted() {
+        assert!(FILE_NAME_RE.is_match("0f8fad5b-d9cb-469f-a165-70867728950e.png"));
+        assert!(!FILE_NAME_RE.is_match("../secrets.png"));
+        assert!(!FILE_NAME_RE.is_match("0f8fad5b-d9cb-469f-a165-70867728950e.svg"));
+    }
+
+    #[test]
+    fn model_ids_are_restricted() {
+        assert!(MODEL_RE.is_match("gemini-2.5-flash-image"));
+        assert!(!MODEL_RE.is_match("gemini:generateContent?key=x"));
+        assert!(!MODEL_RE.is_match("../models/x"));
+        assert!(!MODEL_RE.is_match(""));
+    }
+}
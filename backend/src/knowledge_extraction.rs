@@ -0,0 +1,340 @@
+// ---------------------------------------------------------------------------
+// knowledge_extraction.rs — Conversations → knowledge graph, with provenance
+//
+// Finished sessions (idle, see idle_sessions.rs) are sent to Gemini
+// with a numbered transcript; the model returns entities and relations plus
+// the message numbers they were read from. Nodes and edges are inserted
+// idempotently and every one is linked back to its source messages in
+// gh_knowledge_provenance. The background job is opt-in via
+// `KG_EXTRACTION_ENABLED`; single sessions can be processed on demand.
+// While it is enabled this module is the only writer of conversation-derived
+// graph entries (memory_summary.rs then stores facts only).
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::handlers::api_error;
+use crate::idle_sessions::{self, IdleSessionJob};
+use crate::knowledge_graph;
+use crate::state::AppState;
+
+const MAX_TRANSCRIPT_MESSAGES: usize = 80;
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+const EXTRACTION_PROMPT: &str = r#"You build a knowledge graph from conversations.
+The conversation below has numbered messages like [#3 user]. Return JSON with exactly this shape:
+{
+  "entities": [{ "label": "name", "type": "person|project|technology|concept|file|organization", "messages": [3] }],
+  "relations": [{ "source": "entity label", "target": "entity label", "label": "uses|depends_on|part_of|prefers|related_to", "messages": [3, 4] }]
+}
+"messages" lists the numbers of the messages the entity or relation was taken from.
+Only extract things that stay meaningful outside this conversation; skip greetings and transient details."#;
+
+#[derive(Debug, Default, Deserialize)]
+struct Extraction {
+    #[serde(default)]
+    entities: Vec<Entity>,
+    #[serde(default)]
+    relations: Vec<Relation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entity {
+    label: String,
+    #[serde(rename = "type", default = "default_entity_type")]
+    entity_type: String,
+    #[serde(default)]
+    messages: Vec<usize>,
+}
+
+fn default_entity_type() -> String {
+    "concept".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct Relation {
+    source: String,
+    target: String,
+    #[serde(default = "default_relation")]
+    label: String,
+    #[serde(default)]
+    messages: Vec<usize>,
+}
+
+fn default_relation() -> String {
+    "related_to".to_string()
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExtractionOutcome {
+    pub session_id: Uuid,
+    pub nodes_created: usize,
+    pub edges_created: usize,
+    pub provenance_links: usize,
+}
+
+/// Whether the extraction job (and with it graph writes from conversations) is on.
+pub fn enabled() -> bool {
+    std::env::var("KG_EXTRACTION_ENABLED")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "on"))
+        .unwrap_or(false)
+}
+
+/// Number `(id, role, content)` messages as `[#n role]`, 1-based over `messages`.
+fn build_transcript(messages: &[(Uuid, String, String)]) -> String {
+    messages
+        .iter()
+        .enumerate()
+        .map(|(i, (_, role, content))| {
+            let text: String = content.chars().take(MAX_MESSAGE_CHARS).collect();
+            format!("[#{} {}] {}", i + 1, role, text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Map 1-based message numbers back to ids, dropping out-of-range and repeated ones.
+fn resolve_messages(numbers: &[usize], messages: &[(Uuid, String, String)]) -> Vec<Uuid> {
+    let mut ids = Vec::new();
+    for n in numbers {
+        if let Some((id, _, _)) = n.checked_sub(1).and_then(|i| messages.get(i))
+            && !ids.contains(id)
+        {
+            ids.push(*id);
+        }
+    }
+    ids
+}
+
+/// Link a node (or the edge `node_id -[label]-> target`) to a source message.
+async fn link(
+    state: &AppState,
+    node_id: &str,
+    edge: Option<(&str, &str)>,
+    session_id: Uuid,
+    message_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let (target, label) = edge.unzip();
+    let result = sqlx::query(
+        "INSERT INTO gh_knowledge_provenance (node_id, edge_target, edge_label, session_id, message_id) \
+         SELECT $1, $2, $3, $4, $5 \
+         WHERE EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $1) \
+           AND ($2::text IS NULL OR EXISTS (SELECT 1 FROM gh_knowledge_edges \
+                WHERE source = $1 AND target = $2 AND label = $3)) \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(node_id)
+    .bind(target)
+    .bind(label)
+    .bind(session_id)
+    .bind(message_id)
+    .execute(&state.db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Extract entities/relations from one session and record their provenance.
+pub async fn extract_session(
+    state: &AppState,
+    session_id: Uuid,
+) -> Result<ExtractionOutcome, String> {
+    let mut messages: Vec<(Uuid, String, String)> = sqlx::query_as(
+        "SELECT id, role, content FROM gh_chat_messages WHERE session_id = $1 ORDER BY created_at",
+    )
+    .bind(session_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| format!("failed to load messages: {}", e))?;
+    if messages.is_empty() {
+        return Err("session has no messages".to_string());
+    }
+    let start = messages.len().saturating_sub(MAX_TRANSCRIPT_MESSAGES);
+    messages.drain(..start);
+
+    let prompt = format!(
+        "{}\n\n--- CONVERSATION ---\n{}",
+        EXTRACTION_PROMPT,
+        build_transcript(&messages)
+    );
+    let raw = crate::gemini_api::generate_content(state, &prompt, 0.2, true).await?;
+    let extraction: Extraction = serde_json::from_str(&crate::structured::repair_json(&raw))
+        .map_err(|e| format!("model returned invalid JSON: {}", e))?;
+
+    let db_err = |e: sqlx::Error| format!("failed to store extraction: {}", e);
+    let mut outcome = ExtractionOutcome {
+        session_id,
+        ..Default::default()
+    };
+    for entity in &extraction.entities {
+        let id = knowledge_graph::slugify(&entity.label);
+        if id.is_empty() {
+            continue;
+        }
+        if knowledge_graph::insert_node(&state.db, &id, &entity.entity_type, entity.label.trim())
+            .await
+            .map_err(db_err)?
+        {
+            outcome.nodes_created += 1;
+        }
+        for message_id in resolve_messages(&entity.messages, &messages) {
+            if link(state, &id, None, session_id, message_id)
+                .await
+                .map_err(db_err)?
+            {
+                outcome.provenance_links += 1;
+            }
+        }
+    }
+    for relation in &extraction.relations {
+        let (source, target) = (
+            knowledge_graph::slugify(&relation.source),
+            knowledge_graph::slugify(&relation.target),
+        );
+        if source.is_empty() || target.is_empty() || source == target {
+            continue;
+        }
+        if knowledge_graph::insert_edge(&state.db, &source, &target, &relation.label, 1.0)
+            .await
+            .map_err(db_err)?
+        {
+            outcome.edges_created += 1;
+        }
+        for message_id in resolve_messages(&relation.messages, &messages) {
+            let edge = Some((target.as_str(), relation.label.as_str()));
+            if link(state, &source, edge, session_id, message_id)
+                .await
+                .map_err(db_err)?
+            {
+                outcome.provenance_links += 1;
+            }
+        }
+    }
+
+    sqlx::query("UPDATE gh_sessions SET kg_extracted_at = NOW() WHERE id = $1")
+        .bind(session_id)
+        .execute(&state.db)
+        .await
+        .map_err(db_err)?;
+    tracing::info!(
+        %session_id,
+        nodes_created = outcome.nodes_created,
+        edges_created = outcome.edges_created,
+        provenance_links = outcome.provenance_links,
+        "knowledge_extraction: session processed"
+    );
+    Ok(outcome)
+}
+
+/// Spawn the background extraction job when `KG_EXTRACTION_ENABLED` is set.
+pub fn spawn(state: AppState) -> Option<tokio::task::JoinHandle<()>> {
+    if !enabled() {
+        return None;
+    }
+    let job = IdleSessionJob {
+        name: "knowledge_extraction",
+        done_column: "kg_extracted_at",
+    };
+    Some(idle_sessions::spawn(state, job, |state, id| async move {
+        extract_session(&state, id).await.map(|_| ())
+    }))
+}
+
+/// POST /api/sessions/{id}/extract-knowledge — Run extraction for one session now.
+pub async fn extract_session_knowledge(
+    State(state): State<AppState>,
+    Path(session_id): Path<Uuid>,
+) -> (StatusCode, Json<Value>) {
+    match extract_session(&state, session_id).await {
+        Ok(outcome) => (StatusCode::OK, Json(json!(outcome))),
+        Err(e) => api_error(StatusCode::UNPROCESSABLE_ENTITY, e),
+    }
+}
+
+/// GET /api/knowledge/nodes/{id}/provenance — Source messages of a node and its edges.
+pub async fn node_provenance(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    type Row = (
+        String,
+        Option<String>,
+        Option<String>,
+        Uuid,
+        Uuid,
+        String,
+        String,
+        String,
+    );
+    let rows: Vec<Row> = sqlx::query_as(
+        "SELECT p.node_id, p.edge_target, p.edge_label, p.session_id, p.message_id, \
+                m.role, left(m.content, 200), p.created_at::text \
+         FROM gh_knowledge_provenance p JOIN gh_chat_messages m ON m.id = p.message_id \
+         WHERE p.node_id = $1 OR p.edge_target = $1 \
+         ORDER BY p.created_at DESC LIMIT 200",
+    )
+    .bind(&node_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("knowledge_extraction: provenance query failed: {}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+    })?;
+
+    let sources: Vec<Value> = rows
+        .into_iter()
+        .map(
+            |(source, target, label, session_id, message_id, role, excerpt, at)| {
+                let edge = target.map(|t| json!({ "source": source, "target": t, "label": label }));
+                json!({
+                    "edge": edge,
+                    "session_id": session_id,
+                    "message_id": message_id,
+                    "role": role,
+                    "excerpt": excerpt,
+                    "extracted_at": at,
+                })
+            },
+        )
+        .collect();
+    Ok(Json(json!({ "node_id": node_id, "sources": sources })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(n: usize) -> Vec<(Uuid, String, String)> {
+        (0..n)
+            .map(|i| (Uuid::new_v4(), "user".to_string(), format!("message {}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn transcript_is_numbered_from_one() {
+        let transcript = build_transcript(&messages(2));
+        assert!(transcript.starts_with("[#1 user] message 0"));
+        assert!(transcript.ends_with("[#2 user] message 1"));
+    }
+
+    #[test]
+    fn message_numbers_resolve_to_ids() {
+        let msgs = messages(3);
+        let ids = resolve_messages(&[2, 0, 2, 9, 3], &msgs);
+        assert_eq!(ids, vec![msgs[1].0, msgs[2].0]);
+    }
+
+    #[test]
+    fn extraction_tolerates_missing_message_refs() {
+        let parsed: Extraction = serde_json::from_str(
+            r#"{"entities":[{"label":"Rust"}],"relations":[{"source":"a","target":"b"}]}"#,
+        )
+        .expect("valid");
+        assert!(parsed.entities[0].messages.is_empty());
+        assert_eq!(parsed.relations[0].label, "related_to");
+    }
+}
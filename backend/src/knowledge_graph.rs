@@ -0,0 +1,1118 @@
+// ---------------------------------------------------------------------------
+// knowledge_graph.rs — App-side helpers for gh_knowledge_nodes / gh_knowledge_edges
+//
+// The basic graph CRUD endpoints live in jaskier-core (`sessions` module).
+// This module holds the GeminiHydra-specific additions that write to the same
+// tables: id normalisation, idempotent node/edge inserts used by the
+// background extraction pipelines, GraphML / DOT / Cypher import-export so
+// the graph can be inspected in Gephi or Neo4j, and node/edge maintenance
+// for the properties, weights and timestamps added in migration 053.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+/// Upper bound for an import payload (inline or read from disk).
+const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Normalise a free-form entity label into a stable node id (`"Jaskier Core"` -> `"jaskier-core"`).
+pub fn slugify(label: &str) -> String {
+    let mut slug = String::with_capacity(label.len());
+    let mut last_dash = true;
+    for c in label.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+            last_dash = false;
+        } else if !last_dash {
+            slug.push('-');
+            last_dash = true;
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    slug.chars().take(128).collect()
+}
+
+/// Insert a node unless it already exists. Returns `true` when a row was created.
+pub async fn insert_node<'e>(
+    db: impl sqlx::PgExecutor<'e>,
+    id: &str,
+    node_type: &str,
+    label: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO gh_knowledge_nodes (id, node_type, label) VALUES ($1, $2, $3) \
+         ON CONFLICT (id) DO NOTHING",
+    )
+    .bind(id)
+    .bind(node_type)
+    .bind(label)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Insert an edge between two existing nodes. Returns `true` when a row was created;
+/// edges pointing at unknown nodes are skipped rather than failing the FK.
+pub async fn insert_edge<'e>(
+    db: impl sqlx::PgExecutor<'e>,
+    source: &str,
+    target: &str,
+    label: &str,
+    weight: f64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO gh_knowledge_edges (source, target, label, weight) \
+         SELECT $1, $2, $3, $4 \
+         WHERE EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $1) \
+           AND EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $2) \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(source)
+    .bind(target)
+    .bind(label)
+    .bind(weight)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// ── Import / export ─────────────────────────────────────────────────────────
+
+/// Free-form properties and timestamps carried by both nodes and edges
+/// (migration 053). Every field is optional so foreign files still import.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphMeta {
+    #[serde(default)]
+    pub properties: serde_json::Map<String, Value>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl GraphMeta {
+    /// `(attribute, value)` pairs for the fields that are set, in export order.
+    fn attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attrs = Vec::new();
+        if !self.properties.is_empty() {
+            attrs.push((
+                "properties",
+                Value::Object(self.properties.clone()).to_string(),
+            ));
+        }
+        if let Some(ts) = self.created_at {
+            attrs.push(("created_at", ts.to_rfc3339()));
+        }
+        if let Some(ts) = self.updated_at {
+            attrs.push(("updated_at", ts.to_rfc3339()));
+        }
+        attrs
+    }
+
+    /// Rebuild from parsed attributes; malformed values are dropped, not fatal.
+    fn from_attributes(get: impl Fn(&str) -> Option<String>) -> Self {
+        let timestamp = |key: &str| {
+            get(key)
+                .and_then(|raw| DateTime::parse_from_rfc3339(raw.trim()).ok())
+                .map(|ts| ts.with_timezone(&Utc))
+        };
+        Self {
+            properties: get("properties")
+                .and_then(|raw| serde_json::from_str(&raw).ok())
+                .unwrap_or_default(),
+            created_at: timestamp("created_at"),
+            updated_at: timestamp("updated_at"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub node_type: String,
+    pub label: String,
+    #[serde(flatten)]
+    pub meta: GraphMeta,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub label: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(flatten)]
+    pub meta: GraphMeta,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    Graphml,
+    Dot,
+    Cypher,
+}
+
+impl GraphFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Graphml => "application/graphml+xml; charset=utf-8",
+            Self::Dot => "text/vnd.graphviz; charset=utf-8",
+            Self::Cypher => "text/plain; charset=utf-8",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Graphml => "graphml",
+            Self::Dot => "dot",
+            Self::Cypher => "cypher",
+        }
+    }
+}
+
+/// How imported rows interact with the existing graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Keep existing nodes untouched, only add new ones.
+    #[default]
+    Skip,
+    /// Update type/label of nodes whose id already exists and merge their properties.
+    Overwrite,
+    /// Wipe the graph and load the import as-is.
+    Replace,
+}
+
+type MetaColumns = (String, DateTime<Utc>, DateTime<Utc>);
+
+fn meta_from_row((properties, created_at, updated_at): MetaColumns) -> GraphMeta {
+    GraphMeta {
+        properties: serde_json::from_str(&properties).unwrap_or_default(),
+        created_at: Some(created_at),
+        updated_at: Some(updated_at),
+    }
+}
+
+pub async fn load_graph(db: &sqlx::PgPool) -> Result<GraphSnapshot, sqlx::Error> {
+    let nodes: Vec<(String, String, String, String, DateTime<Utc>, DateTime<Utc>)> =
+        sqlx::query_as(
+            "SELECT id, node_type, label, properties::text, created_at, updated_at \
+             FROM gh_knowledge_nodes ORDER BY id",
+        )
+        .fetch_all(db)
+        .await?;
+    let edges: Vec<(
+        String,
+        String,
+        String,
+        f64,
+        String,
+        DateTime<Utc>,
+        DateTime<Utc>,
+    )> = sqlx::query_as(
+        "SELECT source, target, label, weight, properties::text, created_at, updated_at \
+         FROM gh_knowledge_edges ORDER BY source, target, label",
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(GraphSnapshot {
+        nodes: nodes
+            .into_iter()
+            .map(
+                |(id, node_type, label, props, created, updated)| GraphNode {
+                    id,
+                    node_type,
+                    label,
+                    meta: meta_from_row((props, created, updated)),
+                },
+            )
+            .collect(),
+        edges: edges
+            .into_iter()
+            .map(
+                |(source, target, label, weight, props, created, updated)| GraphEdge {
+                    source,
+                    target,
+                    label,
+                    weight,
+                    meta: meta_from_row((props, created, updated)),
+                },
+            )
+            .collect(),
+    })
+}
+
+pub fn export_graph(graph: &GraphSnapshot, format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Graphml => to_graphml(graph),
+        GraphFormat::Dot => to_dot(graph),
+        GraphFormat::Cypher => to_cypher(graph),
+    }
+}
+
+pub fn parse_graph(input: &str, format: GraphFormat) -> GraphSnapshot {
+    match format {
+        GraphFormat::Graphml => from_graphml(input),
+        GraphFormat::Dot => from_dot(input),
+        GraphFormat::Cypher => from_cypher(input),
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Escape for double-quoted DOT ids/attributes.
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escape for single-quoted Cypher string literals.
+fn cypher_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\n', "\\n")
+}
+
+/// Reverse of `dot_escape` / `cypher_escape`.
+fn backslash_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn to_graphml(graph: &GraphSnapshot) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+         <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>\n  \
+         <key id=\"label\" for=\"all\" attr.name=\"label\" attr.type=\"string\"/>\n  \
+         <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n  \
+         <key id=\"properties\" for=\"all\" attr.name=\"properties\" attr.type=\"string\"/>\n  \
+         <key id=\"created_at\" for=\"all\" attr.name=\"created_at\" attr.type=\"string\"/>\n  \
+         <key id=\"updated_at\" for=\"all\" attr.name=\"updated_at\" attr.type=\"string\"/>\n  \
+         <graph id=\"gh_knowledge\" edgedefault=\"directed\">\n",
+    );
+    for n in &graph.nodes {
+        out.push_str(&format!(
+            "    <node id=\"{}\"><data key=\"type\">{}</data><data key=\"label\">{}</data>{}</node>\n",
+            xml_escape(&n.id),
+            xml_escape(&n.node_type),
+            xml_escape(&n.label),
+            graphml_meta(&n.meta)
+        ));
+    }
+    for e in &graph.edges {
+        out.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"label\">{}</data>\
+             <data key=\"weight\">{}</data>{}</edge>\n",
+            xml_escape(&e.source),
+            xml_escape(&e.target),
+            xml_escape(&e.label),
+            e.weight,
+            graphml_meta(&e.meta)
+        ));
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn graphml_meta(meta: &GraphMeta) -> String {
+    meta.attributes()
+        .into_iter()
+        .map(|(key, value)| format!("<data key=\"{}\">{}</data>", key, xml_escape(&value)))
+        .collect()
+}
+
+fn dot_meta(meta: &GraphMeta) -> String {
+    meta.attributes()
+        .into_iter()
+        .map(|(key, value)| format!(", {}=\"{}\"", key, dot_escape(&value)))
+        .collect()
+}
+
+fn cypher_meta(meta: &GraphMeta) -> String {
+    meta.attributes()
+        .into_iter()
+        .map(|(key, value)| format!(", {}: '{}'", key, cypher_escape(&value)))
+        .collect()
+}
+
+fn to_dot(graph: &GraphSnapshot) -> String {
+    let mut out = String::from("digraph knowledge {\n");
+    for n in &graph.nodes {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\", type=\"{}\"{}];\n",
+            dot_escape(&n.id),
+            dot_escape(&n.label),
+            dot_escape(&n.node_type),
+            dot_meta(&n.meta)
+        ));
+    }
+    for e in &graph.edges {
+        out.push_str(&format!(
+            "  \"{}\" -> \"{}\" [label=\"{}\", weight=\"{}\"{}];\n",
+            dot_escape(&e.source),
+            dot_escape(&e.target),
+            dot_escape(&e.label),
+            e.weight,
+            dot_meta(&e.meta)
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn to_cypher(graph: &GraphSnapshot) -> String {
+    let mut out = String::new();
+    for n in &graph.nodes {
+        out.push_str(&format!(
+            "CREATE (:KnowledgeNode {{id: '{}', type: '{}', label: '{}'{}}});\n",
+            cypher_escape(&n.id),
+            cypher_escape(&n.node_type),
+            cypher_escape(&n.label),
+            cypher_meta(&n.meta)
+        ));
+    }
+    for e in &graph.edges {
+        out.push_str(&format!(
+            "MATCH (a:KnowledgeNode {{id: '{}'}}), (b:KnowledgeNode {{id: '{}'}}) \
+             CREATE (a)-[:RELATED {{label: '{}', weight: {}{}}}]->(b);\n",
+            cypher_escape(&e.source),
+            cypher_escape(&e.target),
+            cypher_escape(&e.label),
+            e.weight,
+            cypher_meta(&e.meta)
+        ));
+    }
+    out
+}
+
+static XML_ATTR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([\w.:-]+)\s*=\s*"([^"]*)""#).expect("valid regex"));
+static GRAPHML_KEY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<key\b([^>]*?)/?>").expect("valid regex"));
+static GRAPHML_ELEMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<(node|edge)\b([^>]*?)(?:/>|>(.*?)</(?:node|edge)>)").expect("valid regex")
+});
+static GRAPHML_DATA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?s)<data\s+key\s*=\s*"([^"]*)"\s*>(.*?)</data>"#).expect("valid regex")
+});
+
+fn xml_attrs(fragment: &str) -> HashMap<String, String> {
+    XML_ATTR_RE
+        .captures_iter(fragment)
+        .map(|c| (c[1].to_string(), xml_unescape(&c[2])))
+        .collect()
+}
+
+/// Parse GraphML. `<key>` declarations are honoured, so files exported by
+/// Gephi/yEd (`d0`, `d1`, ... key ids) map onto `type` / `label` by `attr.name`.
+fn from_graphml(input: &str) -> GraphSnapshot {
+    let key_names: HashMap<String, String> = GRAPHML_KEY_RE
+        .captures_iter(input)
+        .filter_map(|c| {
+            let attrs = xml_attrs(&c[1]);
+            Some((attrs.get("id")?.clone(), attrs.get("attr.name")?.clone()))
+        })
+        .collect();
+
+    let mut graph = GraphSnapshot::default();
+    for c in GRAPHML_ELEMENT_RE.captures_iter(input) {
+        let attrs = xml_attrs(&c[2]);
+        let data: HashMap<String, String> = c
+            .get(3)
+            .map(|inner| {
+                GRAPHML_DATA_RE
+                    .captures_iter(inner.as_str())
+                    .map(|d| {
+                        let key = key_names
+                            .get(&d[1])
+                            .cloned()
+                            .unwrap_or_else(|| d[1].to_string());
+                        (key, xml_unescape(d[2].trim()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if &c[1] == "node" {
+            let Some(id) = attrs.get("id").filter(|id| !id.is_empty()) else {
+                continue;
+            };
+            graph.nodes.push(GraphNode {
+                id: id.clone(),
+                node_type: data
+                    .get("type")
+                    .cloned()
+                    .unwrap_or_else(|| "concept".to_string()),
+                label: data.get("label").cloned().unwrap_or_else(|| id.clone()),
+                meta: GraphMeta::from_attributes(|key| data.get(key).cloned()),
+            });
+        } else if let (Some(source), Some(target)) = (attrs.get("source"), attrs.get("target")) {
+            graph.edges.push(GraphEdge {
+                source: source.clone(),
+                target: target.clone(),
+                label: data
+                    .get("label")
+                    .cloned()
+                    .unwrap_or_else(|| "related_to".to_string()),
+                weight: parse_weight(data.get("weight").map(String::as_str)),
+                meta: GraphMeta::from_attributes(|key| data.get(key).cloned()),
+            });
+        }
+    }
+    graph
+}
+
+fn parse_weight(raw: Option<&str>) -> f64 {
+    raw.and_then(|w| w.trim().parse::<f64>().ok())
+        .filter(|w| w.is_finite())
+        .unwrap_or(1.0)
+}
+
+static DOT_EDGE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*"((?:[^"\\]|\\.)*)"\s*->\s*"((?:[^"\\]|\\.)*)"\s*(?:\[(.*)\])?\s*;?\s*$"#)
+        .expect("valid regex")
+});
+static DOT_NODE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*"((?:[^"\\]|\\.)*)"\s*(?:\[(.*)\])?\s*;?\s*$"#).expect("valid regex")
+});
+static DOT_ATTR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(\w+)\s*=\s*"((?:[^"\\]|\\.)*)""#).expect("valid regex"));
+
+fn dot_attrs(fragment: Option<regex::Match<'_>>) -> HashMap<String, String> {
+    fragment
+        .map(|m| {
+            DOT_ATTR_RE
+                .captures_iter(m.as_str())
+                .map(|c| (c[1].to_string(), backslash_unescape(&c[2])))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse the line-oriented DOT subset produced by `to_dot` (quoted ids, one statement per line).
+fn from_dot(input: &str) -> GraphSnapshot {
+    let mut graph = GraphSnapshot::default();
+    for line in input.lines() {
+        if let Some(c) = DOT_EDGE_RE.captures(line) {
+            let attrs = dot_attrs(c.get(3));
+            graph.edges.push(GraphEdge {
+                source: backslash_unescape(&c[1]),
+                target: backslash_unescape(&c[2]),
+                label: attrs
+                    .get("label")
+                    .cloned()
+                    .unwrap_or_else(|| "related_to".to_string()),
+                weight: parse_weight(attrs.get("weight").map(String::as_str)),
+                meta: GraphMeta::from_attributes(|key| attrs.get(key).cloned()),
+            });
+        } else if let Some(c) = DOT_NODE_RE.captures(line) {
+            let id = backslash_unescape(&c[1]);
+            if id.is_empty() {
+                continue;
+            }
+            let attrs = dot_attrs(c.get(2));
+            graph.nodes.push(GraphNode {
+                node_type: attrs
+                    .get("type")
+                    .cloned()
+                    .unwrap_or_else(|| "concept".to_string()),
+                label: attrs.get("label").cloned().unwrap_or_else(|| id.clone()),
+                meta: GraphMeta::from_attributes(|key| attrs.get(key).cloned()),
+                id,
+            });
+        }
+    }
+    graph
+}
+
+static CYPHER_PROP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\w+)\s*:\s*'((?:[^'\\]|\\.)*)'").expect("valid regex"));
+static CYPHER_WEIGHT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bweight\s*:\s*([-+0-9.eE]+)").expect("valid regex"));
+
+/// Parse the `CREATE (:Label {..})` / `MATCH .. CREATE (a)-[..]->(b)` script produced by `to_cypher`.
+fn from_cypher(input: &str) -> GraphSnapshot {
+    let mut graph = GraphSnapshot::default();
+    for statement in input.split(";\n").map(str::trim) {
+        let props: Vec<(String, String)> = CYPHER_PROP_RE
+            .captures_iter(statement)
+            .map(|c| (c[1].to_string(), backslash_unescape(&c[2])))
+            .collect();
+        let get = |key: &str| props.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+
+        if statement.starts_with("MATCH") {
+            let ids: Vec<&String> = props
+                .iter()
+                .filter(|(k, _)| k == "id")
+                .map(|(_, v)| v)
+                .collect();
+            if let [source, target, ..] = ids.as_slice() {
+                graph.edges.push(GraphEdge {
+                    source: (*source).clone(),
+                    target: (*target).clone(),
+                    label: get("label").unwrap_or_else(|| "related_to".to_string()),
+                    weight: parse_weight(
+                        CYPHER_WEIGHT_RE
+                            .captures(statement)
+                            .and_then(|c| c.get(1))
+                            .map(|m| m.as_str()),
+                    ),
+                    meta: GraphMeta::from_attributes(get),
+                });
+            }
+        } else if statement.starts_with("CREATE")
+            && let Some(id) = get("id").filter(|id| !id.is_empty())
+        {
+            graph.nodes.push(GraphNode {
+                node_type: get("type").unwrap_or_else(|| "concept".to_string()),
+                label: get("label").unwrap_or_else(|| id.clone()),
+                meta: GraphMeta::from_attributes(get),
+                id,
+            });
+        }
+    }
+    graph
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportOutcome {
+    pub nodes_imported: u64,
+    pub edges_imported: u64,
+    /// Edges whose endpoints are missing from both the import and the existing graph.
+    pub edges_skipped: u64,
+}
+
+/// Write `graph` into the knowledge tables in one transaction.
+pub async fn import_graph(
+    db: &sqlx::PgPool,
+    graph: &GraphSnapshot,
+    strategy: MergeStrategy,
+) -> Result<ImportOutcome, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    if strategy == MergeStrategy::Replace {
+        sqlx::query("DELETE FROM gh_knowledge_edges")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM gh_knowledge_nodes")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Missing timestamps default to NOW(); Overwrite merges properties so a
+    // foreign file without them does not wipe what the graph already knows.
+    let node_conflict = if strategy == MergeStrategy::Overwrite {
+        "ON CONFLICT (id) DO UPDATE SET node_type = EXCLUDED.node_type, label = EXCLUDED.label, \
+         properties = gh_knowledge_nodes.properties || EXCLUDED.properties, \
+         updated_at = EXCLUDED.updated_at"
+    } else {
+        "ON CONFLICT (id) DO NOTHING"
+    };
+    let node_sql = format!(
+        "INSERT INTO gh_knowledge_nodes (id, node_type, label, properties, created_at, updated_at) \
+         VALUES ($1, $2, $3, $4::jsonb, COALESCE($5, NOW()), COALESCE($6, NOW())) {}",
+        node_conflict
+    );
+    let mut nodes_imported = 0;
+    for node in &graph.nodes {
+        let inserted = sqlx::query(&node_sql)
+            .bind(&node.id)
+            .bind(&node.node_type)
+            .bind(&node.label)
+            .bind(Value::Object(node.meta.properties.clone()).to_string())
+            .bind(node.meta.created_at)
+            .bind(node.meta.updated_at)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            > 0;
+        nodes_imported += u64::from(inserted);
+    }
+
+    let (mut edges_imported, mut edges_skipped) = (0, 0);
+    for edge in &graph.edges {
+        let inserted = sqlx::query(
+            "INSERT INTO gh_knowledge_edges \
+             (source, target, label, weight, properties, created_at, updated_at) \
+             SELECT $1, $2, $3, $4, $5::jsonb, COALESCE($6, NOW()), COALESCE($7, NOW()) \
+             WHERE EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $1) \
+               AND EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $2) \
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(&edge.source)
+        .bind(&edge.target)
+        .bind(&edge.label)
+        .bind(edge.weight)
+        .bind(Value::Object(edge.meta.properties.clone()).to_string())
+        .bind(edge.meta.created_at)
+        .bind(edge.meta.updated_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if inserted {
+            edges_imported += 1;
+        } else {
+            edges_skipped += 1;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(ImportOutcome {
+        nodes_imported,
+        edges_imported,
+        edges_skipped,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    pub format: GraphFormat,
+}
+
+/// GET /api/knowledge/export?format=graphml|dot|cypher — Download the knowledge graph.
+pub async fn export_knowledge_graph(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let graph = load_graph(&state.db).await.map_err(|e| {
+        tracing::error!("knowledge_graph: export failed: {}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+    })?;
+    let disposition = format!(
+        "attachment; filename=\"knowledge-graph.{}\"",
+        params.format.extension()
+    );
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                params.format.content_type().to_string(),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        export_graph(&graph, params.format),
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    pub format: GraphFormat,
+    /// Inline file content. Either this or `path` is required.
+    pub content: Option<String>,
+    /// Local file to read instead of `content`.
+    pub path: Option<String>,
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+}
+
+fn too_large() -> (StatusCode, Json<Value>) {
+    api_error(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("import exceeds {} bytes", MAX_IMPORT_BYTES),
+    )
+}
+
+/// Read an import file without ever buffering more than MAX_IMPORT_BYTES + 1.
+async fn read_import_file(path: &str) -> Result<String, (StatusCode, Json<Value>)> {
+    use tokio::io::AsyncReadExt;
+
+    let unreadable = |e: std::io::Error| {
+        api_error(
+            StatusCode::BAD_REQUEST,
+            format!("cannot read '{}': {}", path, e),
+        )
+    };
+    let file = tokio::fs::File::open(path).await.map_err(unreadable)?;
+    if file.metadata().await.map_err(unreadable)?.len() > MAX_IMPORT_BYTES as u64 {
+        return Err(too_large());
+    }
+    // The file may grow after the metadata check; `take` still bounds the read.
+    let mut content = String::new();
+    file.take(MAX_IMPORT_BYTES as u64 + 1)
+        .read_to_string(&mut content)
+        .await
+        .map_err(unreadable)?;
+    Ok(content)
+}
+
+/// POST /api/knowledge/import — Seed or merge the knowledge graph from GraphML, DOT or Cypher.
+pub async fn import_knowledge_graph(
+    State(state): State<AppState>,
+    Json(req): Json<ImportRequest>,
+) -> (StatusCode, Json<Value>) {
+    let content = match (req.content, req.path) {
+        (Some(content), _) => content,
+        (None, Some(path)) => match read_import_file(&path).await {
+            Ok(content) => content,
+            Err(e) => return e,
+        },
+        (None, None) => {
+            return api_error(
+                StatusCode::BAD_REQUEST,
+                "either 'content' or 'path' is required",
+            );
+        }
+    };
+    if content.len() > MAX_IMPORT_BYTES {
+        return too_large();
+    }
+
+    let graph = parse_graph(&content, req.format);
+    if graph.nodes.is_empty() && graph.edges.is_empty() {
+        return api_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "no nodes or edges found in input",
+        );
+    }
+
+    match import_graph(&state.db, &graph, req.merge_strategy).await {
+        Ok(outcome) => {
+            tracing::info!(
+                nodes = outcome.nodes_imported,
+                edges = outcome.edges_imported,
+                "knowledge_graph: import complete"
+            );
+            (StatusCode::OK, Json(json!(outcome)))
+        }
+        Err(e) => {
+            tracing::error!("knowledge_graph: import failed: {}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+// ── Node / edge maintenance ─────────────────────────────────────────────────
+
+fn db_error(context: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    tracing::error!("knowledge_graph: {}: {}", context, e);
+    api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+fn node_not_found(id: &str) -> (StatusCode, Json<Value>) {
+    api_error(StatusCode::NOT_FOUND, format!("node '{}' not found", id))
+}
+
+type KgResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+/// GET /api/knowledge/nodes/{id} — Node with properties and its incident (weighted) edges.
+pub async fn get_knowledge_node(State(state): State<AppState>, Path(id): Path<String>) -> KgResult {
+    let node: Option<Value> = sqlx::query_scalar(
+        "SELECT jsonb_build_object('id', id, 'node_type', node_type, 'label', label, \
+         'properties', properties, 'created_at', created_at, 'updated_at', updated_at) \
+         FROM gh_knowledge_nodes WHERE id = $1",
+    )
+    .bind(&id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("node lookup failed", e))?;
+    let mut node = node.ok_or_else(|| node_not_found(&id))?;
+
+    let edges: Vec<Value> = sqlx::query_scalar(
+        "SELECT jsonb_build_object('source', source, 'target', target, 'label', label, \
+         'weight', weight, 'properties', properties, 'created_at', created_at, \
+         'updated_at', updated_at) \
+         FROM gh_knowledge_edges WHERE source = $1 OR target = $1 ORDER BY weight DESC",
+    )
+    .bind(&id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("edge lookup failed", e))?;
+    node["edges"] = json!(edges);
+    Ok(Json(node))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateNodeRequest {
+    pub label: Option<String>,
+    pub node_type: Option<String>,
+    /// JSON object merged into the stored properties (a `null` value removes the key).
+    pub properties: Option<serde_json::Map<String, Value>>,
+    /// Replace the stored properties instead of merging into them.
+    #[serde(default)]
+    pub replace_properties: bool,
+}
+
+/// PATCH /api/knowledge/nodes/{id} — Update label, type and/or properties.
+pub async fn update_knowledge_node(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateNodeRequest>,
+) -> KgResult {
+    let properties = req.properties.map(Value::Object);
+    let row: Option<Value> = sqlx::query_scalar(
+        "UPDATE gh_knowledge_nodes SET \
+           label = COALESCE($2, label), \
+           node_type = COALESCE($3, node_type), \
+           properties = CASE \
+             WHEN $4::jsonb IS NULL THEN properties \
+             WHEN $5 THEN jsonb_strip_nulls($4::jsonb) \
+             ELSE jsonb_strip_nulls(properties || $4::jsonb) END, \
+           updated_at = NOW() \
+         WHERE id = $1 \
+         RETURNING jsonb_build_object('id', id, 'node_type', node_type, 'label', label, \
+           'properties', properties, 'created_at', created_at, 'updated_at', updated_at)",
+    )
+    .bind(&id)
+    .bind(
+        req.label
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty()),
+    )
+    .bind(
+        req.node_type
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty()),
+    )
+    .bind(properties)
+    .bind(req.replace_properties)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("node update failed", e))?;
+    row.map(Json).ok_or_else(|| node_not_found(&id))
+}
+
+/// DELETE /api/knowledge/nodes/{id} — Remove a node together with every edge touching it.
+pub async fn delete_knowledge_node(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> KgResult {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    let edges_removed =
+        sqlx::query("DELETE FROM gh_knowledge_edges WHERE source = $1 OR target = $1")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| db_error("edge cleanup failed", e))?
+            .rows_affected();
+    let nodes_removed = sqlx::query("DELETE FROM gh_knowledge_nodes WHERE id = $1")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| db_error("node delete failed", e))?
+        .rows_affected();
+    if nodes_removed == 0 {
+        return Err(node_not_found(&id));
+    }
+    tx.commit()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    Ok(Json(
+        json!({ "deleted": id, "edges_removed": edges_removed }),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertEdgeRequest {
+    pub source: String,
+    pub target: String,
+    pub label: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default)]
+    pub properties: serde_json::Map<String, Value>,
+}
+
+/// POST /api/knowledge/edges — Create an edge or update weight/properties of an existing one.
+pub async fn upsert_knowledge_edge(
+    State(state): State<AppState>,
+    Json(req): Json<UpsertEdgeRequest>,
+) -> KgResult {
+    if !req.weight.is_finite() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "weight must be a finite number",
+        ));
+    }
+    let row: Option<Value> = sqlx::query_scalar(
+        "INSERT INTO gh_knowledge_edges (source, target, label, weight, properties) \
+         SELECT $1, $2, $3, $4, $5 \
+         WHERE EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $1) \
+           AND EXISTS (SELECT 1 FROM gh_knowledge_nodes WHERE id = $2) \
+         ON CONFLICT (source, target, label) DO UPDATE SET \
+           weight = EXCLUDED.weight, \
+           properties = gh_knowledge_edges.properties || EXCLUDED.properties, \
+           updated_at = NOW() \
+         RETURNING jsonb_build_object('source', source, 'target', target, 'label', label, \
+           'weight', weight, 'properties', properties, 'created_at', created_at, \
+           'updated_at', updated_at)",
+    )
+    .bind(&req.source)
+    .bind(&req.target)
+    .bind(&req.label)
+    .bind(req.weight)
+    .bind(Value::Object(req.properties))
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("edge upsert failed", e))?;
+    row.map(Json).ok_or_else(|| {
+        api_error(
+            StatusCode::NOT_FOUND,
+            "source or target node does not exist",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_normalises_labels() {
+        assert_eq!(slugify("Jaskier Core"), "jaskier-core");
+        assert_eq!(
+            slugify("  PostgreSQL 17 (pgvector)! "),
+            "postgresql-17-pgvector"
+        );
+        assert_eq!(slugify("---"), "");
+    }
+
+    fn sample() -> GraphSnapshot {
+        GraphSnapshot {
+            nodes: vec![
+                GraphNode {
+                    id: "rust".to_string(),
+                    node_type: "technology".to_string(),
+                    label: "Rust <2024> & \"edition\"".to_string(),
+                    meta: GraphMeta {
+                        properties: json!({ "aliases": ["rustlang"], "note": "it's `fast`; \\o/" })
+                            .as_object()
+                            .cloned()
+                            .expect("object"),
+                        created_at: Some(timestamp("2026-09-01T08:30:00.123456Z")),
+                        updated_at: Some(timestamp("2026-10-02T17:45:10Z")),
+                    },
+                },
+                GraphNode {
+                    id: "geminihydra".to_string(),
+                    node_type: "project".to_string(),
+                    label: "Gemini's Hydra".to_string(),
+                    meta: GraphMeta::default(),
+                },
+            ],
+            edges: vec![GraphEdge {
+                source: "geminihydra".to_string(),
+                target: "rust".to_string(),
+                label: "uses".to_string(),
+                weight: 0.75,
+                meta: GraphMeta {
+                    properties: json!({ "since": 2024 })
+                        .as_object()
+                        .cloned()
+                        .expect("object"),
+                    created_at: Some(timestamp("2026-09-03T12:00:00+02:00")),
+                    updated_at: None,
+                },
+            }],
+        }
+    }
+
+    fn timestamp(raw: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(raw)
+            .expect("valid timestamp")
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let graph = sample();
+        for format in [GraphFormat::Graphml, GraphFormat::Dot, GraphFormat::Cypher] {
+            let exported = export_graph(&graph, format);
+            assert_eq!(parse_graph(&exported, format), graph, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn json_snapshot_round_trips_metadata() {
+        let graph = sample();
+        let encoded = serde_json::to_string(&graph).expect("serialize");
+        let decoded: GraphSnapshot = serde_json::from_str(&encoded).expect("deserialize");
+        assert_eq!(decoded, graph);
+
+        // Older snapshots without metadata still deserialize.
+        let legacy: GraphNode =
+            serde_json::from_value(json!({ "id": "a", "node_type": "concept", "label": "A" }))
+                .expect("legacy node");
+        assert_eq!(legacy.meta, GraphMeta::default());
+    }
+
+    #[test]
+    fn graphml_maps_foreign_key_ids() {
+        let input = r#"<graphml>
+  <key id="d0" for="node" attr.name="label" attr.type="string"/>
+  <key id="d1" for="node" attr.name="type" attr.type="string"/>
+  <graph edgedefault="directed">
+    <node id="n0"><data key="d0">Alpha</data><data key="d1">person</data></node>
+    <node id="n1"/>
+    <edge id="e0" source="n0" target="n1"/>
+  </graph>
+</graphml>"#;
+        let graph = from_graphml(input);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes[0].label, "Alpha");
+        assert_eq!(graph.nodes[0].node_type, "person");
+        assert_eq!(graph.nodes[1].label, "n1");
+        assert_eq!(graph.edges[0].label, "related_to");
+        assert_eq!(graph.edges[0].weight, 1.0);
+    }
+}
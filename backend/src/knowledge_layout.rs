@@ -0,0 +1,364 @@
+// ---------------------------------------------------------------------------
+// knowledge_layout.rs — Server-side layouts for the knowledge-graph viewer
+//
+// `GET /api/knowledge/view` filters the graph (node type, label search),
+// keeps the `max_nodes` best-connected nodes, and returns them with x/y
+// positions in a 1000×1000 box and degree-based sizes, so the frontend can
+// draw large graphs without running its own simulation. Layouts are
+// deterministic: the same graph always renders the same way.
+// ---------------------------------------------------------------------------
+
+use std::collections::{HashMap, VecDeque};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::knowledge_graph::{GraphEdge, GraphNode, GraphSnapshot};
+use crate::state::AppState;
+
+const DEFAULT_MAX_NODES: usize = 200;
+const MAX_NODES_LIMIT: usize = 1000;
+const CANVAS: f64 = 1000.0;
+const FORCE_ITERATIONS: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Force,
+    Hierarchical,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ViewParams {
+    #[serde(default)]
+    pub layout: Layout,
+    pub max_nodes: Option<usize>,
+    /// Only nodes of this type.
+    pub node_type: Option<String>,
+    /// Case-insensitive label substring.
+    pub q: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionedNode {
+    pub id: String,
+    pub node_type: String,
+    pub label: String,
+    pub degree: usize,
+    pub size: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Apply filters and keep the `max_nodes` highest-degree nodes with their edges.
+fn select(
+    graph: GraphSnapshot,
+    params: &ViewParams,
+    max_nodes: usize,
+) -> (Vec<GraphNode>, Vec<GraphEdge>, usize) {
+    let q = params.q.as_deref().map(str::to_lowercase);
+    let mut nodes: Vec<GraphNode> = graph
+        .nodes
+        .into_iter()
+        .filter(|n| params.node_type.as_deref().is_none_or(|t| n.node_type == t))
+        .filter(|n| {
+            q.as_deref()
+                .is_none_or(|q| n.label.to_lowercase().contains(q))
+        })
+        .collect();
+    let matched = nodes.len();
+
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for e in &graph.edges {
+        *degree.entry(e.source.as_str()).or_default() += 1;
+        *degree.entry(e.target.as_str()).or_default() += 1;
+    }
+    nodes.sort_by(|a, b| {
+        let (da, db) = (degree.get(a.id.as_str()), degree.get(b.id.as_str()));
+        db.cmp(&da).then(a.id.cmp(&b.id))
+    });
+    nodes.truncate(max_nodes);
+
+    let kept: std::collections::HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    let edges: Vec<GraphEdge> = graph
+        .edges
+        .iter()
+        .filter(|e| kept.contains(e.source.as_str()) && kept.contains(e.target.as_str()))
+        .cloned()
+        .collect();
+    (nodes, edges, matched)
+}
+
+/// Fruchterman–Reingold from evenly spaced starting points on a circle.
+fn force_layout(n: usize, edges: &[(usize, usize)]) -> Vec<(f64, f64)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let center = CANVAS / 2.0;
+    let mut pos: Vec<(f64, f64)> = (0..n)
+        .map(|i| {
+            let angle = i as f64 / n as f64 * std::f64::consts::TAU;
+            (
+                center + center * 0.8 * angle.cos(),
+                center + center * 0.8 * angle.sin(),
+            )
+        })
+        .collect();
+    let k = (CANVAS * CANVAS / n as f64).sqrt();
+    let mut temperature = CANVAS / 10.0;
+    let cooling = temperature / (FORCE_ITERATIONS as f64 + 1.0);
+
+    for _ in 0..FORCE_ITERATIONS {
+        let mut disp = vec![(0.0_f64, 0.0_f64); n];
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let (dx, dy) = (pos[i].0 - pos[j].0, pos[i].1 - pos[j].1);
+                let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+                let force = k * k / dist;
+                let (fx, fy) = (dx / dist * force, dy / dist * force);
+                disp[i].0 += fx;
+                disp[i].1 += fy;
+                disp[j].0 -= fx;
+                disp[j].1 -= fy;
+            }
+        }
+        for &(a, b) in edges {
+            let (dx, dy) = (pos[a].0 - pos[b].0, pos[a].1 - pos[b].1);
+            let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+            let force = dist * dist / k;
+            let (fx, fy) = (dx / dist * force, dy / dist * force);
+            disp[a].0 -= fx;
+            disp[a].1 -= fy;
+            disp[b].0 += fx;
+            disp[b].1 += fy;
+        }
+        for (p, d) in pos.iter_mut().zip(&disp) {
+            let len = (d.0 * d.0 + d.1 * d.1).sqrt().max(0.01);
+            let step = len.min(temperature);
+            p.0 = (p.0 + d.0 / len * step).clamp(0.0, CANVAS);
+            p.1 = (p.1 + d.1 / len * step).clamp(0.0, CANVAS);
+        }
+        temperature -= cooling;
+    }
+    pos
+}
+
+/// Layered layout: BFS from the best-connected unvisited node of each component.
+fn hierarchical_layout(n: usize, edges: &[(usize, usize)]) -> Vec<(f64, f64)> {
+    let mut adjacency = vec![Vec::new(); n];
+    for &(a, b) in edges {
+        adjacency[a].push(b);
+        adjacency[b].push(a);
+    }
+    let mut layer = vec![usize::MAX; n];
+    // Nodes arrive sorted by degree, so index order picks the hubs as roots.
+    for root in 0..n {
+        if layer[root] != usize::MAX {
+            continue;
+        }
+        layer[root] = 0;
+        let mut queue = VecDeque::from([root]);
+        while let Some(u) = queue.pop_front() {
+            for &v in &adjacency[u] {
+                if layer[v] == usize::MAX {
+                    layer[v] = layer[u] + 1;
+                    queue.push_back(v);
+                }
+            }
+        }
+    }
+
+    let depth = layer.iter().copied().max().map_or(1, |d| d + 1);
+    let mut per_layer: Vec<Vec<usize>> = vec![Vec::new(); depth];
+    for (i, &l) in layer.iter().enumerate() {
+        per_layer[l].push(i);
+    }
+    let mut pos = vec![(0.0, 0.0); n];
+    for (l, members) in per_layer.iter().enumerate() {
+        let y = CANVAS * (l as f64 + 0.5) / depth as f64;
+        for (slot, &i) in members.iter().enumerate() {
+            pos[i] = (CANVAS * (slot as f64 + 0.5) / members.len() as f64, y);
+        }
+    }
+    pos
+}
+
+/// Positioned nodes for `nodes`/`edges` in the requested layout.
+fn layout(nodes: Vec<GraphNode>, edges: &[GraphEdge], kind: Layout) -> Vec<PositionedNode> {
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.as_str(), i))
+        .collect();
+    let pairs: Vec<(usize, usize)> = edges
+        .iter()
+        .filter_map(|e| {
+            Some((
+                *index.get(e.source.as_str())?,
+                *index.get(e.target.as_str())?,
+            ))
+        })
+        .filter(|(a, b)| a != b)
+        .collect();
+    let mut degree = vec![0usize; nodes.len()];
+    for &(a, b) in &pairs {
+        degree[a] += 1;
+        degree[b] += 1;
+    }
+    let positions = match kind {
+        Layout::Force => force_layout(nodes.len(), &pairs),
+        Layout::Hierarchical => hierarchical_layout(nodes.len(), &pairs),
+    };
+
+    nodes
+        .into_iter()
+        .zip(positions)
+        .zip(degree)
+        .map(|((n, (x, y)), degree)| PositionedNode {
+            id: n.id,
+            node_type: n.node_type,
+            label: n.label,
+            degree,
+            size: 4.0 + 2.0 * (degree as f64).sqrt(),
+            x: x.round(),
+            y: y.round(),
+        })
+        .collect()
+}
+
+/// GET /api/knowledge/view?layout=force|hierarchical&max_nodes=200&node_type=&q=
+pub async fn knowledge_graph_view(
+    State(state): State<AppState>,
+    Query(params): Query<ViewParams>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let graph = crate::knowledge_graph::load_graph(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("knowledge_layout: failed to load graph: {}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        })?;
+    let max_nodes = params
+        .max_nodes
+        .unwrap_or(DEFAULT_MAX_NODES)
+        .clamp(1, MAX_NODES_LIMIT);
+    let (nodes, edges, matched) = select(graph, &params, max_nodes);
+    let kind = params.layout;
+    // The O(n²) force simulation is CPU-bound; keep it off the async workers.
+    let (nodes, edges) = tokio::task::spawn_blocking(move || {
+        let positioned = layout(nodes, &edges, kind);
+        (positioned, edges)
+    })
+    .await
+    .map_err(|e| {
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("layout failed: {}", e),
+        )
+    })?;
+
+    Ok(Json(json!({
+        "layout": match kind {
+            Layout::Force => "force",
+            Layout::Hierarchical => "hierarchical",
+        },
+        "width": CANVAS,
+        "height": CANVAS,
+        "total_matched": matched,
+        "truncated": matched > nodes.len(),
+        "nodes": nodes,
+        "edges": edges,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, node_type: &str) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            node_type: node_type.to_string(),
+            label: id.to_uppercase(),
+            meta: Default::default(),
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> GraphEdge {
+        GraphEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            label: "uses".to_string(),
+            weight: 1.0,
+            meta: Default::default(),
+        }
+    }
+
+    fn sample() -> GraphSnapshot {
+        GraphSnapshot {
+            nodes: vec![
+                node("hub", "project"),
+                node("a", "technology"),
+                node("b", "technology"),
+                node("lonely", "concept"),
+            ],
+            edges: vec![edge("hub", "a"), edge("hub", "b")],
+        }
+    }
+
+    #[test]
+    fn selection_keeps_best_connected_nodes() {
+        let params = ViewParams {
+            layout: Layout::Force,
+            max_nodes: None,
+            node_type: None,
+            q: None,
+        };
+        let (nodes, edges, matched) = select(sample(), &params, 2);
+        assert_eq!(matched, 4);
+        assert_eq!(nodes[0].id, "hub");
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(edges.len(), 1);
+    }
+
+    #[test]
+    fn filters_by_type_and_label() {
+        let params = ViewParams {
+            layout: Layout::Force,
+            max_nodes: None,
+            node_type: Some("technology".to_string()),
+            q: Some("b".to_string()),
+        };
+        let (nodes, edges, _) = select(sample(), &params, 10);
+        assert_eq!(nodes.len(), 1);
+        assert!(edges.is_empty());
+    }
+
+    #[test]
+    fn layouts_stay_on_canvas_and_size_by_degree() {
+        let graph = sample();
+        for kind in [Layout::Force, Layout::Hierarchical] {
+            let positioned = layout(graph.nodes.clone(), &graph.edges, kind);
+            assert_eq!(positioned.len(), 4);
+            assert!(
+                positioned
+                    .iter()
+                    .all(|n| (0.0..=CANVAS).contains(&n.x) && (0.0..=CANVAS).contains(&n.y))
+            );
+            let hub = positioned.iter().find(|n| n.id == "hub").expect("hub");
+            assert_eq!(hub.degree, 2);
+            assert!(hub.size > positioned[3].size);
+        }
+    }
+
+    #[test]
+    fn hierarchical_puts_neighbours_one_layer_down() {
+        let positions = hierarchical_layout(3, &[(0, 1), (0, 2)]);
+        assert!(positions[1].1 > positions[0].1);
+        assert_eq!(positions[1].1, positions[2].1);
+    }
+}
@@ -4,20 +4,48 @@ pub mod a2a;
 pub mod analysis;
 pub mod audit;
 pub mod browser_proxy;
+pub mod capabilities;
 pub mod classify;
+pub mod code_index;
 pub mod context;
+pub mod crash_reports;
+pub mod data_dir;
 pub mod files;
+pub mod gemini_api;
+pub mod git;
 pub mod handlers;
+pub mod idle_sessions;
+pub mod image_gen;
+pub mod knowledge_extraction;
+pub mod knowledge_graph;
+pub mod knowledge_layout;
 pub mod mcp;
+pub mod memory_recall;
+pub mod memory_summary;
 pub mod model_registry;
 pub mod models;
 pub mod ocr;
+pub mod offline;
+pub mod patch;
+pub mod project_brief;
+pub mod projects;
 pub mod prompt;
+pub mod prompt_templates;
+pub mod quota;
+pub mod regenerate;
 pub mod sessions;
+pub mod shutdown;
 pub mod state;
+pub mod structured;
+pub mod support_bundle;
 pub mod system_monitor;
+pub mod token_budget;
 pub mod tool_defs;
+pub mod tool_registry;
 pub mod tools;
+pub mod usage;
+pub mod vector_store;
+pub mod vertex;
 pub mod watchdog;
 
 use axum::Router;
@@ -249,7 +277,21 @@ fn build_config(state: AppState) -> jaskier_core::router_builder::HydraRouterCon
 
         // App-specific protected routes
         app_protected_routes: Router::new()
-            .route("/api/gemini/models", get(handlers::gemini_models)),
+            .route("/api/gemini/models", get(handlers::gemini_models))
+            .merge(handlers::code_index_router(state.clone()))
+            .merge(handlers::git_router(state.clone()))
+            .merge(handlers::images_router(state.clone()))
+            .merge(handlers::knowledge_router(state.clone()))
+            .merge(handlers::memory_router(state.clone()))
+            .merge(handlers::offline_router(state.clone()))
+            .merge(handlers::projects_router(state.clone()))
+            .merge(handlers::prompts_router(state.clone()))
+            .merge(handlers::regenerate_router(state.clone()))
+            .merge(handlers::structured_router(state.clone()))
+            .merge(handlers::tokens_router(state.clone()))
+            .merge(handlers::tools_router(state.clone()))
+            .merge(handlers::usage_router(state.clone()))
+            .merge(handlers::vector_router(state.clone())),
 
         // ADK sidecar internal tool bridge
         internal_tool_route: Router::new()
@@ -281,6 +323,60 @@ fn sanitize_prom_label(s: &str) -> String {
         .collect()
 }
 
+/// Per-model Gemini usage families. Each family's HELP/TYPE header is followed
+/// by all of its samples, as the exposition format requires.
+fn gemini_usage_metrics(per_model: &[(String, i64, i64, i64, i64)]) -> String {
+    if per_model.is_empty() {
+        return String::new();
+    }
+    let models: Vec<String> = per_model
+        .iter()
+        .map(|(model, ..)| sanitize_prom_label(model))
+        .collect();
+    let mut out = String::from(
+        "# HELP gemini_requests_total Gemini requests by model\n\
+         # TYPE gemini_requests_total counter\n",
+    );
+    for (m, (_, requests, ..)) in models.iter().zip(per_model) {
+        out.push_str(&format!(
+            "gemini_requests_total{{model=\"{}\"}} {}\n",
+            m, requests
+        ));
+    }
+    out.push_str(
+        "# HELP gemini_request_errors_total Failed Gemini requests by model\n\
+         # TYPE gemini_request_errors_total counter\n",
+    );
+    for (m, (_, _, errors, ..)) in models.iter().zip(per_model) {
+        out.push_str(&format!(
+            "gemini_request_errors_total{{model=\"{}\"}} {}\n",
+            m, errors
+        ));
+    }
+    out.push_str(
+        "# HELP gemini_tokens_total Gemini tokens by model and direction\n\
+         # TYPE gemini_tokens_total counter\n",
+    );
+    for (m, (_, _, _, input, output)) in models.iter().zip(per_model) {
+        out.push_str(&format!(
+            "gemini_tokens_total{{model=\"{m}\",direction=\"input\"}} {input}\n\
+             gemini_tokens_total{{model=\"{m}\",direction=\"output\"}} {output}\n"
+        ));
+    }
+    out.push_str(
+        "# HELP gemini_cost_usd_total Estimated Gemini spend by model in USD\n\
+         # TYPE gemini_cost_usd_total counter\n",
+    );
+    for (m, (model, _, _, input, output)) in models.iter().zip(per_model) {
+        out.push_str(&format!(
+            "gemini_cost_usd_total{{model=\"{}\"}} {:.6}\n",
+            m,
+            usage::estimate_cost_usd(model, *input, *output)
+        ));
+    }
+    out
+}
+
 async fn metrics_handler(State(state): State<AppState>) -> String {
     let snapshot = state.system_monitor.read().await;
     let uptime = state.start_time.elapsed().as_secs();
@@ -312,20 +408,56 @@ async fn metrics_handler(State(state): State<AppState>) -> String {
 
     let mut agent_lines = String::new();
     if !per_agent.is_empty() {
+        let agents: Vec<String> = per_agent
+            .iter()
+            .map(|(agent, ..)| sanitize_prom_label(agent))
+            .collect();
         agent_lines.push_str(
             "# HELP a2a_delegation_duration_by_agent Average delegation duration per agent in ms\n\
              # TYPE a2a_delegation_duration_by_agent gauge\n",
         );
-        for (agent, avg_ms, count) in &per_agent {
-            let safe_agent = sanitize_prom_label(agent);
+        for (agent, (_, avg_ms, _)) in agents.iter().zip(&per_agent) {
+            agent_lines.push_str(&format!(
+                "a2a_delegation_duration_by_agent{{agent=\"{}\"}} {:.1}\n",
+                agent, avg_ms
+            ));
+        }
+        agent_lines.push_str(
+            "# HELP a2a_delegation_count_by_agent Delegations with a recorded duration per agent\n\
+             # TYPE a2a_delegation_count_by_agent gauge\n",
+        );
+        for (agent, (_, _, count)) in agents.iter().zip(&per_agent) {
             agent_lines.push_str(&format!(
-                "a2a_delegation_duration_by_agent{{agent=\"{}\"}} {:.1}\n\
-                 a2a_delegation_count_by_agent{{agent=\"{}\"}} {}\n",
-                safe_agent, avg_ms, safe_agent, count
+                "a2a_delegation_count_by_agent{{agent=\"{}\"}} {}\n",
+                agent, count
             ));
         }
     }
 
+    // Gemini usage per model (gh_agent_usage ledger, see usage.rs)
+    let per_model: Vec<(String, i64, i64, i64, i64)> = sqlx::query_as(
+        "SELECT model, COUNT(*), \
+         COUNT(*) FILTER (WHERE NOT COALESCE(success, TRUE)), \
+         COALESCE(SUM(input_tokens), 0)::bigint, COALESCE(SUM(output_tokens), 0)::bigint \
+         FROM gh_agent_usage GROUP BY model ORDER BY model",
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+    agent_lines.push_str(&gemini_usage_metrics(&per_model));
+
+    let crash_count = crash_reports::count().await;
+    agent_lines.push_str(&format!(
+        "# HELP offline_mode Whether offline mode is active (1) or not (0)\n\
+         # TYPE offline_mode gauge\n\
+         offline_mode {}\n\
+         # HELP crash_reports_stored Crash reports stored on disk\n\
+         # TYPE crash_reports_stored gauge\n\
+         crash_reports_stored {}\n",
+        u8::from(offline::is_offline()),
+        crash_count,
+    ));
+
     format!(
         "# HELP cpu_usage_percent CPU usage percentage\n\
          # TYPE cpu_usage_percent gauge\n\
@@ -363,3 +495,31 @@ async fn metrics_handler(State(state): State<AppState>) -> String {
         agent_lines,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_metric_families_are_contiguous() {
+        let rows = vec![
+            ("gemini-2.5-pro".to_string(), 3, 1, 100, 50),
+            ("gemini-2.5-flash".to_string(), 2, 0, 10, 5),
+        ];
+        let text = gemini_usage_metrics(&rows);
+        let families: Vec<&str> = text
+            .lines()
+            .filter(|l| !l.starts_with('#'))
+            .map(|l| l.split('{').next().unwrap_or_default())
+            .collect();
+        let mut seen: Vec<&str> = Vec::new();
+        for family in families {
+            if seen.last() != Some(&family) {
+                assert!(!seen.contains(&family), "{} is split", family);
+                seen.push(family);
+            }
+        }
+        assert_eq!(seen.len(), 4);
+        assert!(gemini_usage_metrics(&[]).is_empty());
+    }
+}
@@ -5,7 +5,11 @@ use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::set_header::SetResponseHeaderLayer;
 use tower_http::trace::TraceLayer;
 
+use gemini_hydra_backend::crash_reports;
+use gemini_hydra_backend::knowledge_extraction;
+use gemini_hydra_backend::memory_summary;
 use gemini_hydra_backend::model_registry;
+use gemini_hydra_backend::shutdown;
 use gemini_hydra_backend::state::{AppState, LogRingBuffer};
 use gemini_hydra_backend::watchdog;
 
@@ -43,6 +47,7 @@ async fn build_app(log_buffer: std::sync::Arc<LogRingBuffer>) -> (axum::Router,
     };
 
     let state = AppState::new(pool, auth_pool, log_buffer).await;
+    gemini_hydra_backend::offline::load(&state.db).await;
 
     // â”€â”€ Spawn system monitor (CPU/memory stats, refreshed every 5s) â”€â”€
     gemini_hydra_backend::system_monitor::spawn(state.system_monitor.clone());
@@ -133,6 +138,11 @@ async fn main() -> shuttle_axum::ShuttleAxum {
 async fn main() -> anyhow::Result<()> {
     app_builder::enable_ansi();
     let log_buffer = app_builder::init_tracing_with_service_name(1000, "geminihydra");
+    crash_reports::install(log_buffer.clone());
+    tracing::info!(
+        "data directory: {}",
+        gemini_hydra_backend::data_dir::base().display()
+    );
 
     let (app, state) = build_app(log_buffer).await;
 
@@ -209,6 +219,12 @@ async fn main() -> anyhow::Result<()> {
     // â”€â”€ Spawn background watchdog â”€â”€
     let _watchdog = watchdog::spawn(state.clone());
 
+    // â”€â”€ Spawn conversation -> knowledge graph extraction (opt-in) â”€â”€
+    let _kg_extraction = knowledge_extraction::spawn(state.clone());
+
+    // â”€â”€ Spawn closed-session memory summaries â”€â”€
+    let _memory_summary = memory_summary::spawn(state.clone());
+
     // â”€â”€ Spawn MCP client startup (connect to enabled MCP servers) â”€â”€
     let mcp_state = state.clone();
     tokio::spawn(async move {
@@ -226,12 +242,13 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("GeminiHydra v15 backend listening on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(
+    let shutdown_token = tokio_util::sync::CancellationToken::new();
+    let server = axum::serve(
         listener,
         app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
     )
-    .with_graceful_shutdown(app_builder::shutdown_signal())
-    .await?;
+    .with_graceful_shutdown(shutdown_token.clone().cancelled_owned());
+    shutdown::serve(&state, server, shutdown_token, shutdown::signal()).await?;
 
     Ok(())
 }
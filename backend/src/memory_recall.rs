@@ -0,0 +1,385 @@
+// ---------------------------------------------------------------------------
+// memory_recall.rs — Remembered facts: the `remember` tool and prompt injection
+//
+// Agents save durable facts with the app-local `remember` tool (stored in
+// gh_memories next to the facts distilled by memory_summary.rs), tagged with
+// the execution's project. Every new chat gets the top-k facts for its agent
+// and its project (see `projects::execution_project`), ranked by importance
+// plus word overlap with its first prompt, appended to the system prompt;
+// continued WebSocket chats are not injected again. The prompt only points
+// at `remember` when the execution is offered the tool.
+// `gh_settings.memory_injection` switches injection off (the flag is cached
+// in-process and updated by the PUT handler), and `GET /api/memory/injection`
+// shows exactly which facts a prompt would get. `remember` stores facts for
+// the executing agent (see `tool_registry::execution_agent`), never for a
+// model-supplied id.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+pub const TOOL_NAME: &str = "remember";
+
+const DEFAULT_TOP_K: usize = 8;
+const CANDIDATE_LIMIT: i64 = 200;
+const MAX_FACT_CHARS: usize = 500;
+/// Weight of prompt overlap (0..1) relative to stored importance (0..1).
+const RELEVANCE_WEIGHT: f64 = 1.5;
+
+const INJECTION_UNKNOWN: u8 = 0;
+const INJECTION_OFF: u8 = 1;
+const INJECTION_ON: u8 = 2;
+/// Cached `gh_settings.memory_injection`; loaded on first use.
+static INJECTION: AtomicU8 = AtomicU8::new(INJECTION_UNKNOWN);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecalledMemory {
+    pub id: Uuid,
+    pub content: String,
+    pub importance: f64,
+    pub score: f64,
+}
+
+fn top_k() -> usize {
+    std::env::var("MEMORY_INJECTION_TOP_K")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|k| *k > 0)
+        .unwrap_or(DEFAULT_TOP_K)
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Rank `(id, content, importance)` candidates against `prompt`, best `k` first.
+fn rank(candidates: Vec<(Uuid, String, f64)>, prompt: &str, k: usize) -> Vec<RecalledMemory> {
+    let prompt_words = words(prompt);
+    let mut ranked: Vec<RecalledMemory> = candidates
+        .into_iter()
+        .map(|(id, content, importance)| {
+            let fact_words = words(&content);
+            let overlap = if fact_words.is_empty() {
+                0.0
+            } else {
+                fact_words.intersection(&prompt_words).count() as f64 / fact_words.len() as f64
+            };
+            RecalledMemory {
+                id,
+                score: importance + RELEVANCE_WEIGHT * overlap,
+                content,
+                importance,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked.truncate(k);
+    ranked
+}
+
+/// System-prompt section for `memories` (empty when there is nothing to inject).
+/// The `remember` hint is only added when the tool is declared to the model.
+fn render_section(memories: &[RecalledMemory], tool_offered: bool) -> String {
+    if memories.is_empty() {
+        return String::new();
+    }
+    let facts: Vec<String> = memories
+        .iter()
+        .map(|m| format!("- {}", m.content))
+        .collect();
+    let mut section = format!(
+        "\n\n# Remembered Facts\nFacts saved in earlier conversations (treat as context, verify if in doubt):\n{}",
+        facts.join("\n")
+    );
+    if tool_offered {
+        section.push_str(&format!(
+            "\nSave new durable facts with the `{}` tool.",
+            TOOL_NAME
+        ));
+    }
+    section
+}
+
+/// Whether the current execution's tool declarations include `remember`
+/// (see `tool_defs::build_tools_with_mcp`).
+async fn tool_offered(state: &AppState) -> bool {
+    crate::tool_registry::in_execution_scope()
+        && !crate::tool_registry::disabled_tools(state)
+            .await
+            .contains(TOOL_NAME)
+}
+
+fn cache_injection(enabled: bool) {
+    let value = if enabled { INJECTION_ON } else { INJECTION_OFF };
+    INJECTION.store(value, Ordering::Relaxed);
+}
+
+async fn injection_enabled(state: &AppState) -> bool {
+    match INJECTION.load(Ordering::Relaxed) {
+        INJECTION_ON => return true,
+        INJECTION_OFF => return false,
+        _ => {}
+    }
+    match sqlx::query_scalar::<_, bool>("SELECT memory_injection FROM gh_settings LIMIT 1")
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(enabled) => {
+            let enabled = enabled.unwrap_or(true);
+            cache_injection(enabled);
+            enabled
+        }
+        // Not cached, so the next execution retries the lookup.
+        Err(e) => {
+            tracing::warn!("memory_recall: failed to read injection setting: {}", e);
+            true
+        }
+    }
+}
+
+/// Top-k memories of `agent` for `project_id` (plus unscoped ones).
+pub async fn recall(
+    state: &AppState,
+    agent: &str,
+    prompt: &str,
+    project_id: Option<Uuid>,
+) -> Vec<RecalledMemory> {
+    let candidates: Vec<(Uuid, String, f64)> = match sqlx::query_as(
+        "SELECT id, content, importance FROM gh_memories \
+         WHERE agent = $1 AND (project_id IS NULL OR project_id = $2) \
+         ORDER BY importance DESC, created_at DESC LIMIT $3",
+    )
+    .bind(agent)
+    .bind(project_id)
+    .bind(CANDIDATE_LIMIT)
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("memory_recall: failed to load memories: {}", e);
+            return Vec::new();
+        }
+    };
+    rank(candidates, prompt, top_k())
+}
+
+/// Append remembered facts for the context's agent to the system prompt of a
+/// new chat.
+pub async fn inject_into_context(
+    state: &AppState,
+    ctx: &mut jaskier_core::context::ExecuteContext,
+) {
+    if !injection_enabled(state).await {
+        return;
+    }
+    let project_id = crate::projects::execution_project(&state.db, &ctx.working_directory)
+        .await
+        .map(|p| p.id);
+    let memories = recall(state, &ctx.agent_id, &ctx.final_user_prompt, project_id).await;
+    let section = render_section(&memories, tool_offered(state).await);
+    ctx.system_prompt.push_str(&section);
+}
+
+/// Gemini function declaration for the `remember` tool.
+pub fn tool_declaration() -> Value {
+    json!({
+        "name": TOOL_NAME,
+        "description": "Save a durable fact (user preference, project decision, convention) to long-term memory so it is available in future conversations. Do not store secrets or transient details.",
+        "parameters": {
+            "type": "object",
+            "properties": {
+                "fact": { "type": "string", "description": "The fact, one self-contained sentence" },
+                "importance": { "type": "number", "description": "0.0-1.0, default 0.5" }
+            },
+            "required": ["fact"]
+        }
+    })
+}
+
+/// Execute `remember` for `agent` (the executing agent, or the default agent
+/// for MCP clients), tagged with the project `working_directory` belongs to.
+/// Refused without an agent rather than guessing one.
+pub async fn execute_remember(
+    state: &AppState,
+    args: &Value,
+    agent: Option<&str>,
+    working_directory: &str,
+) -> Result<String, String> {
+    let fact = args
+        .get("fact")
+        .and_then(|f| f.as_str())
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .ok_or("Missing required argument: fact")?;
+    if fact.chars().count() > MAX_FACT_CHARS {
+        return Err(format!(
+            "fact must be at most {} characters",
+            MAX_FACT_CHARS
+        ));
+    }
+    let importance = args
+        .get("importance")
+        .and_then(|i| i.as_f64())
+        .unwrap_or(0.5)
+        .clamp(0.0, 1.0);
+    let agent = agent
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .ok_or("remember is only available inside an agent execution")?;
+
+    let project_id = crate::projects::execution_project(&state.db, working_directory)
+        .await
+        .map(|p| p.id);
+
+    let duplicate: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM gh_memories WHERE agent = $1 \
+             AND lower(content) = lower($2) \
+             AND (project_id IS NULL OR project_id = $3))",
+    )
+    .bind(agent)
+    .bind(fact)
+    .bind(project_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| format!("failed to check memory: {}", e))?;
+    if duplicate {
+        return Ok(format!("Already remembered for {}: {}", agent, fact));
+    }
+
+    sqlx::query(
+        "INSERT INTO gh_memories (agent, content, importance, project_id) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(agent)
+    .bind(fact)
+    .bind(importance)
+    .bind(project_id)
+    .execute(&state.db)
+    .await
+    .map_err(|e| format!("failed to store memory: {}", e))?;
+    Ok(format!(
+        "Remembered for {} (importance {:.2}): {}",
+        agent, importance, fact
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InjectionParams {
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub prompt: String,
+    /// Working directory of the execution to preview; the active project when empty.
+    #[serde(default)]
+    pub working_directory: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InjectionToggle {
+    pub enabled: bool,
+}
+
+/// GET /api/memory/injection?agent=eskel&prompt=... — Facts and section text a prompt would get.
+pub async fn inspect_injection(
+    State(state): State<AppState>,
+    Query(params): Query<InjectionParams>,
+) -> Json<Value> {
+    let agent = params
+        .agent
+        .filter(|a| !a.is_empty())
+        .unwrap_or_else(|| crate::memory_summary::DEFAULT_AGENT.to_string());
+    let enabled = injection_enabled(&state).await;
+    let project_id = crate::projects::execution_project(&state.db, &params.working_directory)
+        .await
+        .map(|p| p.id);
+    let memories = recall(&state, &agent, &params.prompt, project_id).await;
+    let section = if enabled {
+        render_section(&memories, tool_offered(&state).await)
+    } else {
+        String::new()
+    };
+    Json(json!({
+        "enabled": enabled,
+        "agent": agent,
+        "project_id": project_id,
+        "memories": memories,
+        "section": section.trim_start(),
+    }))
+}
+
+/// PUT /api/memory/injection — Switch injection on or off.
+pub async fn set_injection(
+    State(state): State<AppState>,
+    Json(body): Json<InjectionToggle>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    sqlx::query("UPDATE gh_settings SET memory_injection = $1")
+        .bind(body.enabled)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("memory_recall: failed to update setting: {}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        })?;
+    cache_injection(body.enabled);
+    crate::audit::log_audit(
+        &state.db,
+        "memory_injection_toggled",
+        json!({ "enabled": body.enabled }),
+        None,
+    )
+    .await;
+    Ok(Json(json!({ "enabled": body.enabled })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(content: &str, importance: f64) -> (Uuid, String, f64) {
+        (Uuid::new_v4(), content.to_string(), importance)
+    }
+
+    #[test]
+    fn relevant_facts_outrank_important_ones() {
+        let ranked = rank(
+            vec![
+                memory("User prefers dark themes in every interface", 0.9),
+                memory("Deployment target for backend is Fly.io", 0.4),
+            ],
+            "How should we configure the deployment of the backend?",
+            1,
+        );
+        assert_eq!(ranked.len(), 1);
+        assert!(ranked[0].content.starts_with("Deployment"));
+    }
+
+    #[test]
+    fn section_lists_facts_and_tool_hint() {
+        let ranked = rank(vec![memory("Uses PostgreSQL 16", 0.5)], "", 8);
+        let section = render_section(&ranked, true);
+        assert!(section.contains("# Remembered Facts"));
+        assert!(section.contains("- Uses PostgreSQL 16"));
+        assert!(section.contains(&format!("`{}` tool", TOOL_NAME)));
+        assert!(render_section(&[], true).is_empty());
+    }
+
+    #[test]
+    fn section_omits_the_hint_when_remember_is_not_offered() {
+        let ranked = rank(vec![memory("Uses PostgreSQL 16", 0.5)], "", 8);
+        let section = render_section(&ranked, false);
+        assert!(section.contains("- Uses PostgreSQL 16"));
+        assert!(!section.contains(TOOL_NAME));
+    }
+}
@@ -0,0 +1,341 @@
+// ---------------------------------------------------------------------------
+// memory_summary.rs — Distil finished conversations into durable agent memory
+//
+// `summarize_and_store` sends a transcript to Gemini with a JSON extraction
+// prompt and persists the result: scored facts into gh_memories and the
+// mentioned entities/relations into the knowledge graph — unless
+// knowledge_extraction.rs is enabled, which then owns graph writes (with
+// provenance) and the entities returned here are dropped. A chat counts as
+// closed once it has gone idle (idle_sessions.rs); the background job then
+// summarises it (set `MEMORY_SUMMARY_ENABLED=false` to turn it off), and
+// `POST /api/sessions/{id}/summarize` runs it on demand. Facts are upserted
+// per session, so summarising a session twice does not duplicate them.
+// Sessions idle before the job existed are marked done by migration 061 and
+// only picked up again when they get new messages.
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::handlers::api_error;
+use crate::idle_sessions::{self, IdleSessionJob};
+use crate::knowledge_graph;
+use crate::state::AppState;
+
+const MAX_TRANSCRIPT_MESSAGES: usize = 60;
+const MAX_MESSAGE_CHARS: usize = 2000;
+const MAX_MEMORIES: usize = 10;
+pub const DEFAULT_AGENT: &str = "eskel";
+
+const EXTRACTION_PROMPT: &str = r#"You maintain the long-term memory of an AI agent.
+Read the conversation below and return JSON with exactly this shape:
+{
+  "summary": "2-3 sentence summary of the conversation",
+  "memories": [{ "content": "durable fact worth remembering", "importance": 0.0-1.0 }],
+  "entities": [{ "label": "name", "type": "person|project|technology|concept|file|organization" }],
+  "relations": [{ "source": "entity label", "target": "entity label", "label": "uses|depends_on|part_of|prefers|related_to" }]
+}
+Only keep facts that stay useful in future conversations (preferences, decisions, project facts).
+Skip greetings, transient details and anything already obvious. At most 10 memories."#;
+
+#[derive(Debug, Default, Deserialize)]
+struct Extraction {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    memories: Vec<ExtractedMemory>,
+    #[serde(default)]
+    entities: Vec<ExtractedEntity>,
+    #[serde(default)]
+    relations: Vec<ExtractedRelation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedMemory {
+    content: String,
+    #[serde(default = "default_importance")]
+    importance: f64,
+}
+
+fn default_importance() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedEntity {
+    label: String,
+    #[serde(rename = "type", default = "default_entity_type")]
+    entity_type: String,
+}
+
+fn default_entity_type() -> String {
+    "concept".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedRelation {
+    source: String,
+    target: String,
+    #[serde(default = "default_relation")]
+    label: String,
+}
+
+fn default_relation() -> String {
+    "related_to".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryOutcome {
+    pub agent: String,
+    pub summary: String,
+    pub memories_stored: usize,
+    pub nodes_created: usize,
+    pub edges_created: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SummarizeParams {
+    /// Memory namespace; defaults to the session's locked agent.
+    pub agent: Option<String>,
+}
+
+fn enabled() -> bool {
+    std::env::var("MEMORY_SUMMARY_ENABLED")
+        .map(|v| {
+            !matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "0" | "false" | "off"
+            )
+        })
+        .unwrap_or(true)
+}
+
+/// Render `(role, content)` pairs as a compact transcript, keeping the most recent messages.
+fn build_transcript(messages: &[(String, String)]) -> String {
+    let start = messages.len().saturating_sub(MAX_TRANSCRIPT_MESSAGES);
+    messages[start..]
+        .iter()
+        .map(|(role, content)| {
+            let text: String = content.chars().take(MAX_MESSAGE_CHARS).collect();
+            format!("[{}] {}", role, text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Strip a ```json fence if the model added one despite `responseMimeType`.
+fn strip_code_fence(raw: &str) -> &str {
+    let trimmed = raw.trim();
+    trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|s| s.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+/// Summarise `messages` and persist memories + knowledge-graph entries for `agent`.
+/// Memories are tagged with `project_id` (the session's project; `None` keeps
+/// them unscoped) and with `session_id` so a repeated run updates instead of duplicating.
+pub async fn summarize_and_store(
+    state: &AppState,
+    agent: &str,
+    project_id: Option<Uuid>,
+    session_id: Option<Uuid>,
+    messages: &[(String, String)],
+) -> Result<SummaryOutcome, String> {
+    if messages.is_empty() {
+        return Err("conversation has no messages".to_string());
+    }
+
+    let prompt = format!(
+        "{}\n\n--- CONVERSATION ---\n{}",
+        EXTRACTION_PROMPT,
+        build_transcript(messages)
+    );
+    let raw = crate::gemini_api::generate_content(state, &prompt, 0.2, true).await?;
+    let extraction: Extraction = serde_json::from_str(strip_code_fence(&raw))
+        .map_err(|e| format!("model returned invalid JSON: {}", e))?;
+
+    let db = &state.db;
+    let mut memories_stored = 0;
+    for memory in extraction.memories.iter().take(MAX_MEMORIES) {
+        let content = memory.content.trim();
+        if content.is_empty() {
+            continue;
+        }
+        // Facts this project already knows from another session are skipped; facts from this
+        // session are updated in place. RETURNING is true for new rows only.
+        let inserted: Option<bool> = sqlx::query_scalar(
+            "INSERT INTO gh_memories (agent, content, importance, project_id, session_id) \
+             SELECT $1, $2, $3, $4, $5 \
+             WHERE NOT EXISTS (SELECT 1 FROM gh_memories WHERE agent = $1 \
+                 AND lower(btrim(content)) = lower($2) AND session_id IS DISTINCT FROM $5 \
+                 AND (project_id IS NULL OR project_id = $4)) \
+             ON CONFLICT (session_id, md5(lower(btrim(content)))) WHERE session_id IS NOT NULL \
+             DO UPDATE SET importance = EXCLUDED.importance, project_id = EXCLUDED.project_id \
+             RETURNING (xmax = 0)",
+        )
+        .bind(agent)
+        .bind(content)
+        .bind(memory.importance.clamp(0.0, 1.0))
+        .bind(project_id)
+        .bind(session_id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| format!("failed to store memory: {}", e))?;
+        if inserted == Some(true) {
+            memories_stored += 1;
+        }
+    }
+
+    // With extraction enabled the graph gets these entries, with provenance, from there.
+    let write_graph = !crate::knowledge_extraction::enabled();
+    let mut nodes_created = 0;
+    for entity in extraction.entities.iter().filter(|_| write_graph) {
+        let id = knowledge_graph::slugify(&entity.label);
+        if id.is_empty() {
+            continue;
+        }
+        if knowledge_graph::insert_node(db, &id, &entity.entity_type, entity.label.trim())
+            .await
+            .map_err(|e| format!("failed to store node: {}", e))?
+        {
+            nodes_created += 1;
+        }
+    }
+
+    let mut edges_created = 0;
+    for relation in extraction.relations.iter().filter(|_| write_graph) {
+        let (source, target) = (
+            knowledge_graph::slugify(&relation.source),
+            knowledge_graph::slugify(&relation.target),
+        );
+        if source.is_empty() || target.is_empty() || source == target {
+            continue;
+        }
+        if knowledge_graph::insert_edge(db, &source, &target, &relation.label, 1.0)
+            .await
+            .map_err(|e| format!("failed to store edge: {}", e))?
+        {
+            edges_created += 1;
+        }
+    }
+
+    tracing::info!(
+        agent,
+        memories_stored,
+        nodes_created,
+        edges_created,
+        "memory_summary: conversation distilled"
+    );
+
+    Ok(SummaryOutcome {
+        agent: agent.to_string(),
+        summary: extraction.summary,
+        memories_stored,
+        nodes_created,
+        edges_created,
+    })
+}
+
+/// Summarise a stored session. `Ok(None)` when the session does not exist.
+async fn summarize_stored_session(
+    state: &AppState,
+    session_id: Uuid,
+    agent_override: Option<String>,
+) -> Result<Option<SummaryOutcome>, String> {
+    let session: Option<(Option<String>, Option<Uuid>)> =
+        sqlx::query_as("SELECT agent_id, project_id FROM gh_sessions WHERE id = $1")
+            .bind(session_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| format!("failed to load session: {}", e))?;
+    let Some((session_agent, project_id)) = session else {
+        return Ok(None);
+    };
+
+    let agent = agent_override
+        .filter(|a| !a.is_empty())
+        .or(session_agent.filter(|a| !a.is_empty()))
+        .unwrap_or_else(|| DEFAULT_AGENT.to_string());
+
+    let messages: Vec<(String, String)> = sqlx::query_as(
+        "SELECT role, content FROM gh_chat_messages WHERE session_id = $1 ORDER BY created_at",
+    )
+    .bind(session_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| format!("failed to load messages: {}", e))?;
+
+    let outcome =
+        summarize_and_store(state, &agent, project_id, Some(session_id), &messages).await?;
+    sqlx::query("UPDATE gh_sessions SET memory_summarized_at = NOW() WHERE id = $1")
+        .bind(session_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| format!("failed to mark session: {}", e))?;
+    Ok(Some(outcome))
+}
+
+/// Spawn the closed-session summariser unless `MEMORY_SUMMARY_ENABLED` turns it off.
+pub fn spawn(state: AppState) -> Option<tokio::task::JoinHandle<()>> {
+    if !enabled() {
+        return None;
+    }
+    let job = IdleSessionJob {
+        name: "memory_summary",
+        done_column: "memory_summarized_at",
+    };
+    Some(idle_sessions::spawn(state, job, |state, id| async move {
+        summarize_stored_session(&state, id, None).await.map(|_| ())
+    }))
+}
+
+/// POST /api/sessions/{id}/summarize — Distil a session into agent memory.
+pub async fn summarize_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<Uuid>,
+    Query(params): Query<SummarizeParams>,
+) -> (StatusCode, Json<Value>) {
+    match summarize_stored_session(&state, session_id, params.agent).await {
+        Ok(Some(outcome)) => (StatusCode::OK, Json(json!(outcome))),
+        Ok(None) => api_error(StatusCode::NOT_FOUND, "session not found"),
+        Err(e) => api_error(StatusCode::UNPROCESSABLE_ENTITY, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_keeps_most_recent_messages() {
+        let messages: Vec<(String, String)> = (0..100)
+            .map(|i| ("user".to_string(), format!("message {}", i)))
+            .collect();
+        let transcript = build_transcript(&messages);
+        assert!(!transcript.contains("message 39\n"));
+        assert!(transcript.starts_with("[user] message 40"));
+        assert!(transcript.ends_with("message 99"));
+    }
+
+    #[test]
+    fn code_fence_is_stripped() {
+        assert_eq!(strip_code_fence("```json\n{\"a\":1}\n```"), "{\"a\":1}");
+        assert_eq!(strip_code_fence("{\"a\":1}"), "{\"a\":1}");
+    }
+
+    #[test]
+    fn extraction_tolerates_missing_fields() {
+        let parsed: Extraction =
+            serde_json::from_str(r#"{"memories":[{"content":"Prefers Rust"}]}"#).expect("valid");
+        assert_eq!(parsed.memories.len(), 1);
+        assert!((parsed.memories[0].importance - 0.5).abs() < f64::EPSILON);
+        assert!(parsed.entities.is_empty());
+    }
+}
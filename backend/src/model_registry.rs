@@ -4,9 +4,11 @@
 // All fetch/cache/select logic lives in jaskier-core::model_registry.
 // This module re-exports shared types and provides thin AppState-typed
 // wrappers for the HTTP handlers so lib.rs call-sites need no changes.
+// The wrappers that fetch from Google refuse to while offline mode is on.
 
 use axum::Json;
 use axum::extract::{Path, State};
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use serde_json::Value;
 
@@ -15,9 +17,18 @@ use crate::state::AppState;
 // ── Re-export shared types from jaskier-core ──────────────────────────────────
 pub use jaskier_core::model_registry::{
     ModelCache, ModelInfo, PinModelRequest, ResolvedModels, classify_complexity, get_model_id,
-    refresh_cache, resolve_models, startup_sync,
+    refresh_cache, resolve_models,
 };
 
+/// Startup model sync; skipped while offline, leaving the fallback models in place.
+pub async fn startup_sync(state: &AppState) {
+    if crate::offline::is_offline() {
+        tracing::info!("model registry: offline mode — skipping startup sync");
+        return;
+    }
+    jaskier_core::model_registry::startup_sync(state).await;
+}
+
 // ── Concrete AppState-typed HTTP handlers ─────────────────────────────────────
 //
 // These wrappers simply delegate to the generic jaskier-core handlers, pinning
@@ -34,10 +45,17 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
 
 /// POST /api/models/refresh — Force refresh of model cache
 #[utoipa::path(post, path = "/api/models/refresh", tag = "models",
-    responses((status = 200, description = "Refreshed model cache", body = Value))
+    responses(
+        (status = 200, description = "Refreshed model cache", body = Value),
+        (status = 503, description = "Offline mode is on", body = Value)
+    )
 )]
-pub async fn refresh_models(State(state): State<AppState>) -> Json<Value> {
-    jaskier_core::model_registry::refresh_models::<AppState>(State(state)).await
+pub async fn refresh_models(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    crate::offline::ensure_online()
+        .map_err(|e| crate::handlers::api_error(StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+    Ok(jaskier_core::model_registry::refresh_models::<AppState>(State(state)).await)
 }
 
 /// POST /api/models/pin — Pin a specific model to a use case
@@ -0,0 +1,150 @@
+// ---------------------------------------------------------------------------
+// offline.rs — Global offline switch for air-gapped or metered setups
+//
+// While offline, every outbound path this backend owns short-circuits with
+// `OfflineMode`: Gemini/Vertex calls (execute, streaming, A2A, background jobs),
+// network-tier or MCP tools, the model registry sync and the watchdog's
+// Google reachability check. Local features — files, code analysis,
+// git, knowledge graph, sessions — keep working. The switch is stored in
+// `gh_settings.offline_mode` and loaded at startup (`OFFLINE_MODE=1` forces
+// it on); `PUT /api/offline` persists a change before applying it.
+// ---------------------------------------------------------------------------
+
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+use crate::tool_registry::PermissionTier;
+
+static OFFLINE: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(forced_by_env()));
+
+/// `OFFLINE_MODE=1` keeps the backend offline whatever the stored setting says.
+fn forced_by_env() -> bool {
+    std::env::var("OFFLINE_MODE")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Returned by any network path while offline mode is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("offline mode is enabled — network access is disabled")]
+pub struct OfflineMode;
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+pub fn set_offline(enabled: bool) {
+    OFFLINE.store(enabled, Ordering::Relaxed);
+}
+
+/// Apply the stored switch; called once at startup, before any background
+/// job can reach the network. A failed read keeps the current state.
+pub async fn load(db: &sqlx::PgPool) {
+    match sqlx::query_scalar::<_, bool>("SELECT offline_mode FROM gh_settings LIMIT 1")
+        .fetch_optional(db)
+        .await
+    {
+        Ok(stored) => {
+            let enabled = forced_by_env() || stored.unwrap_or(false);
+            set_offline(enabled);
+            if enabled {
+                tracing::info!("offline mode is enabled — network access is disabled");
+            }
+        }
+        Err(e) => tracing::warn!("offline: failed to read stored setting: {}", e),
+    }
+}
+
+/// `Err(OfflineMode)` while offline.
+pub fn ensure_online() -> Result<(), OfflineMode> {
+    if is_offline() {
+        Err(OfflineMode)
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether a tool of this tier reaches outside the machine.
+pub fn tier_needs_network(tier: PermissionTier) -> bool {
+    matches!(tier, PermissionTier::Network | PermissionTier::External)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OfflineRequest {
+    pub enabled: bool,
+}
+
+/// GET /api/offline
+pub async fn get_offline() -> Json<Value> {
+    Json(json!({ "offline": is_offline() }))
+}
+
+/// PUT /api/offline — Toggle offline mode; the choice survives restarts.
+pub async fn set_offline_handler(
+    State(state): State<AppState>,
+    Json(req): Json<OfflineRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if !req.enabled && forced_by_env() {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            "offline mode is forced by OFFLINE_MODE",
+        ));
+    }
+    sqlx::query("UPDATE gh_settings SET offline_mode = $1")
+        .bind(req.enabled)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("offline: failed to store setting: {}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        })?;
+    let previous = is_offline();
+    set_offline(req.enabled);
+    if previous != req.enabled {
+        tracing::info!(offline = req.enabled, "offline mode toggled");
+        crate::audit::log_audit(
+            &state.db,
+            "offline_mode",
+            json!({ "enabled": req.enabled }),
+            None,
+        )
+        .await;
+    }
+    Ok(Json(json!({ "offline": req.enabled })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_network_tiers_are_blocked() {
+        assert!(tier_needs_network(PermissionTier::Network));
+        assert!(tier_needs_network(PermissionTier::External));
+        assert!(!tier_needs_network(PermissionTier::Read));
+        assert!(!tier_needs_network(PermissionTier::Write));
+        assert!(!tier_needs_network(PermissionTier::Execute));
+    }
+
+    #[test]
+    fn offline_error_message() {
+        assert!(
+            OfflineMode
+                .to_string()
+                .starts_with("offline mode is enabled")
+        );
+    }
+}
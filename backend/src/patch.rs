@@ -0,0 +1,1204 @@
+// ---------------------------------------------------------------------------
+// patch.rs — Line-based unified diffs: preview and atomic multi-file apply
+//
+// `unified_diff` renders the difference between the current content of a
+// file and a proposed one, so the UI can show the actual change before a
+// write is confirmed; `tool_call_diff` attaches the same diff to approval
+// requests for `write_file` / `edit_file`. Lines are compared with their
+// terminators, so a changed line ending or final newline shows up (with
+// `\ No newline at end of file`, as in `git diff`). Common prefix/suffix are
+// trimmed first and the middle goes through an LCS table; inputs too large
+// for the table degrade to a single replace hunk instead of burning CPU.
+// Previewed paths are resolved like patch targets and must stay inside the
+// project root.
+//
+// `apply_patch` takes a multi-file unified diff (as produced by agents or
+// `git diff`), validates every hunk in memory and only then touches the
+// disk — any write failure rolls back the files already changed, including
+// directories created for new files. Renames read the old path and become a
+// write of the new path plus a delete of the old one; a patch that touches
+// the same file twice is rejected. Targets are resolved through symlinks and
+// must stay inside the project root; a deletion must consume the whole file,
+// so a stale diff can't delete content it never saw. `\ No newline at end of
+// file` markers decide whether the patched file ends with a newline.
+// ---------------------------------------------------------------------------
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path as FsPath, PathBuf};
+use std::sync::LazyLock;
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::io::AsyncWriteExt;
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+/// Lines of unchanged context around each hunk (same as `diff -u`).
+pub const DEFAULT_CONTEXT: usize = 3;
+/// Upper bound for the LCS table (rows × columns of the changed middle).
+const MAX_LCS_CELLS: usize = 4_000_000;
+/// Proposed content larger than this is not diffed.
+const MAX_PREVIEW_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffSummary {
+    pub diff: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// Edit script turning `old` into `new`, as (op, line) pairs.
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<(Op, &str)> = old[..prefix].iter().map(|l| (Op::Equal, *l)).collect();
+    if a.len().saturating_mul(b.len()) > MAX_LCS_CELLS {
+        ops.extend(a.iter().map(|l| (Op::Delete, *l)));
+        ops.extend(b.iter().map(|l| (Op::Insert, *l)));
+    } else {
+        // lcs[i][j] = LCS length of a[i..] and b[j..]
+        let (n, m) = (a.len(), b.len());
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        let at = |i: usize, j: usize| i * (m + 1) + j;
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[at(i, j)] = if a[i] == b[j] {
+                    lcs[at(i + 1, j + 1)] + 1
+                } else {
+                    lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if a[i] == b[j] {
+                ops.push((Op::Equal, a[i]));
+                i += 1;
+                j += 1;
+            } else if lcs[at(i + 1, j)] >= lcs[at(i, j + 1)] {
+                ops.push((Op::Delete, a[i]));
+                i += 1;
+            } else {
+                ops.push((Op::Insert, b[j]));
+                j += 1;
+            }
+        }
+        ops.extend(a[i..].iter().map(|l| (Op::Delete, *l)));
+        ops.extend(b[j..].iter().map(|l| (Op::Insert, *l)));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| (Op::Equal, *l)));
+    ops
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // diff -u convention: an empty range points at the line before it.
+    let start = if len == 0 { start } else { start + 1 };
+    if len == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, len)
+    }
+}
+
+/// Lines of `text` including their terminators, so two lines that differ
+/// only in `\r\n` vs `\n` or a missing final newline compare unequal.
+fn terminated_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Render a unified diff (`--- a/path` / `+++ b/path`) between two texts.
+/// `None` for `old` means the file does not exist yet. Returns an empty diff
+/// when the contents are identical.
+pub fn unified_diff(path: &str, old: Option<&str>, new: &str, context: usize) -> DiffSummary {
+    let old_lines: Vec<&str> = old.map(terminated_lines).unwrap_or_default();
+    let new_lines: Vec<&str> = terminated_lines(new);
+    let ops = edit_script(&old_lines, &new_lines);
+
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+        .map(|(i, _)| i)
+        .collect();
+    let additions = ops.iter().filter(|(op, _)| *op == Op::Insert).count();
+    let deletions = ops.iter().filter(|(op, _)| *op == Op::Delete).count();
+    if changed.is_empty() {
+        return DiffSummary {
+            diff: String::new(),
+            additions,
+            deletions,
+        };
+    }
+
+    let mut out = format!(
+        "--- {}\n+++ b/{}\n",
+        if old.is_some() {
+            format!("a/{}", path)
+        } else {
+            "/dev/null".to_string()
+        },
+        path
+    );
+
+    // Group changes whose context windows overlap into one hunk.
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changed {
+        let start = idx.saturating_sub(context);
+        let end = (idx + context + 1).min(ops.len());
+        match groups.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => groups.push((start, end)),
+        }
+    }
+
+    for (start, end) in groups {
+        let old_start = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != Op::Insert)
+            .count();
+        let new_start = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != Op::Delete)
+            .count();
+        let slice = &ops[start..end];
+        let old_len = slice.iter().filter(|(op, _)| *op != Op::Insert).count();
+        let new_len = slice.iter().filter(|(op, _)| *op != Op::Delete).count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        for (op, line) in slice {
+            let sign = match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            };
+            out.push(sign);
+            match line.strip_suffix('\n') {
+                Some(text) => {
+                    out.push_str(text);
+                    out.push('\n');
+                }
+                None => {
+                    out.push_str(line);
+                    out.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+    }
+
+    DiffSummary {
+        diff: out,
+        additions,
+        deletions,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffPreviewRequest {
+    pub path: String,
+    /// Proposed new file content.
+    pub content: String,
+    #[serde(default)]
+    pub context: Option<usize>,
+}
+
+/// Resolve a previewed `path` (relative, or absolute inside the root) under
+/// `root` the same way `apply_patch` resolves its targets.
+async fn preview_path(root: &str, path: &str) -> Result<PathBuf, String> {
+    let root = tokio::fs::canonicalize(root)
+        .await
+        .map_err(|e| format!("project root '{}' is unavailable: {}", root, e))?;
+    resolve_inside(&root, path).await
+}
+
+/// POST /api/files/diff-preview — Unified diff of the proposed content against the file on disk.
+pub async fn diff_preview(
+    State(state): State<AppState>,
+    Json(req): Json<DiffPreviewRequest>,
+) -> (StatusCode, Json<Value>) {
+    if req.content.len() > MAX_PREVIEW_BYTES {
+        return api_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("content exceeds {} bytes", MAX_PREVIEW_BYTES),
+        );
+    }
+    let Some(project) = crate::projects::active_project(&state.db).await else {
+        return api_error(
+            StatusCode::CONFLICT,
+            "no active project — previews are resolved inside its root",
+        );
+    };
+    let full_path = match preview_path(&project.root_path, &req.path).await {
+        Ok(path) => path,
+        Err(e) => return api_error(StatusCode::BAD_REQUEST, e),
+    };
+    let current = match tokio::fs::read(&full_path).await {
+        Ok(bytes) => match String::from_utf8(bytes) {
+            Ok(text) => Some(text),
+            Err(_) => {
+                return api_error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "existing file is not valid UTF-8 text",
+                );
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return api_error(
+                StatusCode::BAD_REQUEST,
+                format!("cannot read '{}': {}", full_path.display(), e),
+            );
+        }
+    };
+
+    let diff = unified_diff(
+        &req.path.replace('\\', "/"),
+        current.as_deref(),
+        &req.content,
+        req.context.unwrap_or(DEFAULT_CONTEXT),
+    );
+    (
+        StatusCode::OK,
+        Json(json!({
+            "path": full_path.display().to_string(),
+            "exists": current.is_some(),
+            "unchanged": diff.diff.is_empty(),
+            "additions": diff.additions,
+            "deletions": diff.deletions,
+            "diff": diff.diff,
+        })),
+    )
+}
+
+fn str_arg<'a>(args: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|k| args.get(*k).and_then(|v| v.as_str()))
+}
+
+/// Diff of the change a `write_file` / `edit_file` call would make, shown
+/// with its approval request. `None` for other tools, incomplete arguments,
+/// paths outside the execution's root or files too large to preview.
+pub async fn tool_call_diff(
+    state: &AppState,
+    name: &str,
+    args: &Value,
+    working_directory: &str,
+) -> Option<Value> {
+    if name != "write_file" && name != "edit_file" {
+        return None;
+    }
+    let path = str_arg(args, &["path", "file_path"])?;
+    let root = if working_directory.trim().is_empty() {
+        crate::projects::active_project(&state.db).await?.root_path
+    } else {
+        working_directory.to_string()
+    };
+    let full_path = preview_path(&root, path).await.ok()?;
+    let current = match tokio::fs::read_to_string(&full_path).await {
+        Ok(text) if text.len() <= MAX_PREVIEW_BYTES => Some(text),
+        Ok(_) => return None,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(_) => return None,
+    };
+    let proposed = if name == "write_file" {
+        str_arg(args, &["content"])?.to_string()
+    } else {
+        let old = str_arg(args, &["old_text", "old_string", "search"])?;
+        let new = str_arg(args, &["new_text", "new_string", "replace"])?;
+        let text = current.as_deref()?;
+        if old.is_empty() || !text.contains(old) {
+            return None;
+        }
+        text.replacen(old, new, 1)
+    };
+    if proposed.len() > MAX_PREVIEW_BYTES {
+        return None;
+    }
+    let diff = unified_diff(
+        &path.replace('\\', "/"),
+        current.as_deref(),
+        &proposed,
+        DEFAULT_CONTEXT,
+    );
+    Some(json!({
+        "path": full_path.display().to_string(),
+        "exists": current.is_some(),
+        "additions": diff.additions,
+        "deletions": diff.deletions,
+        "diff": diff.diff,
+    }))
+}
+
+// ── Applying unified diffs ───────────────────────────────────────────────────
+
+static HUNK_HEADER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").expect("valid regex")
+});
+
+#[derive(Debug, Clone, PartialEq)]
+struct Hunk {
+    old_start: usize,
+    new_start: usize,
+    /// (' ' | '-' | '+', line text)
+    lines: Vec<(char, String)>,
+    /// `\ No newline at end of file` follows the hunk's last old line.
+    old_missing_newline: bool,
+    /// `\ No newline at end of file` follows the hunk's last new line.
+    new_missing_newline: bool,
+}
+
+impl Hunk {
+    /// Record a `\ No newline at end of file` marker against the line before
+    /// it: a context line ends both sides, `-` / `+` only its own.
+    fn mark_missing_newline(&mut self) {
+        match self.lines.last().map(|(sign, _)| *sign) {
+            Some(' ') => {
+                self.old_missing_newline = true;
+                self.new_missing_newline = true;
+            }
+            Some('-') => self.old_missing_newline = true,
+            Some('+') => self.new_missing_newline = true,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FilePatch {
+    /// `None` for `/dev/null` (file creation).
+    old_path: Option<String>,
+    /// `None` for `/dev/null` (file deletion).
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn display_path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HunkStatus {
+    pub hunk: usize,
+    /// `applied`, `offset` (context found elsewhere) or `failed`.
+    pub status: &'static str,
+    /// Line shift relative to the hunk header, when the context moved.
+    pub offset: isize,
+}
+
+fn parse_patch_path(raw: &str) -> Option<String> {
+    // Strip trailing timestamp ("--- a/x\t2024-01-01 ...") and a/ b/ prefixes.
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Parse a (possibly multi-file) unified diff.
+fn parse_patch(input: &str) -> Result<Vec<FilePatch>, String> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut lines = input.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let Some(new) = lines.next().and_then(|l| l.strip_prefix("+++ ")) else {
+                return Err(format!("'--- {}' is not followed by a '+++' line", old));
+            };
+            files.push(FilePatch {
+                old_path: parse_patch_path(old),
+                new_path: parse_patch_path(new),
+                hunks: Vec::new(),
+            });
+        } else if let Some(c) = HUNK_HEADER_RE.captures(line) {
+            let file = files
+                .last_mut()
+                .ok_or_else(|| "hunk found before any file header".to_string())?;
+            let num = |i: usize, default: usize| {
+                c.get(i)
+                    .map_or(Ok(default), |m| m.as_str().parse::<usize>())
+                    .map_err(|e| format!("bad hunk header '{}': {}", line, e))
+            };
+            let (old_start, mut old_left) = (num(1, 0)?, num(2, 1)?);
+            let (new_start, mut new_left) = (num(3, 0)?, num(4, 1)?);
+            let mut hunk = Hunk {
+                old_start,
+                new_start,
+                lines: Vec::new(),
+                old_missing_newline: false,
+                new_missing_newline: false,
+            };
+            while old_left > 0 || new_left > 0 {
+                let Some(body) = lines.next() else {
+                    return Err(format!("hunk '{}' is truncated", line));
+                };
+                // A bare empty line is an empty context line (some tools drop the space).
+                let (sign, text) = match body.chars().next() {
+                    None => (' ', ""),
+                    Some('\\') => {
+                        // "\ No newline at end of file"
+                        hunk.mark_missing_newline();
+                        continue;
+                    }
+                    Some(sign @ (' ' | '-' | '+')) => (sign, &body[1..]),
+                    Some(_) => return Err(format!("unexpected line in hunk: '{}'", body)),
+                };
+                match sign {
+                    ' ' => {
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    '-' => old_left = old_left.saturating_sub(1),
+                    _ => new_left = new_left.saturating_sub(1),
+                }
+                hunk.lines.push((sign, text.to_string()));
+            }
+            while lines.peek().is_some_and(|l| l.starts_with('\\')) {
+                lines.next();
+                hunk.mark_missing_newline();
+            }
+            file.hunks.push(hunk);
+        }
+    }
+    if files.is_empty() {
+        return Err("no file headers ('--- a/..' / '+++ b/..') found".to_string());
+    }
+    Ok(files)
+}
+
+/// Apply hunks to `content`. Each hunk is tried at its header position first,
+/// then at the nearest position (after the previous hunk) where its context matches.
+/// A hunk with a `\ No newline at end of file` marker only matches at the end
+/// of the file and sets whether the result ends with a newline.
+fn apply_hunks(content: &str, hunks: &[Hunk]) -> (Option<String>, Vec<HunkStatus>) {
+    let crlf = content.contains("\r\n");
+    let mut trailing_newline = content.is_empty() || content.ends_with('\n');
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut statuses = Vec::with_capacity(hunks.len());
+    let mut failed = false;
+    // Shift between header line numbers and positions in `lines` caused by earlier hunks.
+    let mut delta: isize = 0;
+    let mut floor = 0usize;
+
+    for (idx, hunk) in hunks.iter().enumerate() {
+        let expected: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter(|(s, _)| *s != '+')
+            .map(|(_, l)| l.as_str())
+            .collect();
+        let replacement: Vec<String> = hunk
+            .lines
+            .iter()
+            .filter(|(s, _)| *s != '-')
+            .map(|(_, l)| l.clone())
+            .collect();
+
+        // Header is 1-based; an empty old range points at the line before.
+        let header_pos = if expected.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let wanted = (header_pos as isize + delta).max(floor as isize) as usize;
+        let at_eof = hunk.old_missing_newline || hunk.new_missing_newline;
+        // The old side's missing newline must match the file as it is now.
+        let old_end_matches = !hunk.old_missing_newline || !trailing_newline;
+        let matches_at = |pos: usize| {
+            old_end_matches
+                && (!at_eof || pos + expected.len() == lines.len())
+                && pos + expected.len() <= lines.len()
+                && lines[pos..pos + expected.len()]
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| a == b)
+        };
+        let last_start = lines.len().saturating_sub(expected.len());
+        let found = (0..=lines.len()).find_map(|distance| {
+            let after = wanted + distance;
+            if after <= last_start && matches_at(after) {
+                return Some(after);
+            }
+            let before = wanted.checked_sub(distance)?;
+            (distance > 0 && before >= floor && matches_at(before)).then_some(before)
+        });
+
+        match found {
+            Some(pos) => {
+                let offset = pos as isize - (header_pos as isize + delta);
+                statuses.push(HunkStatus {
+                    hunk: idx + 1,
+                    status: if offset == 0 { "applied" } else { "offset" },
+                    offset,
+                });
+                lines.splice(pos..pos + expected.len(), replacement.iter().cloned());
+                if hunk.new_missing_newline {
+                    trailing_newline = false;
+                } else if hunk.old_missing_newline {
+                    trailing_newline = true;
+                }
+                delta += replacement.len() as isize - expected.len() as isize + offset;
+                floor = pos + replacement.len();
+            }
+            None => {
+                failed = true;
+                statuses.push(HunkStatus {
+                    hunk: idx + 1,
+                    status: "failed",
+                    offset: 0,
+                });
+            }
+        }
+    }
+
+    if failed {
+        return (None, statuses);
+    }
+    let newline = if crlf { "\r\n" } else { "\n" };
+    let mut out = lines.join(newline);
+    if trailing_newline && !lines.is_empty() {
+        out.push_str(newline);
+    }
+    (Some(out), statuses)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyPatchRequest {
+    /// Unified diff, one or more files.
+    pub patch: String,
+    /// Validate and report per-hunk status without touching the disk.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What to do with one file once every hunk validated.
+enum FileChange {
+    Write {
+        path: PathBuf,
+        original: Option<String>,
+        content: String,
+    },
+    Delete {
+        path: PathBuf,
+        original: String,
+    },
+}
+
+/// Undo already-performed changes (best effort, reverse order), then remove the
+/// directories that were created for new files, deepest first.
+async fn rollback(done: &[&FileChange], created_dirs: &[PathBuf]) {
+    for change in done.iter().rev() {
+        let result = match change {
+            FileChange::Write {
+                path,
+                original: Some(original),
+                ..
+            }
+            | FileChange::Delete { path, original } => tokio::fs::write(path, original).await,
+            FileChange::Write {
+                path,
+                original: None,
+                ..
+            } => tokio::fs::remove_file(path).await,
+        };
+        if let Err(e) = result {
+            tracing::error!("patch: rollback failed: {}", e);
+        }
+    }
+    for dir in created_dirs.iter().rev() {
+        if let Err(e) = tokio::fs::remove_dir(dir).await {
+            tracing::error!("patch: rollback could not remove {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Unique hidden sibling of `path` to stage a write in; opened with
+/// `create_new`, so it can never clobber a real file.
+fn temp_path(path: &FsPath) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.ghpatch.tmp", name, uuid::Uuid::new_v4()))
+}
+
+/// Perform one change. Directories it has to create are appended to
+/// `created_dirs` (shallowest first) before anything is written.
+async fn perform(change: &FileChange, created_dirs: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    match change {
+        FileChange::Write { path, content, .. } => {
+            if let Some(parent) = path.parent() {
+                let mut missing = Vec::new();
+                let mut dir = Some(parent);
+                while let Some(d) = dir.filter(|d| !d.as_os_str().is_empty()) {
+                    if tokio::fs::try_exists(d).await? {
+                        break;
+                    }
+                    missing.push(d.to_path_buf());
+                    dir = d.parent();
+                }
+                tokio::fs::create_dir_all(parent).await?;
+                created_dirs.extend(missing.into_iter().rev());
+            }
+            // Write next to the target, then rename — a crash never leaves a half-written file.
+            let tmp = temp_path(path);
+            let mut file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp)
+                .await?;
+            let written = async {
+                file.write_all(content.as_bytes()).await?;
+                file.sync_all().await
+            }
+            .await;
+            drop(file);
+            if let Err(e) = written {
+                let _ = tokio::fs::remove_file(&tmp).await;
+                return Err(e);
+            }
+            tokio::fs::rename(&tmp, path).await
+        }
+        FileChange::Delete { path, .. } => tokio::fs::remove_file(path).await,
+    }
+}
+
+/// Perform every change, or none: on the first failure everything done so far is undone.
+async fn perform_all(changes: &[FileChange]) -> std::io::Result<()> {
+    let mut done: Vec<&FileChange> = Vec::with_capacity(changes.len());
+    let mut created_dirs = Vec::new();
+    for change in changes {
+        if let Err(e) = perform(change, &mut created_dirs).await {
+            tracing::error!("patch: write failed, rolling back: {}", e);
+            rollback(&done, &created_dirs).await;
+            return Err(e);
+        }
+        done.push(change);
+    }
+    Ok(())
+}
+
+/// `true` for a non-empty relative path made only of normal components.
+fn is_plain_relative(rel: &str) -> bool {
+    let path = FsPath::new(rel);
+    !rel.is_empty()
+        && !path.is_absolute()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Resolve `rel` under the canonical `root`, following symlinks: the deepest
+/// existing ancestor is canonicalised and must stay inside the root, so a
+/// link inside the project can't redirect a write or delete outside it.
+async fn resolve_inside(root: &FsPath, rel: &str) -> Result<PathBuf, String> {
+    let full = root.join(rel);
+    let mut existing = full.as_path();
+    let mut rest = Vec::new();
+    while tokio::fs::symlink_metadata(existing).await.is_err() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            break;
+        };
+        rest.push(name.to_os_string());
+        existing = parent;
+    }
+    let canonical = tokio::fs::canonicalize(existing)
+        .await
+        .map_err(|e| format!("'{}' cannot be resolved: {}", rel, e))?;
+    if !canonical.starts_with(root) {
+        return Err(format!("'{}' resolves outside the project", rel));
+    }
+    Ok(rest
+        .into_iter()
+        .rev()
+        .fold(canonical, |path, name| path.join(name)))
+}
+
+/// Per-file report, the changes to perform, and whether every file validated.
+type Plan = (Vec<Value>, Vec<FileChange>, bool);
+
+/// Validate every file of a parsed patch against the tree at `root` without
+/// writing anything. Structural problems (paths escaping the root, a file
+/// touched twice) are errors; hunks that don't apply are reported per file.
+async fn plan_changes(
+    root: &FsPath,
+    files: &[FilePatch],
+) -> Result<Plan, (StatusCode, Json<Value>)> {
+    let mut touched: HashSet<&str> = HashSet::new();
+    for file in files {
+        for rel in [file.old_path.as_deref(), file.new_path.as_deref()]
+            .into_iter()
+            .flatten()
+        {
+            if !is_plain_relative(rel) {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("'{}' must be a plain path inside the project", rel),
+                ));
+            }
+        }
+        if file.old_path.is_none() && file.new_path.is_none() {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "a file header has /dev/null on both sides".to_string(),
+            ));
+        }
+        let mut paths: Vec<&str> = file.old_path.iter().map(String::as_str).collect();
+        if file.new_path != file.old_path {
+            paths.extend(file.new_path.as_deref());
+        }
+        for rel in paths {
+            if !touched.insert(rel) {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("'{}' is touched by more than one file in the patch", rel),
+                ));
+            }
+        }
+    }
+
+    let root = tokio::fs::canonicalize(root).await.map_err(|e| {
+        api_error(
+            StatusCode::CONFLICT,
+            format!("project root '{}' is unavailable: {}", root.display(), e),
+        )
+    })?;
+    let mut resolved: HashMap<&str, PathBuf> = HashMap::with_capacity(touched.len());
+    for rel in touched {
+        let path = resolve_inside(&root, rel)
+            .await
+            .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+        resolved.insert(rel, path);
+    }
+    let path_of = |rel: &str| resolved[rel].clone();
+
+    let mut report = Vec::with_capacity(files.len());
+    let mut changes = Vec::with_capacity(files.len());
+    let mut all_ok = true;
+    for file in files {
+        let rel = file.display_path();
+        let action = match (file.old_path.as_deref(), file.new_path.as_deref()) {
+            (None, _) => "create",
+            (_, None) => "delete",
+            (Some(old), Some(new)) if old != new => "rename",
+            _ => "modify",
+        };
+        let original = match file.old_path.as_deref() {
+            Some(old) => match tokio::fs::read_to_string(path_of(old)).await {
+                Ok(text) => Some(text),
+                Err(e) => {
+                    all_ok = false;
+                    report.push(json!({ "path": old, "error": format!("cannot read: {}", e) }));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        if matches!(action, "create" | "rename")
+            && tokio::fs::try_exists(path_of(rel)).await.unwrap_or(false)
+        {
+            all_ok = false;
+            report.push(json!({ "path": rel, "error": "file already exists" }));
+            continue;
+        }
+
+        let (content, hunks) = apply_hunks(original.as_deref().unwrap_or_default(), &file.hunks);
+        // A delete must account for the whole current content; anything left
+        // over means the diff is stale and the file changed since.
+        let stale_delete = action == "delete" && content.as_deref().is_some_and(|c| !c.is_empty());
+        let content = content.filter(|_| !stale_delete);
+        let ok = content.is_some();
+        all_ok &= ok;
+        let mut entry = json!({
+            "path": rel,
+            "action": action,
+            "ok": ok,
+            "hunks": hunks,
+        });
+        if stale_delete {
+            entry["error"] = json!("file has content the deletion does not account for");
+        }
+        if action == "rename" {
+            entry["from"] = json!(file.old_path);
+        }
+        report.push(entry);
+        let Some(content) = content else {
+            continue;
+        };
+        match (file.old_path.as_deref(), file.new_path.as_deref(), original) {
+            (Some(old), None, Some(original)) => changes.push(FileChange::Delete {
+                path: path_of(old),
+                original,
+            }),
+            (Some(old), Some(new), Some(original)) if old != new => {
+                // New file first: a failed delete rolls back to the old file only.
+                changes.push(FileChange::Write {
+                    path: path_of(new),
+                    original: None,
+                    content,
+                });
+                changes.push(FileChange::Delete {
+                    path: path_of(old),
+                    original,
+                });
+            }
+            (_, _, original) => changes.push(FileChange::Write {
+                path: path_of(rel),
+                original,
+                content,
+            }),
+        }
+    }
+    Ok((report, changes, all_ok))
+}
+
+/// POST /api/files/apply-patch — Apply a multi-file unified diff to the active project,
+/// all-or-nothing. `dry_run` only reports per-hunk status.
+pub async fn apply_patch(
+    State(state): State<AppState>,
+    Json(req): Json<ApplyPatchRequest>,
+) -> (StatusCode, Json<Value>) {
+    let Some(project) = crate::projects::active_project(&state.db).await else {
+        return api_error(
+            StatusCode::CONFLICT,
+            "no active project — patches are applied to its root",
+        );
+    };
+    let root = PathBuf::from(&project.root_path);
+    let files = match parse_patch(&req.patch) {
+        Ok(files) => files,
+        Err(e) => return api_error(StatusCode::BAD_REQUEST, format!("invalid patch: {}", e)),
+    };
+    let (report, changes, all_ok) = match plan_changes(&root, &files).await {
+        Ok(plan) => plan,
+        Err(e) => return e,
+    };
+
+    if !all_ok || req.dry_run {
+        let status = if all_ok {
+            StatusCode::OK
+        } else {
+            StatusCode::UNPROCESSABLE_ENTITY
+        };
+        return (
+            status,
+            Json(json!({ "applied": false, "dry_run": req.dry_run, "files": report })),
+        );
+    }
+
+    if let Err(e) = perform_all(&changes).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "applied": false,
+                "error": format!("write failed, all changes rolled back: {}", e),
+                "files": report,
+            })),
+        );
+    }
+
+    let paths: Vec<&str> = files.iter().map(FilePatch::display_path).collect();
+    crate::audit::log_audit(
+        &state.db,
+        "apply_patch",
+        json!({ "root": project.root_path, "files": paths }),
+        None,
+    )
+    .await;
+    (
+        StatusCode::OK,
+        Json(json!({ "applied": true, "dry_run": false, "files": report })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_content_has_no_diff() {
+        let d = unified_diff("a.txt", Some("x\ny\n"), "x\ny\n", 3);
+        assert!(d.diff.is_empty());
+        assert_eq!((d.additions, d.deletions), (0, 0));
+    }
+
+    #[test]
+    fn single_line_change_renders_one_hunk() {
+        let old = "one\ntwo\nthree\nfour\nfive\n";
+        let new = "one\ntwo\nTHREE\nfour\nfive\n";
+        let d = unified_diff("src/x.rs", Some(old), new, 1);
+        assert_eq!(
+            d.diff,
+            "--- a/src/x.rs\n+++ b/src/x.rs\n@@ -2,3 +2,3 @@\n two\n-three\n+THREE\n four\n"
+        );
+        assert_eq!((d.additions, d.deletions), (1, 1));
+    }
+
+    #[test]
+    fn line_ending_changes_are_shown() {
+        let d = unified_diff("a.txt", Some("x\ny\n"), "x\ny", 3);
+        assert_eq!(
+            d.diff,
+            "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n x\n-y\n+y\n\\ No newline at end of file\n"
+        );
+        let crlf = unified_diff("a.txt", Some("x\r\ny\r\n"), "x\ny\n", 3);
+        assert_eq!((crlf.additions, crlf.deletions), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn previews_stay_inside_the_root() {
+        let root = scratch_dir();
+        std::fs::write(root.join("a.txt"), "a\n").expect("seed file");
+        let root_str = root.to_string_lossy().into_owned();
+        assert!(preview_path(&root_str, "a.txt").await.is_ok());
+        let inside = root.join("a.txt").to_string_lossy().into_owned();
+        assert!(preview_path(&root_str, &inside).await.is_ok());
+        let outside = std::env::temp_dir().to_string_lossy().into_owned();
+        assert!(preview_path(&root_str, &outside).await.is_err());
+        assert!(preview_path(&root_str, "../x.txt").await.is_err());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn new_file_diffs_against_dev_null() {
+        let d = unified_diff("new.txt", None, "hello\nworld\n", 3);
+        assert!(
+            d.diff
+                .starts_with("--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n")
+        );
+        assert_eq!(d.additions, 2);
+    }
+
+    #[test]
+    fn distant_changes_become_separate_hunks() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new = old
+            .replacen("2\n", "two\n", 1)
+            .replacen("19\n", "nineteen\n", 1);
+        let d = unified_diff("n.txt", Some(&old), &new, 2);
+        assert_eq!(d.diff.matches("@@ -").count(), 2);
+    }
+
+    #[test]
+    fn generated_diff_applies_back() {
+        let old = "fn main() {\n    println!(\"hi\");\n}\n";
+        let new = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n";
+        let diff = unified_diff("src/main.rs", Some(old), new, 3).diff;
+        let files = parse_patch(&diff).expect("valid patch");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].new_path.as_deref(), Some("src/main.rs"));
+        let (patched, statuses) = apply_hunks(old, &files[0].hunks);
+        assert_eq!(patched.as_deref(), Some(new));
+        assert_eq!(statuses[0].status, "applied");
+    }
+
+    #[test]
+    fn hunk_with_moved_context_applies_with_offset() {
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n";
+        let files = parse_patch(patch).expect("valid patch");
+        let (patched, statuses) = apply_hunks("x\ny\na\nb\nc\n", &files[0].hunks);
+        assert_eq!(patched.as_deref(), Some("x\ny\na\nB\nc\n"));
+        assert_eq!(statuses[0].status, "offset");
+        assert_eq!(statuses[0].offset, 2);
+    }
+
+    #[test]
+    fn mismatched_context_fails_the_file() {
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+B\n";
+        let files = parse_patch(patch).expect("valid patch");
+        let (patched, statuses) = apply_hunks("a\nzzz\n", &files[0].hunks);
+        assert!(patched.is_none());
+        assert_eq!(statuses[0].status, "failed");
+    }
+
+    #[test]
+    fn multi_file_patch_with_creation_and_crlf() {
+        let patch = "diff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-old\n+new\n\
+                     --- /dev/null\n+++ b/y/new.txt\n@@ -0,0 +1,2 @@\n+hello\n+world\n";
+        let files = parse_patch(patch).expect("valid patch");
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].old_path, None);
+        let (patched, _) = apply_hunks("old\r\nkeep\r\n", &files[0].hunks);
+        assert_eq!(patched.as_deref(), Some("new\r\nkeep\r\n"));
+        let (created, _) = apply_hunks("", &files[1].hunks);
+        assert_eq!(created.as_deref(), Some("hello\nworld\n"));
+    }
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gh-patch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create scratch dir");
+        dir
+    }
+
+    #[tokio::test]
+    async fn rename_reads_old_path_and_moves_the_file() {
+        let root = scratch_dir();
+        std::fs::write(root.join("old.txt"), "a\nb\n").expect("seed file");
+        let patch = "--- a/old.txt\n+++ b/new.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+B\n";
+        let files = parse_patch(patch).expect("valid patch");
+        let (report, changes, all_ok) = plan_changes(&root, &files).await.expect("plan");
+        assert!(all_ok, "{:?}", report);
+        assert_eq!(report[0]["action"], "rename");
+        perform_all(&changes).await.expect("apply");
+        assert!(!root.join("old.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("new.txt")).expect("renamed file"),
+            "a\nB\n"
+        );
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn duplicate_targets_are_rejected() {
+        let root = scratch_dir();
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -1 +1 @@\n-a\n+b\n\
+                     --- a/f.txt\n+++ b/f.txt\n@@ -1 +1 @@\n-b\n+c\n";
+        let files = parse_patch(patch).expect("valid patch");
+        let (status, _) = plan_changes(&root, &files).await.err().expect("rejected");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let escaping = parse_patch("--- a/../x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n").expect("valid");
+        assert!(plan_changes(&root, &escaping).await.is_err());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn failed_apply_removes_created_directories() {
+        let root = scratch_dir();
+        std::fs::write(root.join("blocker"), "not a directory").expect("seed file");
+        let changes = vec![
+            FileChange::Write {
+                path: root.join("new/deep/a.txt"),
+                original: None,
+                content: "a\n".to_string(),
+            },
+            FileChange::Write {
+                path: root.join("blocker/b.txt"),
+                original: None,
+                content: "b\n".to_string(),
+            },
+        ];
+        assert!(perform_all(&changes).await.is_err());
+        assert!(!root.join("new").exists());
+        assert!(root.join("blocker").is_file());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn delete_must_consume_the_whole_file() {
+        let root = scratch_dir();
+        std::fs::write(root.join("gone.txt"), "a\nb\nadded later\n").expect("seed file");
+        let stale = parse_patch("--- a/gone.txt\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-a\n-b\n")
+            .expect("valid patch");
+        let (report, changes, all_ok) = plan_changes(&root, &stale).await.expect("plan");
+        assert!(!all_ok);
+        assert!(changes.is_empty());
+        assert!(report[0]["error"].is_string());
+
+        let full =
+            parse_patch("--- a/gone.txt\n+++ /dev/null\n@@ -1,3 +0,0 @@\n-a\n-b\n-added later\n")
+                .expect("valid patch");
+        let (_, changes, all_ok) = plan_changes(&root, &full).await.expect("plan");
+        assert!(all_ok);
+        perform_all(&changes).await.expect("apply");
+        assert!(!root.join("gone.txt").exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_out_of_the_root_are_rejected() {
+        let root = scratch_dir();
+        let outside = scratch_dir();
+        std::fs::write(outside.join("secret.txt"), "a\n").expect("seed file");
+        std::os::unix::fs::symlink(&outside, root.join("link")).expect("symlink");
+        let patch =
+            parse_patch("--- a/link/secret.txt\n+++ b/link/secret.txt\n@@ -1 +1 @@\n-a\n+b\n")
+                .expect("valid patch");
+        let (status, _) = plan_changes(&root, &patch).await.err().expect("rejected");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            std::fs::read_to_string(outside.join("secret.txt")).expect("untouched"),
+            "a\n"
+        );
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::remove_dir_all(&outside).ok();
+    }
+
+    #[tokio::test]
+    async fn staged_writes_never_clobber_siblings() {
+        let root = scratch_dir();
+        std::fs::write(root.join("a.ghpatch.tmp"), "keep\n").expect("seed sibling");
+        let changes = vec![FileChange::Write {
+            path: root.join("a.txt"),
+            original: None,
+            content: "new\n".to_string(),
+        }];
+        perform_all(&changes).await.expect("apply");
+        assert_eq!(
+            std::fs::read_to_string(root.join("a.ghpatch.tmp")).expect("sibling"),
+            "keep\n"
+        );
+        assert_ne!(
+            temp_path(&root.join("a.txt")),
+            temp_path(&root.join("a.txt"))
+        );
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn missing_newline_markers_are_honoured() {
+        let drop = "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n";
+        let files = parse_patch(drop).expect("valid patch");
+        let (patched, _) = apply_hunks("a\nb\n", &files[0].hunks);
+        assert_eq!(patched.as_deref(), Some("a\nb"));
+
+        let add = "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n";
+        let files = parse_patch(add).expect("valid patch");
+        let (patched, _) = apply_hunks("a\nb", &files[0].hunks);
+        assert_eq!(patched.as_deref(), Some("a\nb\n"));
+        // The old side claims no final newline, but the file has one.
+        let (stale, _) = apply_hunks("a\nb\n", &files[0].hunks);
+        assert!(stale.is_none());
+
+        let old = "x\ny";
+        let new = "x\nz\n";
+        let diff = unified_diff("f", Some(old), new, 3).diff;
+        let files = parse_patch(&diff).expect("valid patch");
+        let (patched, _) = apply_hunks(old, &files[0].hunks);
+        assert_eq!(patched.as_deref(), Some(new));
+    }
+
+    #[test]
+    fn truncated_hunk_is_rejected() {
+        assert!(parse_patch("--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n").is_err());
+        assert!(parse_patch("just text").is_err());
+    }
+}
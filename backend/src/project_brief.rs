@@ -0,0 +1,331 @@
+// ---------------------------------------------------------------------------
+// project_brief.rs — Structured "project brief" for agent workspaces
+//
+// Scans a workspace (README, manifests, top-level layout), asks Gemini for a
+// short summary and caches it per workspace root. The brief is injected as
+// standing context into every execution whose working directory points at
+// that workspace, and is regenerated when any key file changes. When Gemini
+// fails the structural outline is used instead and retried after a few minutes.
+//
+// Executions never wait for Gemini: a missing or stale brief is built in the
+// background (one build per workspace at a time) and injected once ready.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::state::AppState;
+
+/// Cache of generated briefs keyed by canonical workspace root.
+pub type ProjectBriefCache = Arc<BriefCache>;
+
+#[derive(Default)]
+pub struct BriefCache {
+    briefs: RwLock<HashMap<PathBuf, Arc<ProjectBrief>>>,
+    /// Per-root build lock, held for the whole generation so concurrent
+    /// requests for one workspace share a single Gemini call.
+    building: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+impl BriefCache {
+    fn build_lock(&self, root: &Path) -> Arc<Mutex<()>> {
+        self.building
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(root.to_path_buf())
+            .or_default()
+            .clone()
+    }
+}
+
+/// README candidates, checked in order — the first hit is used.
+const README_FILES: &[&str] = &["README.md", "README", "README.txt", "readme.md"];
+
+/// Manifest file name -> detected stack.
+const MANIFESTS: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust"),
+    ("package.json", "Node.js / TypeScript"),
+    ("pyproject.toml", "Python"),
+    ("requirements.txt", "Python"),
+    ("go.mod", "Go"),
+    ("pom.xml", "Java (Maven)"),
+    ("build.gradle", "Java / Kotlin (Gradle)"),
+    ("composer.json", "PHP"),
+    ("Gemfile", "Ruby"),
+];
+
+/// Directories never worth listing in a brief.
+const SKIP_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "__pycache__"];
+
+const MAX_TOP_LEVEL_ENTRIES: usize = 40;
+const MAX_EXCERPT_CHARS: usize = 2000;
+const MAX_BRIEF_CHARS: usize = 4000;
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(20);
+/// A structural fallback (Gemini failed) is only reused this long, so a
+/// transient error doesn't pin a degraded brief until a key file changes.
+const FALLBACK_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectBrief {
+    pub workspace: String,
+    pub name: String,
+    pub stack: Vec<String>,
+    pub key_files: Vec<String>,
+    pub top_level: Vec<String>,
+    /// Markdown summary injected into the system prompt.
+    pub summary: String,
+    /// `true` when `summary` came from Gemini, `false` for the structural fallback.
+    pub model_generated: bool,
+    /// SHA-256 over key file names, sizes and mtimes — a change triggers regeneration.
+    pub fingerprint: String,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ProjectBrief {
+    /// Whether this brief can still be served for `fingerprint`.
+    fn is_fresh(&self, fingerprint: &str) -> bool {
+        self.fingerprint == fingerprint
+            && (self.model_generated
+                || (chrono::Utc::now() - self.generated_at)
+                    .to_std()
+                    .is_ok_and(|age| age < FALLBACK_TTL))
+    }
+}
+
+/// Canonical directory for `workspace`, or `None` when it is empty or not a directory.
+async fn workspace_root(workspace: &str) -> Option<PathBuf> {
+    if workspace.trim().is_empty() {
+        return None;
+    }
+    let root = tokio::fs::canonicalize(workspace).await.ok()?;
+    tokio::fs::metadata(&root)
+        .await
+        .ok()?
+        .is_dir()
+        .then_some(root)
+}
+
+/// Cached brief for `root` if its fingerprint still matches (and, for a
+/// fallback, it is younger than `FALLBACK_TTL`).
+async fn fresh_cached(
+    state: &AppState,
+    root: &Path,
+    fingerprint: &str,
+) -> Option<Arc<ProjectBrief>> {
+    state
+        .project_briefs
+        .briefs
+        .read()
+        .await
+        .get(root)
+        .filter(|b| b.is_fresh(fingerprint))
+        .cloned()
+}
+
+/// Build (or pick up a concurrently built) brief for `root` under its build lock.
+async fn build_locked(state: &AppState, root: PathBuf) -> Arc<ProjectBrief> {
+    let lock = state.project_briefs.build_lock(&root);
+    let _guard = lock.lock().await;
+
+    let key_files = find_key_files(&root).await;
+    let fingerprint = fingerprint(&root, &key_files).await;
+    if let Some(cached) = fresh_cached(state, &root, &fingerprint).await {
+        return cached;
+    }
+
+    let brief = Arc::new(build_brief(state, &root, key_files, fingerprint).await);
+    tracing::info!(
+        workspace = %brief.workspace,
+        model_generated = brief.model_generated,
+        "project_brief: generated"
+    );
+    state
+        .project_briefs
+        .briefs
+        .write()
+        .await
+        .insert(root, brief.clone());
+    brief
+}
+
+/// Return the brief for `workspace`, regenerating it when the key files changed.
+/// Returns `None` when the path is empty or not a directory.
+pub async fn generate_project_brief(
+    state: &AppState,
+    workspace: &str,
+) -> Option<Arc<ProjectBrief>> {
+    let root = workspace_root(workspace).await?;
+    Some(build_locked(state, root).await)
+}
+
+/// Append the workspace brief (if any) to the execution context's system prompt.
+///
+/// Never blocks on generation: when the brief is missing or stale a background
+/// build is started (unless one is already running) and the last brief, if
+/// any, is used for this execution.
+pub async fn inject_into_context(
+    state: &AppState,
+    ctx: &mut jaskier_core::context::ExecuteContext,
+) {
+    let Some(root) = workspace_root(&ctx.working_directory).await else {
+        return;
+    };
+    let key_files = find_key_files(&root).await;
+    let fingerprint = fingerprint(&root, &key_files).await;
+    let cached = state.project_briefs.briefs.read().await.get(&root).cloned();
+
+    let is_fresh = cached.as_ref().is_some_and(|b| b.is_fresh(&fingerprint));
+    if !is_fresh && state.project_briefs.build_lock(&root).try_lock().is_ok() {
+        let state = state.clone();
+        tokio::spawn(async move {
+            build_locked(&state, root).await;
+        });
+    }
+
+    if let Some(brief) = cached {
+        ctx.system_prompt.push_str(&format!(
+            "\n\n# Project Brief (standing context)\n{}",
+            brief.summary
+        ));
+    }
+}
+
+async fn find_key_files(root: &Path) -> Vec<String> {
+    let mut found = Vec::new();
+    for name in README_FILES {
+        if tokio::fs::metadata(root.join(name)).await.is_ok() {
+            found.push((*name).to_string());
+            break;
+        }
+    }
+    for (name, _) in MANIFESTS {
+        if tokio::fs::metadata(root.join(name)).await.is_ok() {
+            found.push((*name).to_string());
+        }
+    }
+    found
+}
+
+async fn fingerprint(root: &Path, key_files: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for name in key_files {
+        hasher.update(name.as_bytes());
+        if let Ok(meta) = tokio::fs::metadata(root.join(name)).await {
+            hasher.update(meta.len().to_le_bytes());
+            if let Ok(modified) = meta.modified()
+                && let Ok(since) = modified.duration_since(std::time::UNIX_EPOCH)
+            {
+                hasher.update(since.as_secs().to_le_bytes());
+            }
+        }
+    }
+    hex::encode(hasher.finalize())
+}
+
+async fn list_top_level(root: &Path) -> Vec<String> {
+    let mut entries = Vec::new();
+    let Ok(mut dir) = tokio::fs::read_dir(root).await else {
+        return entries;
+    };
+    while let Ok(Some(entry)) = dir.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || SKIP_DIRS.contains(&name.as_str()) {
+            continue;
+        }
+        let is_dir = entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false);
+        entries.push(if is_dir { format!("{}/", name) } else { name });
+    }
+    entries.sort();
+    entries.truncate(MAX_TOP_LEVEL_ENTRIES);
+    entries
+}
+
+async fn read_excerpt(path: &Path) -> Option<String> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    Some(content.chars().take(MAX_EXCERPT_CHARS).collect())
+}
+
+async fn build_brief(
+    state: &AppState,
+    root: &Path,
+    key_files: Vec<String>,
+    fingerprint: String,
+) -> ProjectBrief {
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.display().to_string());
+    let mut stack: Vec<String> = Vec::new();
+    for (file, label) in MANIFESTS {
+        if key_files.iter().any(|k| k == file) && !stack.iter().any(|s| s == label) {
+            stack.push((*label).to_string());
+        }
+    }
+    let top_level = list_top_level(root).await;
+
+    let mut excerpts = String::new();
+    for file in &key_files {
+        if let Some(text) = read_excerpt(&root.join(file)).await {
+            excerpts.push_str(&format!("\n--- {} ---\n{}\n", file, text));
+        }
+    }
+
+    let outline = format!(
+        "## {}\nRoot: {}\nStack: {}\nKey files: {}\nTop-level: {}",
+        name,
+        root.display(),
+        if stack.is_empty() {
+            "unknown".to_string()
+        } else {
+            stack.join(", ")
+        },
+        key_files.join(", "),
+        top_level.join(", "),
+    );
+
+    let (summary, model_generated) = match summarize_with_gemini(state, &outline, &excerpts).await {
+        Some(text) => (format!("{}\n\n{}", outline, text), true),
+        None => (outline, false),
+    };
+
+    ProjectBrief {
+        workspace: root.display().to_string(),
+        name,
+        stack,
+        key_files,
+        top_level,
+        summary: summary.chars().take(MAX_BRIEF_CHARS).collect(),
+        model_generated,
+        fingerprint,
+        generated_at: chrono::Utc::now(),
+    }
+}
+
+/// Ask Gemini for a compact brief. Returns `None` without a credential or on any failure,
+/// in which case the structural outline alone is used.
+async fn summarize_with_gemini(state: &AppState, outline: &str, excerpts: &str) -> Option<String> {
+    let prompt = format!(
+        "Write a concise project brief (max 200 words, markdown bullet points) for engineers \
+         about to work in this repository: purpose, main components, how to build/run, and \
+         conventions worth knowing. Use only the information below.\n\n{}\n{}",
+        outline, excerpts
+    );
+    let call = crate::gemini_api::generate_content(state, &prompt, 0.2, false);
+    match tokio::time::timeout(SUMMARY_TIMEOUT, call).await {
+        Ok(Ok(text)) => Some(text),
+        Ok(Err(e)) => {
+            tracing::debug!("project_brief: summary skipped: {}", e);
+            None
+        }
+        Err(_) => {
+            tracing::warn!("project_brief: summary timed out");
+            None
+        }
+    }
+}
@@ -0,0 +1,504 @@
+// ---------------------------------------------------------------------------
+// projects.rs — Project workspaces (root path, default model, system prompt)
+//
+// A project scopes chats, memories and swarm tasks to one codebase. An
+// execution's project is the one whose root contains its working directory
+// (with no working directory, the active project's root is used); it gets
+// that project's system prompt and default model. Memories are tagged with
+// the execution's or session's project where they are written. Sessions,
+// created by the shared crate, are tagged on their first chat turn by
+// `tag_session`, with paths normalised here rather than compared as raw
+// strings in SQL; swarm tasks and sessions without a working directory are
+// tagged by triggers (migration 064). `/api/projects/{id}/sessions` and
+// `/memories` list one project's rows only.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::path::Path as FsPath;
+use std::sync::{LazyLock, Mutex};
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+type ProjectResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+/// Whole-row JSON projection, used in SELECT and RETURNING clauses.
+const PROJECT_JSON: &str = "to_jsonb(gh_projects)";
+
+/// Working directory -> canonical path, so executions don't hit the filesystem
+/// every time. Cleared wholesale once it reaches `CANONICAL_CACHE_LIMIT`.
+static CANONICAL_DIRS: LazyLock<Mutex<HashMap<String, String>>> = LazyLock::new(Mutex::default);
+const CANONICAL_CACHE_LIMIT: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: Uuid,
+    pub name: String,
+    pub root_path: String,
+    pub default_model: Option<String>,
+    pub system_prompt: String,
+    pub is_active: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProjectRequest {
+    pub name: String,
+    pub root_path: String,
+    #[serde(default)]
+    pub default_model: Option<String>,
+    #[serde(default)]
+    pub system_prompt: String,
+    /// Switch to the new project right away.
+    #[serde(default)]
+    pub activate: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProjectRequest {
+    pub name: Option<String>,
+    pub root_path: Option<String>,
+    /// `""` clears the default model.
+    pub default_model: Option<String>,
+    pub system_prompt: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignSessionRequest {
+    /// `null` detaches the session from any project.
+    pub project_id: Option<Uuid>,
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+fn db_error(context: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    if let sqlx::Error::Database(ref db) = e
+        && db.code().as_deref() == Some("23505")
+    {
+        let message = match db.constraint() {
+            Some("gh_projects_root_path_key") => "a project with this root path already exists",
+            Some("idx_gh_projects_active") => "another project was activated at the same time",
+            _ => "project conflicts with an existing row",
+        };
+        return api_error(StatusCode::CONFLICT, message);
+    }
+    tracing::error!("projects: {}: {}", context, e);
+    api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+fn not_found(id: Uuid) -> (StatusCode, Json<Value>) {
+    api_error(StatusCode::NOT_FOUND, format!("project {} not found", id))
+}
+
+/// Canonical form of `path`, without the `\\?\` verbatim prefix Windows
+/// adds, so root paths and working directories always compare alike.
+async fn canonicalize(path: &str) -> Option<std::path::PathBuf> {
+    let canonical = tokio::fs::canonicalize(path).await.ok()?;
+    Some(dunce::simplified(&canonical).to_path_buf())
+}
+
+/// Canonicalise a root path so prefix matching against working directories is reliable.
+async fn normalize_root(raw: &str) -> Result<String, (StatusCode, Json<Value>)> {
+    let trimmed = raw.trim();
+    match canonicalize(trimmed).await {
+        Some(path) if path.is_dir() => Ok(path.display().to_string()),
+        _ => Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("'{}' is not an existing directory", trimmed),
+        )),
+    }
+}
+
+/// `true` when `dir` is `root` or lies below it (component-wise, not string prefix).
+fn is_within(dir: &str, root: &str) -> bool {
+    FsPath::new(dir).starts_with(FsPath::new(root))
+}
+
+fn parse_project(row: Value) -> Option<Project> {
+    serde_json::from_value(row)
+        .map_err(|e| tracing::warn!("projects: malformed row: {}", e))
+        .ok()
+}
+
+pub async fn active_project(db: &sqlx::PgPool) -> Option<Project> {
+    sqlx::query_scalar::<_, Value>(&format!(
+        "SELECT {} FROM gh_projects WHERE is_active LIMIT 1",
+        PROJECT_JSON
+    ))
+    .fetch_optional(db)
+    .await
+    .map_err(|e| tracing::warn!("projects: active lookup failed: {}", e))
+    .ok()
+    .flatten()
+    .and_then(parse_project)
+}
+
+async fn canonical_dir(working_directory: &str) -> Option<String> {
+    let cached = CANONICAL_DIRS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(working_directory)
+        .cloned();
+    if cached.is_some() {
+        return cached;
+    }
+    let dir = canonicalize(working_directory).await?.display().to_string();
+    let mut cache = CANONICAL_DIRS.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= CANONICAL_CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(working_directory.to_string(), dir.clone());
+    Some(dir)
+}
+
+/// Project whose root contains `working_directory` (deepest root wins).
+/// The prefix match runs in SQL; `is_within` re-checks it component-wise.
+pub async fn project_for_directory(db: &sqlx::PgPool, working_directory: &str) -> Option<Project> {
+    let dir = canonical_dir(working_directory).await?;
+    let projects = sqlx::query_scalar::<_, Value>(&format!(
+        "SELECT {} FROM gh_projects \
+         WHERE $1 = root_path \
+            OR starts_with($1, rtrim(root_path, '/\\') || '/') \
+            OR starts_with($1, rtrim(root_path, '/\\') || E'\\\\') \
+         ORDER BY length(root_path) DESC",
+        PROJECT_JSON
+    ))
+    .bind(&dir)
+    .fetch_all(db)
+    .await
+    .map_err(|e| tracing::warn!("projects: lookup failed: {}", e))
+    .ok()?;
+    projects
+        .into_iter()
+        .filter_map(parse_project)
+        .find(|p| is_within(&dir, &p.root_path))
+}
+
+/// Project an execution with `working_directory` runs in: the one containing
+/// the directory, or the active project when there is none.
+pub async fn execution_project(db: &sqlx::PgPool, working_directory: &str) -> Option<Project> {
+    if working_directory.trim().is_empty() {
+        active_project(db).await
+    } else {
+        project_for_directory(db, working_directory).await
+    }
+}
+
+/// Tag a session that has no project yet with the project of its working
+/// directory. Sessions without a working directory were already tagged with
+/// the active project on insert.
+pub async fn tag_session(db: &sqlx::PgPool, session_id: &Uuid) {
+    let working_directory: Option<String> = sqlx::query_scalar(
+        "SELECT working_directory FROM gh_sessions WHERE id = $1 AND project_id IS NULL",
+    )
+    .bind(session_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|e| tracing::warn!("projects: session lookup failed: {}", e))
+    .ok()
+    .flatten()
+    .flatten();
+    let Some(working_directory) = working_directory.filter(|wd| !wd.trim().is_empty()) else {
+        return;
+    };
+    let Some(project) = project_for_directory(db, &working_directory).await else {
+        return;
+    };
+    if let Err(e) =
+        sqlx::query("UPDATE gh_sessions SET project_id = $1 WHERE id = $2 AND project_id IS NULL")
+            .bind(project.id)
+            .bind(session_id)
+            .execute(db)
+            .await
+    {
+        tracing::warn!("projects: failed to tag session {}: {}", session_id, e);
+    }
+}
+
+/// Apply project scoping to an execution context: default the working directory
+/// to the active project, append the project's system prompt and — unless the
+/// caller picked a model explicitly — use the project's default model.
+pub async fn apply_to_context(
+    state: &AppState,
+    ctx: &mut jaskier_core::context::ExecuteContext,
+    model_overridden: bool,
+) {
+    let Some(project) = execution_project(&state.db, &ctx.working_directory).await else {
+        return;
+    };
+    if ctx.working_directory.trim().is_empty() {
+        ctx.working_directory = project.root_path.clone();
+    }
+
+    if !project.system_prompt.trim().is_empty() {
+        ctx.system_prompt.push_str(&format!(
+            "\n\n# Project: {}\n{}",
+            project.name, project.system_prompt
+        ));
+    }
+    if !model_overridden && let Some(model) = project.default_model.filter(|m| !m.is_empty()) {
+        ctx.model = model;
+    }
+}
+
+// ── Handlers ─────────────────────────────────────────────────────────────────
+
+/// GET /api/projects — All projects with session and memory counts.
+pub async fn list_projects(State(state): State<AppState>) -> ProjectResult {
+    let rows: Vec<Value> = sqlx::query_scalar(
+        "SELECT jsonb_build_object( \
+           'id', p.id, 'name', p.name, 'root_path', p.root_path, \
+           'default_model', p.default_model, 'system_prompt', p.system_prompt, \
+           'is_active', p.is_active, 'created_at', p.created_at, 'updated_at', p.updated_at, \
+           'session_count', (SELECT COUNT(*) FROM gh_sessions s WHERE s.project_id = p.id), \
+           'memory_count', (SELECT COUNT(*) FROM gh_memories m WHERE m.project_id = p.id)) \
+         FROM gh_projects p ORDER BY p.is_active DESC, p.updated_at DESC",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("list failed", e))?;
+    Ok(Json(json!({ "projects": rows })))
+}
+
+/// POST /api/projects — Register a project root.
+pub async fn create_project(
+    State(state): State<AppState>,
+    Json(req): Json<CreateProjectRequest>,
+) -> ProjectResult {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "name is required"));
+    }
+    let root_path = normalize_root(&req.root_path).await?;
+
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    if req.activate {
+        sqlx::query("UPDATE gh_projects SET is_active = FALSE WHERE is_active")
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| db_error("deactivate failed", e))?;
+    }
+    let project = sqlx::query_scalar::<_, Value>(&format!(
+        "INSERT INTO gh_projects (name, root_path, default_model, system_prompt, is_active) \
+         VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+        PROJECT_JSON
+    ))
+    .bind(name)
+    .bind(&root_path)
+    .bind(req.default_model.filter(|m| !m.is_empty()))
+    .bind(&req.system_prompt)
+    .bind(req.activate)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| db_error("insert failed", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+
+    tracing::info!(project = name, root = %root_path, "projects: created");
+    Ok(Json(project))
+}
+
+/// PATCH /api/projects/{id} — Update name, root, default model or system prompt.
+pub async fn update_project(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateProjectRequest>,
+) -> ProjectResult {
+    let root_path = match req.root_path.as_deref() {
+        Some(raw) => Some(normalize_root(raw).await?),
+        None => None,
+    };
+    let project = sqlx::query_scalar::<_, Value>(&format!(
+        "UPDATE gh_projects SET \
+           name = COALESCE($2, name), \
+           root_path = COALESCE($3, root_path), \
+           default_model = CASE WHEN $4::text IS NULL THEN default_model \
+                                ELSE NULLIF($4, '') END, \
+           system_prompt = COALESCE($5, system_prompt), \
+           updated_at = NOW() \
+         WHERE id = $1 RETURNING {}",
+        PROJECT_JSON
+    ))
+    .bind(id)
+    .bind(req.name.as_deref().map(str::trim).filter(|n| !n.is_empty()))
+    .bind(root_path)
+    .bind(req.default_model)
+    .bind(req.system_prompt)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("update failed", e))?;
+    project.map(Json).ok_or_else(|| not_found(id))
+}
+
+/// DELETE /api/projects/{id} — Remove a project; tagged rows fall back to "no project".
+pub async fn delete_project(State(state): State<AppState>, Path(id): Path<Uuid>) -> ProjectResult {
+    let result = sqlx::query("DELETE FROM gh_projects WHERE id = $1")
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("delete failed", e))?;
+    if result.rows_affected() == 0 {
+        return Err(not_found(id));
+    }
+    Ok(Json(json!({ "deleted": id })))
+}
+
+/// POST /api/projects/{id}/activate — Switch the active project.
+pub async fn activate_project(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> ProjectResult {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    sqlx::query("UPDATE gh_projects SET is_active = FALSE WHERE is_active AND id <> $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| db_error("deactivate failed", e))?;
+    let project = sqlx::query_scalar::<_, Value>(&format!(
+        "UPDATE gh_projects SET is_active = TRUE, updated_at = NOW() WHERE id = $1 RETURNING {}",
+        PROJECT_JSON
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| db_error("activate failed", e))?
+    .ok_or_else(|| not_found(id))?;
+    tx.commit()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    tracing::info!(project = %id, "projects: activated");
+    Ok(Json(project))
+}
+
+/// POST /api/projects/deactivate — Leave project mode (executions without a
+/// working directory no longer fall back to a project).
+pub async fn deactivate_projects(State(state): State<AppState>) -> ProjectResult {
+    sqlx::query("UPDATE gh_projects SET is_active = FALSE WHERE is_active")
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("deactivate failed", e))?;
+    Ok(Json(json!({ "active": null })))
+}
+
+/// PUT /api/sessions/{id}/project — Move an existing chat (and the memories
+/// distilled from it) into or out of a project.
+pub async fn assign_session_project(
+    State(state): State<AppState>,
+    Path(session_id): Path<Uuid>,
+    Json(req): Json<AssignSessionRequest>,
+) -> ProjectResult {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    let result = sqlx::query("UPDATE gh_sessions SET project_id = $1 WHERE id = $2")
+        .bind(req.project_id)
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.code().as_deref() == Some("23503") => {
+                api_error(StatusCode::NOT_FOUND, "project not found")
+            }
+            e => db_error("assign failed", e),
+        })?;
+    if result.rows_affected() == 0 {
+        return Err(api_error(StatusCode::NOT_FOUND, "session not found"));
+    }
+    let memories = sqlx::query("UPDATE gh_memories SET project_id = $1 WHERE session_id = $2")
+        .bind(req.project_id)
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| db_error("memory reassign failed", e))?
+        .rows_affected();
+    tx.commit()
+        .await
+        .map_err(|e| db_error("transaction failed", e))?;
+    Ok(Json(json!({
+        "session_id": session_id,
+        "project_id": req.project_id,
+        "memories_moved": memories,
+    })))
+}
+
+async fn ensure_exists(state: &AppState, id: Uuid) -> Result<(), (StatusCode, Json<Value>)> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM gh_projects WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&state.db)
+            .await
+            .map_err(|e| db_error("lookup failed", e))?;
+    if exists { Ok(()) } else { Err(not_found(id)) }
+}
+
+/// GET /api/projects/{id}/sessions — Chats of one project, newest first.
+pub async fn list_project_sessions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> ProjectResult {
+    ensure_exists(&state, id).await?;
+    let sessions: Vec<Value> = sqlx::query_scalar(
+        "SELECT jsonb_build_object( \
+           'id', s.id, 'title', s.title, 'agent_id', s.agent_id, \
+           'working_directory', s.working_directory, \
+           'created_at', s.created_at, 'updated_at', s.updated_at, \
+           'message_count', (SELECT COUNT(*) FROM gh_chat_messages m WHERE m.session_id = s.id)) \
+         FROM gh_sessions s WHERE s.project_id = $1 ORDER BY s.updated_at DESC",
+    )
+    .bind(id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("session list failed", e))?;
+    Ok(Json(json!({ "project_id": id, "sessions": sessions })))
+}
+
+/// GET /api/projects/{id}/memories — Memories of one project, most important first.
+pub async fn list_project_memories(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> ProjectResult {
+    ensure_exists(&state, id).await?;
+    let memories: Vec<Value> = sqlx::query_scalar(
+        "SELECT jsonb_build_object( \
+           'id', id, 'agent', agent, 'content', content, 'importance', importance, \
+           'session_id', session_id, 'created_at', created_at) \
+         FROM gh_memories WHERE project_id = $1 ORDER BY importance DESC, created_at DESC",
+    )
+    .bind(id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("memory list failed", e))?;
+    Ok(Json(json!({ "project_id": id, "memories": memories })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn within_matches_whole_components() {
+        assert!(is_within("/work/app", "/work/app"));
+        assert!(is_within("/work/app/src/lib", "/work/app"));
+        assert!(!is_within("/work/application", "/work/app"));
+        assert!(!is_within("/work", "/work/app"));
+    }
+}
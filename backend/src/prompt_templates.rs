@@ -0,0 +1,499 @@
+// ---------------------------------------------------------------------------
+// prompt_templates.rs — Library of named system prompts with {{variables}}
+//
+// Templates live in gh_prompt_templates. `{{name}}` placeholders are filled
+// from caller-supplied variables at render time; unknown placeholders are
+// left in place and reported so the UI can ask for them. A template marked
+// `default_for_agent` is rendered into every execution of that agent (chat
+// and A2A) with the built-in variables (`BUILTIN_VARIABLES`) plus the
+// user-defined ones in gh_prompt_variables; such a template is rejected when
+// it uses a placeholder neither of them provides.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+type TemplateResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+const MAX_TEMPLATE_CHARS: usize = 32_000;
+
+static PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.]*)\s*\}\}").expect("valid regex"));
+static VARIABLE_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_.]{0,63}$").expect("valid regex"));
+
+/// Variables every execution fills in from its own context.
+pub const BUILTIN_VARIABLES: &[&str] = &[
+    "agent",
+    "model",
+    "working_directory",
+    "project",
+    "prompt",
+    "date",
+    "time",
+];
+
+/// Substitute `{{var}}` placeholders. Returns the rendered text and the
+/// names of placeholders that had no value (left untouched, deduplicated).
+pub fn render(template: &str, vars: &HashMap<String, String>) -> (String, Vec<String>) {
+    let mut missing: Vec<String> = Vec::new();
+    let rendered =
+        PLACEHOLDER_RE.replace_all(template, |c: &regex::Captures<'_>| match vars.get(&c[1]) {
+            Some(value) => value.clone(),
+            None => {
+                if !missing.iter().any(|m| m == &c[1]) {
+                    missing.push(c[1].to_string());
+                }
+                c[0].to_string()
+            }
+        });
+    (rendered.into_owned(), missing)
+}
+
+/// Placeholder names used by a template, in order of first appearance.
+pub fn variables(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for c in PLACEHOLDER_RE.captures_iter(template) {
+        if !names.iter().any(|n| n == &c[1]) {
+            names.push(c[1].to_string());
+        }
+    }
+    names
+}
+
+/// User-defined variables from gh_prompt_variables (empty when the lookup fails).
+async fn stored_variables(db: &sqlx::PgPool) -> HashMap<String, String> {
+    sqlx::query_as::<_, (String, String)>("SELECT name, value FROM gh_prompt_variables")
+        .fetch_all(db)
+        .await
+        .map_err(|e| tracing::warn!("prompt_templates: variable lookup failed: {}", e))
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+/// Placeholders of `content` that neither a built-in nor a stored variable can fill.
+async fn unresolvable(db: &sqlx::PgPool, content: &str) -> Vec<String> {
+    let custom: Vec<String> = variables(content)
+        .into_iter()
+        .filter(|v| !BUILTIN_VARIABLES.contains(&v.as_str()))
+        .collect();
+    if custom.is_empty() {
+        return custom;
+    }
+    let stored = stored_variables(db).await;
+    custom
+        .into_iter()
+        .filter(|v| !stored.contains_key(v))
+        .collect()
+}
+
+/// 400 listing the placeholders an agent default template could not fill at chat time.
+async fn check_resolvable(
+    db: &sqlx::PgPool,
+    content: &str,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let missing = unresolvable(db, content).await;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err((
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "error": format!(
+                "agent default templates can only use built-in or stored variables; unresolved: {}",
+                missing.join(", ")
+            ),
+            "unresolved": missing,
+            "builtin": BUILTIN_VARIABLES,
+        })),
+    ))
+}
+
+/// Append the agent's default template (if any) to the execution's system prompt.
+pub async fn apply_agent_default(
+    state: &AppState,
+    ctx: &mut jaskier_core::context::ExecuteContext,
+) {
+    let content: Option<String> = match sqlx::query_scalar(
+        "SELECT content FROM gh_prompt_templates WHERE default_for_agent = $1",
+    )
+    .bind(&ctx.agent_id)
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::warn!("prompt_templates: default lookup failed: {}", e);
+            return;
+        }
+    };
+    let Some(content) = content else {
+        return;
+    };
+
+    let used = variables(&content);
+    let mut vars = if used
+        .iter()
+        .any(|v| !BUILTIN_VARIABLES.contains(&v.as_str()))
+    {
+        stored_variables(&state.db).await
+    } else {
+        HashMap::new()
+    };
+    let project = if used.iter().any(|v| v == "project") {
+        crate::projects::project_for_directory(&state.db, &ctx.working_directory)
+            .await
+            .map(|p| p.name)
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let now = chrono::Utc::now();
+    vars.extend(
+        [
+            ("agent", ctx.agent_id.clone()),
+            ("model", ctx.model.clone()),
+            ("working_directory", ctx.working_directory.clone()),
+            ("project", project),
+            ("prompt", ctx.final_user_prompt.clone()),
+            ("date", now.format("%Y-%m-%d").to_string()),
+            ("time", now.format("%H:%M UTC").to_string()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v)),
+    );
+    let (rendered, missing) = render(&content, &vars);
+    if !missing.is_empty() {
+        tracing::warn!(
+            agent = %ctx.agent_id,
+            "prompt_templates: default template left placeholders unresolved: {}",
+            missing.join(", ")
+        );
+    }
+    ctx.system_prompt
+        .push_str(&format!("\n\n# Agent Instructions\n{}", rendered));
+}
+
+fn db_error(context: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    if let sqlx::Error::Database(ref db) = e
+        && db.code().as_deref() == Some("23505")
+    {
+        return api_error(
+            StatusCode::CONFLICT,
+            "name already taken or agent already has a default template",
+        );
+    }
+    tracing::error!("prompt_templates: {}: {}", context, e);
+    api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+fn not_found(name: &str) -> (StatusCode, Json<Value>) {
+    api_error(
+        StatusCode::NOT_FOUND,
+        format!("template '{}' not found", name),
+    )
+}
+
+fn with_variables(mut row: Value) -> Value {
+    let vars = row
+        .get("content")
+        .and_then(|c| c.as_str())
+        .map(variables)
+        .unwrap_or_default();
+    row["variables"] = json!(vars);
+    row
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateRequest {
+    pub name: String,
+    pub content: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub default_for_agent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTemplateRequest {
+    pub content: Option<String>,
+    pub description: Option<String>,
+    /// `""` removes the agent default.
+    pub default_for_agent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenderRequest {
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+fn validate_content(content: &str) -> Result<(), (StatusCode, Json<Value>)> {
+    if content.trim().is_empty() || content.chars().count() > MAX_TEMPLATE_CHARS {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("content must be 1..{} characters", MAX_TEMPLATE_CHARS),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/prompts — All templates with their placeholder names.
+pub async fn list_templates(State(state): State<AppState>) -> TemplateResult {
+    let rows: Vec<Value> =
+        sqlx::query_scalar("SELECT to_jsonb(t) FROM gh_prompt_templates t ORDER BY name")
+            .fetch_all(&state.db)
+            .await
+            .map_err(|e| db_error("list failed", e))?;
+    let templates: Vec<Value> = rows.into_iter().map(with_variables).collect();
+    Ok(Json(json!({ "templates": templates })))
+}
+
+/// GET /api/prompts/{name}
+pub async fn get_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> TemplateResult {
+    let name = name.trim();
+    let row: Option<Value> =
+        sqlx::query_scalar("SELECT to_jsonb(t) FROM gh_prompt_templates t WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| db_error("lookup failed", e))?;
+    row.map(|r| Json(with_variables(r)))
+        .ok_or_else(|| not_found(name))
+}
+
+/// POST /api/prompts — Create a template.
+pub async fn create_template(
+    State(state): State<AppState>,
+    Json(req): Json<TemplateRequest>,
+) -> TemplateResult {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "name is required"));
+    }
+    validate_content(&req.content)?;
+    let default_for_agent = req.default_for_agent.filter(|a| !a.trim().is_empty());
+    if default_for_agent.is_some() {
+        check_resolvable(&state.db, &req.content).await?;
+    }
+    let row: Value = sqlx::query_scalar(
+        "INSERT INTO gh_prompt_templates (name, description, content, default_for_agent) \
+         VALUES ($1, $2, $3, $4) RETURNING to_jsonb(gh_prompt_templates)",
+    )
+    .bind(name)
+    .bind(&req.description)
+    .bind(&req.content)
+    .bind(default_for_agent.as_deref().map(str::trim))
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| db_error("insert failed", e))?;
+    Ok(Json(with_variables(row)))
+}
+
+/// PATCH /api/prompts/{name} — Update content, description or agent default.
+pub async fn update_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateTemplateRequest>,
+) -> TemplateResult {
+    let name = name.trim();
+    if let Some(content) = req.content.as_deref() {
+        validate_content(content)?;
+    }
+    let default_for_agent = req.default_for_agent.as_deref().map(str::trim);
+    let current: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT content, default_for_agent FROM gh_prompt_templates WHERE name = $1",
+    )
+    .bind(name)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("lookup failed", e))?;
+    let (current_content, current_default) = current.ok_or_else(|| not_found(name))?;
+    let will_be_default = match default_for_agent {
+        Some(agent) => !agent.is_empty(),
+        None => current_default.is_some(),
+    };
+    if will_be_default {
+        check_resolvable(
+            &state.db,
+            req.content.as_deref().unwrap_or(&current_content),
+        )
+        .await?;
+    }
+    let row: Option<Value> = sqlx::query_scalar(
+        "UPDATE gh_prompt_templates SET \
+           content = COALESCE($2, content), \
+           description = COALESCE($3, description), \
+           default_for_agent = CASE WHEN $4::text IS NULL THEN default_for_agent \
+                                    ELSE NULLIF($4, '') END, \
+           updated_at = NOW() \
+         WHERE name = $1 RETURNING to_jsonb(gh_prompt_templates)",
+    )
+    .bind(name)
+    .bind(req.content)
+    .bind(req.description)
+    .bind(default_for_agent)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("update failed", e))?;
+    row.map(|r| Json(with_variables(r)))
+        .ok_or_else(|| not_found(name))
+}
+
+/// DELETE /api/prompts/{name}
+pub async fn delete_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> TemplateResult {
+    let name = name.trim();
+    let result = sqlx::query("DELETE FROM gh_prompt_templates WHERE name = $1")
+        .bind(name)
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("delete failed", e))?;
+    if result.rows_affected() == 0 {
+        return Err(not_found(name));
+    }
+    Ok(Json(json!({ "deleted": name })))
+}
+
+/// POST /api/prompts/{name}/render — Substitute variables and return the prompt.
+pub async fn render_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<RenderRequest>,
+) -> TemplateResult {
+    let name = name.trim();
+    let content: Option<String> =
+        sqlx::query_scalar("SELECT content FROM gh_prompt_templates WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| db_error("lookup failed", e))?;
+    let content = content.ok_or_else(|| not_found(name))?;
+    let mut vars = stored_variables(&state.db).await;
+    vars.extend(req.variables);
+    let (rendered, missing) = render(&content, &vars);
+    Ok(Json(json!({
+        "name": name,
+        "rendered": rendered,
+        "missing": missing,
+    })))
+}
+
+// ── Stored variables ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct VariableRequest {
+    pub value: String,
+}
+
+/// GET /api/prompt-variables — User-defined variables and the built-in names.
+pub async fn list_variables(State(state): State<AppState>) -> TemplateResult {
+    let rows: Vec<(String, String)> =
+        sqlx::query_as("SELECT name, value FROM gh_prompt_variables ORDER BY name")
+            .fetch_all(&state.db)
+            .await
+            .map_err(|e| db_error("variable list failed", e))?;
+    let variables: serde_json::Map<String, Value> = rows
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
+    Ok(Json(
+        json!({ "variables": variables, "builtin": BUILTIN_VARIABLES }),
+    ))
+}
+
+/// PUT /api/prompt-variables/{name} — Set a variable used at chat time.
+pub async fn set_variable(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<VariableRequest>,
+) -> TemplateResult {
+    let name = name.trim();
+    if !VARIABLE_NAME_RE.is_match(name) || BUILTIN_VARIABLES.contains(&name) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("'{}' is not a valid custom variable name", name),
+        ));
+    }
+    if req.value.chars().count() > MAX_TEMPLATE_CHARS {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("value must be at most {} characters", MAX_TEMPLATE_CHARS),
+        ));
+    }
+    sqlx::query(
+        "INSERT INTO gh_prompt_variables (name, value) VALUES ($1, $2) \
+         ON CONFLICT (name) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()",
+    )
+    .bind(name)
+    .bind(&req.value)
+    .execute(&state.db)
+    .await
+    .map_err(|e| db_error("variable update failed", e))?;
+    Ok(Json(json!({ "name": name, "value": req.value })))
+}
+
+/// DELETE /api/prompt-variables/{name}
+pub async fn delete_variable(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> TemplateResult {
+    let name = name.trim();
+    let result = sqlx::query("DELETE FROM gh_prompt_variables WHERE name = $1")
+        .bind(name)
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("variable delete failed", e))?;
+    if result.rows_affected() == 0 {
+        return Err(api_error(
+            StatusCode::NOT_FOUND,
+            format!("variable '{}' not found", name),
+        ));
+    }
+    Ok(Json(json!({ "deleted": name })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_substitutes_and_reports_missing() {
+        let vars: HashMap<String, String> = [("lang".to_string(), "Rust".to_string())].into();
+        let (out, missing) = render("Write {{ lang }} code for {{project}}. {{project}}!", &vars);
+        assert_eq!(out, "Write Rust code for {{project}}. {{project}}!");
+        assert_eq!(missing, vec!["project"]);
+    }
+
+    #[test]
+    fn custom_variable_names_are_validated() {
+        assert!(VARIABLE_NAME_RE.is_match("team.lead"));
+        assert!(!VARIABLE_NAME_RE.is_match("9lives"));
+        assert!(!VARIABLE_NAME_RE.is_match("has space"));
+        assert!(BUILTIN_VARIABLES.contains(&"prompt"));
+    }
+
+    #[test]
+    fn variables_are_listed_once_in_order() {
+        assert_eq!(
+            variables("{{b}} {{a}} {{b}} {{ not valid }} {x}"),
+            vec!["b", "a"]
+        );
+    }
+}
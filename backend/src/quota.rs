@@ -0,0 +1,386 @@
+// ---------------------------------------------------------------------------
+// quota.rs — Quota guard for Gemini calls
+//
+// Optional limits per provider, all from the environment (`GEMINI_` for the
+// Generative Language API, `VERTEX_` when Vertex AI is configured):
+//   {PROVIDER}_QUOTA_REQUESTS_PER_MIN — in-process sliding window
+//   {PROVIDER}_QUOTA_TOKENS_PER_DAY   — from gh_agent_usage, last 24 hours
+//   {PROVIDER}_QUOTA_USD_PER_MONTH    — estimated cost (usage.rs), last 30 days
+// A call is checked against the limits of the provider it goes to: the
+// active one, except WebSocket chat and A2A rounds, which always use the
+// Generative Language API (see vertex.rs). The ledger has no provider
+// column, so token and cost totals cover every recorded call.
+// `check` runs before every streamed chat turn, A2A (swarm) round,
+// `/api/execute` request and app-local `generateContent` call; token
+// counting and model metadata are not gated. Past 80% of a limit a
+// `quota-warning` is logged and broadcast to the WebSocket clients once per
+// provider and UTC day; at 100% requests are rejected until usage falls back
+// under the limit. Ledger totals are cached for 30 seconds.
+// ---------------------------------------------------------------------------
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::Json;
+use axum::extract::State;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use crate::state::AppState;
+
+const WARNING_RATIO: f64 = 0.8;
+const LEDGER_CACHE_TTL: Duration = Duration::from_secs(30);
+const MINUTE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Gemini,
+    Vertex,
+}
+
+impl Provider {
+    /// The provider Gemini calls currently go to.
+    pub fn current() -> Self {
+        if crate::vertex::config().is_some() {
+            Self::Vertex
+        } else {
+            Self::Gemini
+        }
+    }
+
+    fn env_prefix(self) -> &'static str {
+        match self {
+            Self::Gemini => "GEMINI",
+            Self::Vertex => "VERTEX",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QuotaLimits {
+    pub requests_per_min: Option<u64>,
+    pub tokens_per_day: Option<u64>,
+    pub usd_per_month: Option<f64>,
+}
+
+impl QuotaLimits {
+    /// Limits of the active provider.
+    pub fn from_env() -> Self {
+        Self::for_provider(Provider::current())
+    }
+
+    pub fn for_provider(provider: Provider) -> Self {
+        let var = |limit: &str| {
+            std::env::var(format!("{}_QUOTA_{}", provider.env_prefix(), limit))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+        Self {
+            requests_per_min: var("REQUESTS_PER_MIN").and_then(|v| v.trim().parse().ok()),
+            tokens_per_day: var("TOKENS_PER_DAY").and_then(|v| v.trim().parse().ok()),
+            usd_per_month: var("USD_PER_MONTH").and_then(|v| v.trim().parse().ok()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.requests_per_min.is_none()
+            && self.tokens_per_day.is_none()
+            && self.usd_per_month.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaUsage {
+    pub name: &'static str,
+    pub used: f64,
+    pub limit: f64,
+    pub ratio: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaLevel {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+fn usage(name: &'static str, used: f64, limit: Option<f64>) -> Option<QuotaUsage> {
+    let limit = limit?;
+    let ratio = if limit > 0.0 {
+        used / limit
+    } else {
+        f64::INFINITY
+    };
+    Some(QuotaUsage {
+        name,
+        used,
+        limit,
+        ratio,
+    })
+}
+
+/// Worst level across the tracked quotas.
+pub fn level(usages: &[QuotaUsage]) -> QuotaLevel {
+    let worst = usages.iter().map(|u| u.ratio).fold(0.0_f64, f64::max);
+    if worst >= 1.0 {
+        QuotaLevel::Exceeded
+    } else if worst >= WARNING_RATIO {
+        QuotaLevel::Warning
+    } else {
+        QuotaLevel::Ok
+    }
+}
+
+struct LedgerTotals {
+    tokens_today: f64,
+    usd_month: f64,
+    fetched_at: Instant,
+}
+
+/// Request timestamps of the last minute, per provider.
+static RECENT_REQUESTS: LazyLock<Mutex<HashMap<Provider, VecDeque<Instant>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static LEDGER: LazyLock<Mutex<Option<LedgerTotals>>> = LazyLock::new(|| Mutex::new(None));
+/// UTC day (days since the epoch) of the last warning, per provider. Kept
+/// apart from the ledger cache so a refresh does not re-arm the warning.
+static LAST_WARNING_DAY: LazyLock<std::sync::Mutex<HashMap<Provider, u64>>> =
+    LazyLock::new(Default::default);
+
+async fn ledger_totals(state: &AppState) -> (f64, f64) {
+    let mut cache = LEDGER.lock().await;
+    if let Some(t) = cache.as_ref()
+        && t.fetched_at.elapsed() < LEDGER_CACHE_TTL
+    {
+        return (t.tokens_today, t.usd_month);
+    }
+
+    let tokens_today: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(total_tokens), 0)::bigint FROM gh_agent_usage \
+         WHERE created_at >= NOW() - INTERVAL '1 day'",
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or_else(|e| {
+        tracing::warn!("quota: token total query failed: {}", e);
+        0
+    });
+    let per_model: Vec<(String, i64, i64)> = sqlx::query_as(
+        "SELECT model, COALESCE(SUM(input_tokens), 0)::bigint, COALESCE(SUM(output_tokens), 0)::bigint \
+         FROM gh_agent_usage WHERE created_at >= NOW() - INTERVAL '30 days' GROUP BY model",
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+    let usd_month: f64 = per_model
+        .iter()
+        .map(|(model, input, output)| crate::usage::estimate_cost_usd(model, *input, *output))
+        .sum();
+
+    *cache = Some(LedgerTotals {
+        tokens_today: tokens_today as f64,
+        usd_month,
+        fetched_at: Instant::now(),
+    });
+    (tokens_today as f64, usd_month)
+}
+
+fn utc_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default()
+}
+
+/// Claim the warning for `provider` on `day`; false when it was already sent.
+fn claim_warning(warned: &mut HashMap<Provider, u64>, provider: Provider, day: u64) -> bool {
+    if warned.get(&provider) == Some(&day) {
+        return false;
+    }
+    warned.insert(provider, day);
+    true
+}
+
+fn prune(window: &mut VecDeque<Instant>) {
+    while window.front().is_some_and(|t| t.elapsed() > MINUTE) {
+        window.pop_front();
+    }
+}
+
+fn usages(limits: &QuotaLimits, recent: f64, tokens_today: f64, usd_month: f64) -> Vec<QuotaUsage> {
+    [
+        usage(
+            "requests_per_min",
+            recent,
+            limits.requests_per_min.map(|v| v as f64),
+        ),
+        usage(
+            "tokens_per_day",
+            tokens_today,
+            limits.tokens_per_day.map(|v| v as f64),
+        ),
+        usage("usd_per_month", usd_month, limits.usd_per_month),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Current usage against the active provider's limits (empty when none are set).
+pub async fn status(state: &AppState) -> Vec<QuotaUsage> {
+    let provider = Provider::current();
+    let limits = QuotaLimits::for_provider(provider);
+    if limits.is_empty() {
+        return Vec::new();
+    }
+    let (tokens_today, usd_month) = ledger_totals(state).await;
+    let recent = {
+        let mut windows = RECENT_REQUESTS.lock().await;
+        let window = windows.entry(provider).or_default();
+        prune(window);
+        window.len() as f64
+    };
+    usages(&limits, recent, tokens_today, usd_month)
+}
+
+/// Broadcast a `quota-warning` to the WebSocket clients.
+fn emit_warning(state: &AppState, provider: Provider, usages: &[QuotaUsage]) {
+    let _ = state.swarm_tx.send(jaskier_core::models::AgentMessage {
+        agent_id: "quota-warning".to_string(),
+        content: json!({
+            "type": "quota-warning",
+            "provider": provider,
+            "quotas": usages,
+        })
+        .to_string(),
+        is_final: false,
+    });
+}
+
+/// Gate one outbound request: counts it and rejects it when a limit is reached.
+/// The request window is checked and updated under one lock, so concurrent
+/// callers cannot all slip under the per-minute limit. Callers run it after
+/// their other gates (shutdown, offline mode), and `refund` the count when a
+/// gate after it refuses the request.
+pub async fn check(state: &AppState) -> Result<(), String> {
+    check_provider(state, Provider::current()).await
+}
+
+/// `check` for a call that goes to `provider` regardless of the active one.
+pub async fn check_provider(state: &AppState, provider: Provider) -> Result<(), String> {
+    let limits = QuotaLimits::for_provider(provider);
+    if limits.is_empty() {
+        return Ok(());
+    }
+    let (tokens_today, usd_month) = ledger_totals(state).await;
+
+    let mut windows = RECENT_REQUESTS.lock().await;
+    let window = windows.entry(provider).or_default();
+    prune(window);
+    let usages = usages(&limits, window.len() as f64, tokens_today, usd_month);
+    match level(&usages) {
+        QuotaLevel::Exceeded => {
+            let exceeded: Vec<String> = usages
+                .iter()
+                .filter(|u| u.ratio >= 1.0)
+                .map(|u| format!("{} ({:.2}/{:.2})", u.name, u.used, u.limit))
+                .collect();
+            return Err(format!("quota exceeded: {}", exceeded.join(", ")));
+        }
+        QuotaLevel::Warning => {
+            let first = claim_warning(
+                &mut LAST_WARNING_DAY.lock().unwrap_or_else(|e| e.into_inner()),
+                provider,
+                utc_day(),
+            );
+            if first {
+                tracing::warn!(
+                    ?provider,
+                    ?usages,
+                    "quota-warning: over 80% of a Gemini quota"
+                );
+                emit_warning(state, provider, &usages);
+            }
+        }
+        QuotaLevel::Ok => {}
+    }
+    window.push_back(Instant::now());
+    Ok(())
+}
+
+/// Take back the request `check_provider` just counted for `provider` when a
+/// later gate (such as the circuit breaker) refused it, so refusals don't use
+/// up the window.
+pub async fn refund(provider: Provider) {
+    if QuotaLimits::for_provider(provider).is_empty() {
+        return;
+    }
+    if let Some(window) = RECENT_REQUESTS.lock().await.get_mut(&provider) {
+        window.pop_back();
+    }
+}
+
+/// GET /api/usage/quota — Configured limits and current consumption.
+pub async fn quota_status(State(state): State<AppState>) -> Json<Value> {
+    let usages = status(&state).await;
+    Json(json!({
+        "provider": Provider::current(),
+        "level": level(&usages),
+        "quotas": usages,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_uses_worst_ratio() {
+        let ok = usage("tokens_per_day", 100.0, Some(1000.0)).expect("limit set");
+        let warn = usage("usd_per_month", 8.5, Some(10.0)).expect("limit set");
+        let over = usage("requests_per_min", 60.0, Some(60.0)).expect("limit set");
+        assert_eq!(level(&[]), QuotaLevel::Ok);
+        assert_eq!(level(std::slice::from_ref(&ok)), QuotaLevel::Ok);
+        assert_eq!(level(&[ok.clone(), warn.clone()]), QuotaLevel::Warning);
+        assert_eq!(level(&[ok, warn, over]), QuotaLevel::Exceeded);
+    }
+
+    #[test]
+    fn unset_limits_are_not_tracked() {
+        assert!(usage("tokens_per_day", 5.0, None).is_none());
+        assert!(QuotaLimits::default().is_empty());
+    }
+
+    #[test]
+    fn usages_only_cover_configured_limits() {
+        let limits = QuotaLimits {
+            requests_per_min: Some(10),
+            tokens_per_day: None,
+            usd_per_month: Some(5.0),
+        };
+        let names: Vec<&str> = usages(&limits, 9.0, 1e9, 1.0)
+            .iter()
+            .map(|u| u.name)
+            .collect();
+        assert_eq!(names, ["requests_per_min", "usd_per_month"]);
+        assert_eq!(Provider::Vertex.env_prefix(), "VERTEX");
+    }
+
+    #[test]
+    fn warning_is_sent_once_per_provider_and_day() {
+        let mut warned = HashMap::new();
+        assert!(claim_warning(&mut warned, Provider::Gemini, 20_000));
+        assert!(!claim_warning(&mut warned, Provider::Gemini, 20_000));
+        assert!(claim_warning(&mut warned, Provider::Vertex, 20_000));
+        assert!(claim_warning(&mut warned, Provider::Gemini, 20_001));
+    }
+
+    #[test]
+    fn zero_limit_blocks_everything() {
+        let zero = usage("usd_per_month", 0.0, Some(0.0)).expect("limit set");
+        assert_eq!(level(&[zero]), QuotaLevel::Exceeded);
+    }
+}
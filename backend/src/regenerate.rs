@@ -0,0 +1,544 @@
+// ---------------------------------------------------------------------------
+// regenerate.rs — "Try again" for assistant replies
+//
+// Re-runs the conversation up to the user turn that produced an assistant
+// message, sampling N alternatives with spread temperatures and distinct
+// seeds. Each run uses the original reply's model and the same system
+// instruction and tools a chat turn of that session gets (built through
+// `prepare_execution_ctx`), except that only read-tier tools are offered:
+// N parallel alternatives must not repeat writes, commands or commits, so
+// tool calls are limited to lookups and executed in a bounded loop.
+// Alternatives are stored in gh_message_alternatives next to the original
+// (kept as index 0); selecting one rewrites the message in chat history so
+// later turns and exports see the chosen version. The `/stream` variant
+// emits every alternative as its own SSE event as soon as it finishes.
+// ---------------------------------------------------------------------------
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{FuturesUnordered, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::handlers::api_error;
+use crate::handlers::streaming::HasGeminiStreamingState;
+use crate::state::AppState;
+
+type RegenResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+const MAX_ALTERNATIVES: usize = 5;
+const MAX_HISTORY_MESSAGES: i64 = 40;
+const DEFAULT_TEMPERATURE: f64 = 1.0;
+const TEMPERATURE_STEP: f64 = 0.15;
+/// Function-calling rounds per alternative before giving up.
+const MAX_TOOL_ROUNDS: usize = 8;
+/// Attempts to claim the next free `idx` when alternatives are stored concurrently.
+const IDX_RETRIES: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateRequest {
+    #[serde(default = "default_alternatives")]
+    pub n_alternatives: usize,
+    pub temperature: Option<f64>,
+}
+
+fn default_alternatives() -> usize {
+    1
+}
+
+fn db_error(context: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    tracing::error!("regenerate: {}: {}", context, e);
+    api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+fn not_found(what: &str) -> (StatusCode, Json<Value>) {
+    api_error(StatusCode::NOT_FOUND, format!("{} not found", what))
+}
+
+/// Temperatures fanned out around `base` so alternatives actually differ.
+fn spread_temperatures(base: f64, n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| {
+            let offset =
+                if i % 2 == 0 { 1.0 } else { -1.0 } * TEMPERATURE_STEP * i.div_ceil(2) as f64;
+            (base + offset).clamp(0.0, 2.0)
+        })
+        .collect()
+}
+
+/// Only tools on the registry's explicit read-only list; MCP tools and
+/// anything unlisted are never run by parallel alternatives.
+fn is_read_only(name: &str) -> bool {
+    crate::tool_registry::READ_TOOLS.contains(&name)
+}
+
+/// Keep only read-tier declarations (`[{ "function_declarations": [...] }]`).
+fn read_only_tools(mut tools: Value) -> Value {
+    if let Some(groups) = tools.as_array_mut() {
+        for group in groups {
+            if let Some(decls) = group
+                .get_mut("function_declarations")
+                .and_then(|d| d.as_array_mut())
+            {
+                decls.retain(|d| d["name"].as_str().is_some_and(is_read_only));
+            }
+        }
+    }
+    tools
+}
+
+/// Convert `(role, content)` history into Gemini `contents`; the last turn must be the user's.
+fn build_contents(history: &[(String, String)]) -> Option<Vec<Value>> {
+    let contents: Vec<Value> = history
+        .iter()
+        .filter(|(_, content)| !content.trim().is_empty())
+        .map(|(role, content)| {
+            let role = if role == "user" { "user" } else { "model" };
+            json!({ "role": role, "parts": [{ "text": content }] })
+        })
+        .collect();
+    contents
+        .last()
+        .is_some_and(|c| c["role"] == "user")
+        .then_some(contents)
+}
+
+/// Everything an alternative run needs, resolved once per request.
+struct Regeneration {
+    message_id: Uuid,
+    agent_id: String,
+    model: String,
+    system_prompt: String,
+    working_directory: String,
+    /// `generationConfig` of a chat turn; temperature and seed are set per run.
+    generation_config: Value,
+    tools: Value,
+    contents: Vec<Value>,
+    base_temperature: f64,
+}
+
+/// Validate the target message and rebuild the context its reply was produced with.
+async fn prepare(
+    state: &AppState,
+    session_id: Uuid,
+    message_id: Uuid,
+    req: &RegenerateRequest,
+) -> Result<Regeneration, (StatusCode, Json<Value>)> {
+    if req.n_alternatives == 0 || req.n_alternatives > MAX_ALTERNATIVES {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("n_alternatives must be 1..={}", MAX_ALTERNATIVES),
+        ));
+    }
+
+    let target: Option<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT role, content, model FROM gh_chat_messages WHERE id = $1 AND session_id = $2",
+    )
+    .bind(message_id)
+    .bind(session_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("message lookup failed", e))?;
+    let (role, original, original_model) = target.ok_or_else(|| not_found("message"))?;
+    if role != "assistant" {
+        return Err(api_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "only assistant messages can be regenerated",
+        ));
+    }
+
+    let mut history: Vec<(String, String)> = sqlx::query_as(
+        "SELECT role, content FROM gh_chat_messages \
+         WHERE session_id = $1 \
+           AND created_at < (SELECT created_at FROM gh_chat_messages WHERE id = $2) \
+         ORDER BY created_at DESC LIMIT $3",
+    )
+    .bind(session_id)
+    .bind(message_id)
+    .bind(MAX_HISTORY_MESSAGES)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("history lookup failed", e))?;
+    history.reverse();
+    let contents = build_contents(&history).ok_or_else(|| {
+        api_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "no user prompt precedes this message",
+        )
+    })?;
+    let prompt = history
+        .iter()
+        .rev()
+        .find(|(role, _)| role == "user")
+        .map(|(_, content)| content.clone())
+        .unwrap_or_default();
+
+    // Keep the original reply as alternative 0 the first time it is regenerated.
+    sqlx::query(
+        "INSERT INTO gh_message_alternatives (message_id, idx, content, model, selected) \
+         VALUES ($1, 0, $2, $3, TRUE) ON CONFLICT (message_id, idx) DO NOTHING",
+    )
+    .bind(message_id)
+    .bind(&original)
+    .bind(&original_model)
+    .execute(&state.db)
+    .await
+    .map_err(|e| db_error("failed to keep original", e))?;
+
+    let session_wd: String =
+        sqlx::query_scalar("SELECT working_directory FROM gh_sessions WHERE id = $1")
+            .bind(session_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| db_error("session lookup failed", e))?
+            .unwrap_or_default();
+    let agent = state.resolve_session_agent(&session_id, &prompt).await;
+    let model_override = original_model.filter(|m| !m.trim().is_empty());
+    // Tool calls below get the agent explicitly; keep the binding local.
+    let ctx = crate::tool_registry::execution_scope(state.prepare_execution_ctx(
+        &prompt,
+        model_override,
+        Some(agent),
+        &session_wd,
+    ))
+    .await;
+
+    let mut generation_config = json!({
+        "temperature": ctx.temperature,
+        "topP": ctx.top_p,
+        "maxOutputTokens": ctx.max_tokens,
+    });
+    if let Some(tc) = state.build_thinking_config(&ctx.model, &ctx.thinking_level) {
+        generation_config["thinkingConfig"] = tc;
+    }
+    let base_temperature = req
+        .temperature
+        .or_else(|| generation_config["temperature"].as_f64())
+        .unwrap_or(DEFAULT_TEMPERATURE);
+    Ok(Regeneration {
+        message_id,
+        tools: read_only_tools(state.build_tools_json().await),
+        agent_id: ctx.agent_id,
+        model: ctx.model,
+        system_prompt: ctx.system_prompt,
+        working_directory: ctx.working_directory,
+        generation_config,
+        contents,
+        base_temperature,
+    })
+}
+
+/// Sample one alternative, executing tool calls until the model answers in text.
+async fn sample(
+    state: &AppState,
+    regen: &Regeneration,
+    temperature: f64,
+    seed: i64,
+) -> Result<String, String> {
+    let mut generation_config = regen.generation_config.clone();
+    generation_config["temperature"] = json!(temperature);
+    generation_config["seed"] = json!(seed);
+    let mut contents = regen.contents.clone();
+
+    for _ in 0..MAX_TOOL_ROUNDS {
+        let body = json!({
+            "systemInstruction": { "parts": [{ "text": regen.system_prompt }] },
+            "contents": contents,
+            "tools": regen.tools,
+            "generationConfig": generation_config,
+        });
+        let response =
+            crate::gemini_api::generate_raw(state, &regen.model, body, "regenerate").await?;
+        let parts: Vec<Value> = response
+            .pointer("/candidates/0/content/parts")
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let calls: Vec<Value> = parts
+            .iter()
+            .filter_map(|p| p.get("functionCall").cloned())
+            .collect();
+        if calls.is_empty() {
+            return crate::gemini_api::response_text(&response)
+                .ok_or_else(|| "Gemini returned no text".to_string());
+        }
+
+        contents.push(json!({ "role": "model", "parts": parts }));
+        let mut responses = Vec::with_capacity(calls.len());
+        for call in calls {
+            let name = call["name"].as_str().unwrap_or_default().to_string();
+            let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+            if !is_read_only(&name) {
+                let error = format!("'{}' is not available while regenerating", name);
+                responses.push(
+                    json!({ "functionResponse": { "name": name, "response": { "error": error } } }),
+                );
+                continue;
+            }
+            let output = match crate::tool_registry::execute_tool(
+                &name,
+                &args,
+                state,
+                &regen.working_directory,
+                Some(&regen.agent_id),
+            )
+            .await
+            {
+                Ok(output) => json!({ "content": output.text }),
+                Err(e) => json!({ "error": e }),
+            };
+            responses.push(json!({ "functionResponse": { "name": name, "response": output } }));
+        }
+        contents.push(json!({ "role": "user", "parts": responses }));
+    }
+    Err(format!(
+        "no final answer after {} tool rounds",
+        MAX_TOOL_ROUNDS
+    ))
+}
+
+/// Store an alternative under the next free `idx`. Concurrent writers can
+/// claim the same index, so a conflict is retried with a fresh MAX(idx).
+async fn store_alternative(
+    state: &AppState,
+    regen: &Regeneration,
+    content: &str,
+    temperature: f64,
+    seed: i64,
+) -> Result<Value, (StatusCode, Json<Value>)> {
+    for _ in 0..IDX_RETRIES {
+        let row: Option<Value> = sqlx::query_scalar(
+            "INSERT INTO gh_message_alternatives (message_id, idx, content, model, temperature, seed) \
+             VALUES ($1, (SELECT COALESCE(MAX(idx), 0) + 1 FROM gh_message_alternatives WHERE message_id = $1), \
+                     $2, $3, $4, $5) \
+             ON CONFLICT (message_id, idx) DO NOTHING \
+             RETURNING to_jsonb(gh_message_alternatives)",
+        )
+        .bind(regen.message_id)
+        .bind(content)
+        .bind(&regen.model)
+        .bind(temperature)
+        .bind(seed)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| db_error("failed to store alternative", e))?;
+        if let Some(row) = row {
+            return Ok(row);
+        }
+    }
+    Err(api_error(
+        StatusCode::CONFLICT,
+        "could not allocate an alternative index, try again",
+    ))
+}
+
+/// Sample and store one alternative; errors are reported as text.
+async fn run_alternative(
+    state: &AppState,
+    regen: &Regeneration,
+    temperature: f64,
+) -> Result<Value, String> {
+    let seed = i64::from(rand::random::<i32>());
+    let content = sample(state, regen, temperature, seed).await?;
+    store_alternative(state, regen, &content, temperature, seed)
+        .await
+        .map_err(|(_, Json(body))| body["error"].as_str().unwrap_or("store failed").to_string())
+}
+
+/// POST /api/sessions/{id}/messages/{message_id}/regenerate — Sample alternatives for a reply.
+pub async fn regenerate_message(
+    State(state): State<AppState>,
+    Path((session_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<RegenerateRequest>,
+) -> RegenResult {
+    let regen = prepare(&state, session_id, message_id, &req).await?;
+    let temperatures = spread_temperatures(regen.base_temperature, req.n_alternatives);
+    let runs = temperatures
+        .iter()
+        .map(|&temperature| run_alternative(&state, &regen, temperature));
+    let results = futures_util::future::join_all(runs).await;
+
+    let mut alternatives = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(row) => alternatives.push(row),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if alternatives.is_empty() {
+        return Err((
+            StatusCode::BAD_GATEWAY,
+            Json(json!({ "error": "all generations failed", "details": errors })),
+        ));
+    }
+    Ok(Json(json!({
+        "message_id": message_id,
+        "alternatives": alternatives,
+        "errors": errors,
+    })))
+}
+
+/// POST /api/sessions/{id}/messages/{message_id}/regenerate/stream — Same as
+/// `regenerate_message`, but every alternative is sent as its own SSE event
+/// (`alternative` or `failed`, tagged with its `stream` index) as soon as it
+/// is ready, followed by `done`.
+pub async fn regenerate_message_stream(
+    State(state): State<AppState>,
+    Path((session_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<RegenerateRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<Value>)> {
+    let regen = Arc::new(prepare(&state, session_id, message_id, &req).await?);
+    let temperatures = spread_temperatures(regen.base_temperature, req.n_alternatives);
+
+    let stream = async_stream::stream! {
+        for (index, temperature) in temperatures.iter().enumerate() {
+            yield Ok(Event::default()
+                .event("started")
+                .json_data(json!({ "stream": index, "temperature": temperature }))
+                .unwrap_or_default());
+        }
+        let mut runs: FuturesUnordered<_> = temperatures
+            .iter()
+            .enumerate()
+            .map(|(index, &temperature)| {
+                let state = state.clone();
+                let regen = regen.clone();
+                async move { (index, run_alternative(&state, &regen, temperature).await) }
+            })
+            .collect();
+        while let Some((index, result)) = runs.next().await {
+            let event = match result {
+                Ok(row) => Event::default()
+                    .event("alternative")
+                    .json_data(json!({ "stream": index, "alternative": row })),
+                Err(e) => Event::default()
+                    .event("failed")
+                    .json_data(json!({ "stream": index, "error": e })),
+            };
+            yield Ok(event.unwrap_or_default());
+        }
+        yield Ok(Event::default()
+            .event("done")
+            .json_data(json!({ "message_id": regen.message_id }))
+            .unwrap_or_default());
+    };
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// GET /api/messages/{id}/alternatives — Original and regenerated versions of a reply.
+pub async fn list_alternatives(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+) -> RegenResult {
+    let rows: Vec<Value> = sqlx::query_scalar(
+        "SELECT to_jsonb(a) FROM gh_message_alternatives a WHERE message_id = $1 ORDER BY idx",
+    )
+    .bind(message_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("list failed", e))?;
+    Ok(Json(
+        json!({ "message_id": message_id, "alternatives": rows }),
+    ))
+}
+
+/// POST /api/messages/{id}/alternatives/{alternative_id}/select — Make an alternative the reply.
+pub async fn select_alternative(
+    State(state): State<AppState>,
+    Path((message_id, alternative_id)): Path<(Uuid, Uuid)>,
+) -> RegenResult {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| db_error("begin failed", e))?;
+    let chosen: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT content, model FROM gh_message_alternatives WHERE id = $1 AND message_id = $2",
+    )
+    .bind(alternative_id)
+    .bind(message_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| db_error("alternative lookup failed", e))?;
+    let (content, model) = chosen.ok_or_else(|| not_found("alternative"))?;
+
+    sqlx::query("UPDATE gh_message_alternatives SET selected = (id = $2) WHERE message_id = $1")
+        .bind(message_id)
+        .bind(alternative_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| db_error("select failed", e))?;
+    sqlx::query(
+        "UPDATE gh_chat_messages SET content = $2, model = COALESCE($3, model) WHERE id = $1",
+    )
+    .bind(message_id)
+    .bind(&content)
+    .bind(&model)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| db_error("message update failed", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| db_error("commit failed", e))?;
+
+    Ok(Json(json!({
+        "message_id": message_id,
+        "selected": alternative_id,
+        "content": content,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_read_tools_are_offered() {
+        let tools = json!([{ "function_declarations": [
+            { "name": "read_file" },
+            { "name": "write_file" },
+            { "name": "execute_command" },
+            { "name": "git_commit_with_message" },
+            { "name": crate::memory_recall::TOOL_NAME },
+            { "name": "mcp_fs_write" },
+            { "name": "upload_anywhere" },
+        ] }]);
+        let names: Vec<String> = read_only_tools(tools)[0]["function_declarations"]
+            .as_array()
+            .expect("declarations")
+            .iter()
+            .filter_map(|d| d["name"].as_str().map(String::from))
+            .collect();
+        assert_eq!(names, vec!["read_file"]);
+    }
+
+    #[test]
+    fn temperatures_fan_out_and_clamp() {
+        let temps = spread_temperatures(1.0, 4);
+        assert_eq!(temps.len(), 4);
+        assert!((temps[0] - 1.0).abs() < 1e-9);
+        assert!(temps[1] < 1.0 && temps[2] > 1.0);
+        assert!(spread_temperatures(2.0, 3).iter().all(|t| *t <= 2.0));
+    }
+
+    #[test]
+    fn contents_require_trailing_user_turn() {
+        let history = vec![
+            ("user".to_string(), "hi".to_string()),
+            ("assistant".to_string(), "hello".to_string()),
+            ("user".to_string(), "again".to_string()),
+        ];
+        let contents = build_contents(&history).expect("ends with user");
+        assert_eq!(contents[1]["role"], "model");
+        assert!(build_contents(&history[..2]).is_none());
+        assert!(build_contents(&[]).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+// ---------------------------------------------------------------------------
+// shutdown.rs — Orderly teardown after SIGINT/SIGTERM
+//
+// `axum::serve(...).with_graceful_shutdown` waits for every open connection,
+// and long-lived WebSocket streams could hold the process forever. `serve`
+// here stops new Gemini rounds (`ensure_running` fails in the circuit
+// check, which ends streaming generations and A2A tasks at their next call),
+// cancels in-flight A2A (swarm) tasks and gives connections DRAIN_TIMEOUT to
+// finish. Teardown then waits (bounded) for streams and tasks to wind down,
+// records the shutdown and its reason in the audit log and closes the
+// database pools so pending writes are flushed before exit.
+// ---------------------------------------------------------------------------
+
+use std::future::{Future, IntoFuture};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::state::AppState;
+
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(15);
+const TEARDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Fails once shutdown has started, so no new Gemini round is sent.
+pub fn ensure_running() -> Result<(), String> {
+    if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        Err("server is shutting down".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Resolves with the name of the first termination signal received.
+pub async fn signal() -> &'static str {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("shutdown: cannot listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("shutdown: cannot listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => "SIGINT",
+        () = terminate => "SIGTERM",
+    }
+}
+
+/// Run `server` until `signal` fires, then drain (bounded) and tear down.
+/// `server` must be built with `.with_graceful_shutdown(token.cancelled_owned())`.
+pub async fn serve<S>(
+    state: &AppState,
+    server: S,
+    token: CancellationToken,
+    signal: impl Future<Output = &'static str>,
+) -> std::io::Result<()>
+where
+    S: IntoFuture<Output = std::io::Result<()>>,
+{
+    // Captured before any connection is accepted: every permit is free.
+    let ws_permits = state.ws_semaphore.available_permits();
+    let server = server.into_future();
+    tokio::pin!(server);
+
+    let reason = tokio::select! {
+        result = &mut server => {
+            let reason = match &result {
+                Ok(()) => "server stopped".to_string(),
+                Err(e) => format!("server error: {}", e),
+            };
+            stop_work(state).await;
+            teardown(state, &reason, ws_permits).await;
+            return result;
+        }
+        signal = signal => {
+            tracing::info!("shutdown: {} received, draining connections (max {}s)", signal, DRAIN_TIMEOUT.as_secs());
+            token.cancel();
+            signal
+        }
+    };
+
+    // Stop generations and swarm work first so they do not keep connections busy while draining.
+    stop_work(state).await;
+    let result = match tokio::time::timeout(DRAIN_TIMEOUT, &mut server).await {
+        Ok(result) => result,
+        Err(_) => {
+            tracing::warn!(
+                "shutdown: connections still open after {}s, closing them",
+                DRAIN_TIMEOUT.as_secs()
+            );
+            Ok(())
+        }
+    };
+    teardown(state, reason, ws_permits).await;
+    result
+}
+
+/// Refuse further Gemini rounds and cancel running A2A tasks.
+async fn stop_work(state: &AppState) {
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    let tokens = state.base.a2a_cancel_tokens.read().await;
+    if !tokens.is_empty() {
+        tracing::info!("shutdown: cancelling {} running A2A task(s)", tokens.len());
+    }
+    for token in tokens.values() {
+        token.cancel();
+    }
+}
+
+/// Wait until every streaming connection has released its permit and every
+/// A2A task has deregistered its cancellation token.
+async fn wait_for_work(state: &AppState, ws_permits: usize) {
+    let streams = async {
+        let permits = u32::try_from(ws_permits).unwrap_or(u32::MAX);
+        if let Ok(all) = state.ws_semaphore.acquire_many(permits).await {
+            all.forget();
+        }
+        state.ws_semaphore.close();
+    };
+    let tasks = async {
+        while !state.base.a2a_cancel_tokens.read().await.is_empty() {
+            tokio::time::sleep(TASK_POLL_INTERVAL).await;
+        }
+    };
+    tokio::join!(streams, tasks);
+}
+
+async fn teardown(state: &AppState, reason: &str, ws_permits: usize) {
+    if tokio::time::timeout(TEARDOWN_TIMEOUT, wait_for_work(state, ws_permits))
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "shutdown: streams or A2A tasks still running after {}s",
+            TEARDOWN_TIMEOUT.as_secs()
+        );
+    }
+    let work = async {
+        crate::audit::log_audit(
+            &state.db,
+            "shutdown",
+            serde_json::json!({ "reason": reason }),
+            None,
+        )
+        .await;
+        state.db.close().await;
+    };
+    if tokio::time::timeout(TEARDOWN_TIMEOUT, work).await.is_err() {
+        tracing::warn!(
+            "shutdown: teardown did not finish within {}s",
+            TEARDOWN_TIMEOUT.as_secs()
+        );
+    } else {
+        tracing::info!("shutdown: complete ({})", reason);
+    }
+}
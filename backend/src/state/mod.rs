@@ -34,6 +34,12 @@ pub struct AppState {
     pub base: BaseHydraState,
     /// Shared auth state for jaskier-auth integration (B13 Unified Auth).
     pub auth: Arc<jaskier_auth::AuthState>,
+    /// Cached per-workspace project briefs (see `project_brief.rs`).
+    pub project_briefs: crate::project_brief::ProjectBriefCache,
+    /// Cached per-workspace symbol indexes (see `code_index.rs`).
+    pub code_indexes: crate::code_index::CodeIndexCache,
+    /// Tool enable/approval settings (see `tool_registry.rs`), loaded lazily.
+    pub tool_settings: crate::tool_registry::ToolSettingsCache,
 }
 
 impl Deref for AppState {
@@ -70,7 +76,13 @@ impl AppState {
         let auth_config = jaskier_auth::AuthConfig::from_env();
         let auth = jaskier_auth::AuthState::new(auth_db, auth_config);
 
-        Self { base, auth }
+        Self {
+            base,
+            auth,
+            project_briefs: Arc::default(),
+            code_indexes: Arc::default(),
+            tool_settings: Arc::default(),
+        }
     }
 
     pub fn is_ready(&self) -> bool {
@@ -37,8 +37,22 @@ impl jaskier_core::context::HasExecutionContext for AppState {
 
     /// Resolves the Google API key from environment variables. B13: credentials
     /// are injected by Vault at process startup via `GOOGLE_API_KEY` /
-    /// `GEMINI_API_KEY` env vars.
+    /// `GEMINI_API_KEY` env vars. With Vertex AI configured, a service-account
+    /// access token is returned instead (sent as a bearer token). If minting
+    /// it fails the credential is left empty so the Vertex request fails with
+    /// an auth error — the API key is never sent to the Vertex endpoint.
+    /// WebSocket and A2A contexts swap it back for the API key, since their
+    /// URLs are not routed to Vertex (see vertex.rs).
     async fn resolve_api_credential(&self) -> (String, bool) {
+        if let Some(cfg) = crate::vertex::config() {
+            return match crate::vertex::access_token(self, &cfg).await {
+                Ok(token) => (token, true),
+                Err(e) => {
+                    tracing::error!("vertex: failed to obtain access token: {}", e);
+                    (String::new(), true)
+                }
+            };
+        }
         // B13: Credentials from env vars (Vault sets these)
         let key = std::env::var("GOOGLE_API_KEY")
             .or_else(|_| std::env::var("GEMINI_API_KEY"))
@@ -72,15 +86,15 @@ impl jaskier_core::handlers::execute::HasExecuteState for AppState {
     }
 
     /// Builds a validated HTTPS URL for the Gemini `generateContent` endpoint.
-    /// Returns an error if the model name produces an invalid or non-HTTPS URL.
+    /// Fails while offline mode is on.
+    /// Returns an error if the model name produces an invalid or non-HTTPS URL
+    /// (plain HTTP is tolerated for loopback `GEMINI_API_BASE_URL` overrides).
     fn build_api_url(&self, model: &str) -> Result<reqwest::Url, String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-            model
-        );
+        crate::offline::ensure_online().map_err(|e| e.to_string())?;
+        let url = crate::gemini_api::model_method_url(model, "generateContent");
         reqwest::Url::parse(&url)
             .ok()
-            .filter(|u| u.scheme() == "https")
+            .filter(crate::gemini_api::is_allowed_url)
             .ok_or_else(|| "API credentials require HTTPS".to_string())
     }
 
@@ -96,11 +110,13 @@ impl jaskier_core::handlers::execute::HasExecuteState for AppState {
         if let Some(tc) = crate::prompt::build_thinking_config(&ctx.model, &ctx.thinking_level) {
             gen_config["thinkingConfig"] = tc;
         }
-        serde_json::json!({
+        let mut body = serde_json::json!({
             "systemInstruction": { "parts": [{ "text": ctx.system_prompt }] },
             "contents": [{ "parts": [{ "text": ctx.final_user_prompt }] }],
             "generationConfig": gen_config
-        })
+        });
+        crate::gemini_api::apply_safety_settings(&mut body);
+        body
     }
 
     /// Extracts the text content from the first Gemini response candidate.
@@ -139,15 +155,26 @@ impl jaskier_core::handlers::execute::HasExecuteState for AppState {
         if let Some(tc) = crate::prompt::build_thinking_config(&ctx.model, &ctx.thinking_level) {
             gen_config["thinkingConfig"] = tc;
         }
-        Some(serde_json::json!({
+        let mut body = serde_json::json!({
             "systemInstruction": { "parts": [{ "text": format!("{}\n\nIMPORTANT: You are running in text-only mode. Do NOT attempt to call any tools or functions. Answer the user's question directly using your knowledge.", ctx.system_prompt) }] },
             "contents": [{ "parts": [{ "text": ctx.final_user_prompt }] }],
             "generationConfig": gen_config
-        }))
+        });
+        crate::gemini_api::apply_safety_settings(&mut body);
+        Some(body)
     }
 
+    /// Filtered responses are reported as `gemini-blocked: <reason>` so the
+    /// UI can tell them apart from empty or failed generations.
     fn format_diagnostic(&self, j: &serde_json::Value) -> String {
-        jaskier_core::handlers::gemini_diagnose(j)
+        match crate::gemini_api::block_reason(j) {
+            Some(reason) => format!(
+                "gemini-blocked: {} ({})",
+                reason,
+                jaskier_core::handlers::gemini_diagnose(j)
+            ),
+            None => jaskier_core::handlers::gemini_diagnose(j),
+        }
     }
 
     async fn execute_tool_by_name(
@@ -156,9 +183,15 @@ impl jaskier_core::handlers::execute::HasExecuteState for AppState {
         args: &serde_json::Value,
         working_directory: &str,
     ) -> Result<String, String> {
-        crate::tools::execute_tool(name, args, self, working_directory)
-            .await
-            .map(|o| o.text)
+        crate::tool_registry::execute_tool(
+            name,
+            args,
+            self,
+            working_directory,
+            crate::tool_registry::execution_agent().as_deref(),
+        )
+        .await
+        .map(|o| o.text)
     }
 }
 
@@ -227,7 +260,17 @@ impl jaskier_core::mcp::server::HasMcpServerState for AppState {
         args: &serde_json::Value,
         working_directory: &str,
     ) -> Result<(String, Option<serde_json::Value>), String> {
-        match crate::tools::execute_tool(name, args, self, working_directory).await {
+        // MCP clients act outside any agent execution; what they remember
+        // goes to the default agent.
+        match crate::tool_registry::execute_tool(
+            name,
+            args,
+            self,
+            working_directory,
+            Some(crate::memory_summary::DEFAULT_AGENT),
+        )
+        .await
+        {
             Ok(output) => {
                 let inline = output.inline_data.map(|d| {
                     serde_json::json!({
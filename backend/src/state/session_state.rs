@@ -88,7 +88,15 @@ impl jaskier_ai_modules::a2a::HasA2aState for AppState {
     }
 
     async fn circuit_check(&self) -> Result<(), String> {
-        self.base.gemini_circuit.check().await
+        crate::shutdown::ensure_running()?;
+        crate::offline::ensure_online().map_err(|e| e.to_string())?;
+        crate::vertex::ensure_api_key_route()?;
+        crate::quota::check_provider(self, crate::quota::Provider::Gemini).await?;
+        let admitted = self.base.gemini_circuit.check().await;
+        if admitted.is_err() {
+            crate::quota::refund(crate::quota::Provider::Gemini).await;
+        }
+        admitted
     }
 
     async fn circuit_success(&self) {
@@ -108,7 +116,8 @@ impl jaskier_ai_modules::a2a::HasA2aState for AppState {
         agent_override: Option<(String, f64, String)>,
         session_wd: &str,
     ) -> jaskier_ai_modules::a2a::A2aContext {
-        let ctx = crate::context::prepare_execution(
+        let model_overridden = model_override.is_some();
+        let mut ctx = crate::context::prepare_execution(
             self,
             prompt,
             model_override,
@@ -116,6 +125,12 @@ impl jaskier_ai_modules::a2a::HasA2aState for AppState {
             session_wd,
         )
         .await;
+        crate::vertex::use_api_key_credential(&mut ctx);
+        crate::projects::apply_to_context(self, &mut ctx, model_overridden).await;
+        crate::project_brief::inject_into_context(self, &mut ctx).await;
+        crate::memory_recall::inject_into_context(self, &mut ctx).await;
+        crate::prompt_templates::apply_agent_default(self, &mut ctx).await;
+        crate::tool_registry::bind_execution_agent(&ctx.agent_id);
         jaskier_ai_modules::a2a::A2aContext {
             agent_id: ctx.agent_id,
             model: ctx.model,
@@ -143,9 +158,15 @@ impl jaskier_ai_modules::a2a::HasA2aState for AppState {
         args: &serde_json::Value,
         working_dir: &str,
     ) -> Result<String, String> {
-        crate::tools::execute_tool(name, args, self, working_dir)
-            .await
-            .map(|out| out.text)
+        crate::tool_registry::execute_tool(
+            name,
+            args,
+            self,
+            working_dir,
+            crate::tool_registry::execution_agent().as_deref(),
+        )
+        .await
+        .map(|out| out.text)
     }
 
     fn build_a2a_thinking_config(
@@ -0,0 +1,449 @@
+// ---------------------------------------------------------------------------
+// structured.rs — Schema-checked JSON output from Gemini
+//
+// `generate_structured` asks for JSON, repairs the usual model slips (code
+// fences, prose around the object, trailing commas, unquoted keys, single
+// quotes), validates the result against a JSON Schema subset and — up to
+// `max_repairs` times — re-prompts the model with the validation errors.
+// Supported keywords: type, properties, required, additionalProperties
+// (false), items, enum, minItems/maxItems, minLength/maxLength,
+// minimum/maximum.
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+const MAX_REPAIRS_LIMIT: u32 = 3;
+const MAX_REPORTED_ERRORS: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct StructuredRequest {
+    pub prompt: String,
+    pub schema: Value,
+    #[serde(default = "default_max_repairs")]
+    pub max_repairs: u32,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+}
+
+fn default_max_repairs() -> u32 {
+    1
+}
+
+/// Best-effort fix-up of almost-JSON model output. Never fails; the result
+/// may still be invalid JSON.
+pub fn repair_json(raw: &str) -> String {
+    let text = extract_json_span(strip_code_fence(raw));
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    // Whether the previous significant character opens a key position (`{` or `,`).
+    let mut expect_key = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                i = copy_string(&chars, i, &mut out);
+                expect_key = false;
+                continue;
+            }
+            ',' => {
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(',');
+                    expect_key = true;
+                }
+            }
+            '{' => {
+                out.push(c);
+                expect_key = true;
+            }
+            c if expect_key && (c.is_ascii_alphabetic() || c == '_' || c == '$') => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+                let next = chars[i..].iter().find(|c| !c.is_whitespace());
+                if next == Some(&':') {
+                    out.push('"');
+                    out.push_str(&ident);
+                    out.push('"');
+                } else {
+                    out.push_str(&ident);
+                }
+                expect_key = false;
+                continue;
+            }
+            c if c.is_whitespace() => out.push(c),
+            _ => {
+                out.push(c);
+                expect_key = false;
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Copy a quoted string starting at `start` as a double-quoted JSON string;
+/// returns the index after the closing quote.
+fn copy_string(chars: &[char], start: usize, out: &mut String) -> usize {
+    let quote = chars[start];
+    out.push('"');
+    let mut i = start + 1;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() {
+            // `\'` is not a JSON escape.
+            if chars[i + 1] == '\'' {
+                out.push('\'');
+            } else {
+                out.push(c);
+                out.push(chars[i + 1]);
+            }
+            i += 2;
+            continue;
+        }
+        if c == quote {
+            out.push('"');
+            return i + 1;
+        }
+        if c == '"' {
+            out.push_str("\\\"");
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    out.push('"');
+    i
+}
+
+fn strip_code_fence(raw: &str) -> &str {
+    let trimmed = raw.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map(|(_, b)| b).unwrap_or(rest);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Cut prose before the first `{`/`[` and after the last matching closer.
+fn extract_json_span(text: &str) -> &str {
+    let Some(start) = text.find(['{', '[']) else {
+        return text;
+    };
+    let closer = if text[start..].starts_with('{') {
+        '}'
+    } else {
+        ']'
+    };
+    match text.rfind(closer) {
+        Some(end) if end > start => &text[start..=end],
+        _ => &text[start..],
+    }
+}
+
+/// Validate `value` against the supported schema subset; returns error paths.
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(value, schema, "$", &mut errors);
+    errors
+}
+
+fn type_matches(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    if errors.len() >= MAX_REPORTED_ERRORS {
+        return;
+    }
+
+    if let Some(ty) = schema.get("type") {
+        let allowed: Vec<&str> = match ty {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(value, t)) {
+            errors.push(format!("{}: expected {}", path, allowed.join(" or ")));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array())
+        && !options.contains(value)
+    {
+        errors.push(format!(
+            "{}: must be one of {}",
+            path,
+            Value::Array(options.clone())
+        ));
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if !map.contains_key(key) {
+                        errors.push(format!("{}: missing required property '{}'", path, key));
+                    }
+                }
+            }
+            for (key, item) in map {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(sub) => validate_at(item, sub, &format!("{}.{}", path, key), errors),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected property '{}'", path, key));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64())
+                && len < min
+            {
+                errors.push(format!("{}: expected at least {} items", path, min));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64())
+                && len > max
+            {
+                errors.push(format!("{}: expected at most {} items", path, max));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item, item_schema, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64())
+                && len < min
+            {
+                errors.push(format!("{}: shorter than {} characters", path, min));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64())
+                && len > max
+            {
+                errors.push(format!("{}: longer than {} characters", path, max));
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64())
+                && n < min
+            {
+                errors.push(format!("{}: below minimum {}", path, min));
+            }
+            if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64())
+                && n > max
+            {
+                errors.push(format!("{}: above maximum {}", path, max));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parse raw output (plain, then repaired) and validate it.
+fn parse_and_validate(raw: &str, schema: &Value) -> Result<(Value, bool), Vec<String>> {
+    let (value, repaired) = match serde_json::from_str::<Value>(raw.trim()) {
+        Ok(v) => (v, false),
+        Err(_) => match serde_json::from_str::<Value>(&repair_json(raw)) {
+            Ok(v) => (v, true),
+            Err(e) => return Err(vec![format!("$: not valid JSON ({})", e)]),
+        },
+    };
+    let errors = validate(&value, schema);
+    if errors.is_empty() {
+        Ok((value, repaired))
+    } else {
+        Err(errors)
+    }
+}
+
+#[derive(Debug)]
+pub struct StructuredOutput {
+    pub value: Value,
+    /// Number of re-prompts needed after the first answer.
+    pub reprompts: u32,
+    /// Whether local syntax repair was applied to the accepted answer.
+    pub repaired: bool,
+}
+
+/// Generate JSON for `prompt` that conforms to `schema`, re-prompting with
+/// validation errors up to `max_repairs` times.
+pub async fn generate_structured(
+    state: &AppState,
+    prompt: &str,
+    schema: &Value,
+    max_repairs: u32,
+    temperature: f64,
+) -> Result<StructuredOutput, (String, Vec<String>)> {
+    let base_prompt = format!(
+        "{}\n\nRespond with JSON only, conforming to this JSON Schema:\n{}",
+        prompt, schema
+    );
+    let mut current = base_prompt.clone();
+    let mut last_errors = Vec::new();
+
+    for attempt in 0..=max_repairs.min(MAX_REPAIRS_LIMIT) {
+        let raw = crate::gemini_api::generate_content(state, &current, temperature, true)
+            .await
+            .map_err(|e| (e, Vec::new()))?;
+        match parse_and_validate(&raw, schema) {
+            Ok((value, repaired)) => {
+                return Ok(StructuredOutput {
+                    value,
+                    reprompts: attempt,
+                    repaired,
+                });
+            }
+            Err(errors) => {
+                tracing::debug!(
+                    attempt,
+                    errors = errors.len(),
+                    "structured: output rejected"
+                );
+                current = format!(
+                    "{}\n\nYour previous answer was:\n{}\n\nIt failed validation:\n- {}\n\
+                     Return a corrected JSON document only.",
+                    base_prompt,
+                    raw,
+                    errors.join("\n- ")
+                );
+                last_errors = errors;
+            }
+        }
+    }
+    Err((
+        "model output did not match the schema".to_string(),
+        last_errors,
+    ))
+}
+
+/// POST /api/generate/structured — Schema-validated JSON generation.
+pub async fn generate_structured_handler(
+    State(state): State<AppState>,
+    Json(req): Json<StructuredRequest>,
+) -> (StatusCode, Json<Value>) {
+    if req.prompt.trim().is_empty() || !req.schema.is_object() {
+        return api_error(
+            StatusCode::BAD_REQUEST,
+            "prompt and an object schema are required",
+        );
+    }
+    let temperature = req.temperature.unwrap_or(0.2).clamp(0.0, 2.0);
+    match generate_structured(
+        &state,
+        &req.prompt,
+        &req.schema,
+        req.max_repairs,
+        temperature,
+    )
+    .await
+    {
+        Ok(out) => (
+            StatusCode::OK,
+            Json(json!({
+                "value": out.value,
+                "reprompts": out.reprompts,
+                "repaired": out.repaired,
+            })),
+        ),
+        Err((error, validation_errors)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": error, "validation_errors": validation_errors })),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repair_fixes_common_slips() {
+        let raw = "Here you go:\n```json\n{name: 'Geralt', tags: [\"witcher\", ], 'age': 97,}\n```";
+        let fixed: Value = serde_json::from_str(&repair_json(raw)).expect("repaired JSON");
+        assert_eq!(
+            fixed,
+            json!({ "name": "Geralt", "tags": ["witcher"], "age": 97 })
+        );
+    }
+
+    #[test]
+    fn repair_leaves_string_contents_alone() {
+        let raw = r#"{"text": "a, } b: c", "q": 'say "hi"'}"#;
+        let fixed: Value = serde_json::from_str(&repair_json(raw)).expect("repaired JSON");
+        assert_eq!(fixed["text"], "a, } b: c");
+        assert_eq!(fixed["q"], "say \"hi\"");
+    }
+
+    #[test]
+    fn validation_reports_paths() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "items"],
+            "additionalProperties": false,
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "items": { "type": "array", "items": { "type": "integer", "minimum": 0 } }
+            }
+        });
+        assert!(validate(&json!({ "name": "a", "items": [1, 2] }), &schema).is_empty());
+        let errors = validate(&json!({ "items": [1, -2, "x"], "extra": true }), &schema);
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.contains("missing required property 'name'"))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("$.items[1]: below minimum"))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("$.items[2]: expected integer"))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.contains("unexpected property 'extra'"))
+        );
+    }
+
+    #[test]
+    fn enum_and_type_unions() {
+        let schema = json!({ "type": ["string", "null"], "enum": ["a", "b", null] });
+        assert!(validate(&json!(null), &schema).is_empty());
+        assert_eq!(validate(&json!("c"), &schema).len(), 1);
+        assert_eq!(validate(&json!(1), &schema).len(), 1);
+    }
+}
@@ -0,0 +1,400 @@
+// ---------------------------------------------------------------------------
+// support_bundle.rs — Redacted diagnostics bundle for bug reports
+//
+// Collects settings (secrets stripped), host info, the cached model list,
+// recent audit entries and A2A errors, and applied migration versions into
+// `bundle.json`, and zips it together with the recent backend log lines
+// (`backend.log`). `?preview=true` returns the redacted `bundle.json`
+// content and the number of log lines without building the archive, so
+// users can check exactly what will be shared.
+// ---------------------------------------------------------------------------
+
+use std::io::Write;
+use std::sync::LazyLock;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+const RECENT_AUDIT_LIMIT: i64 = 200;
+const RECENT_ERROR_LIMIT: i64 = 50;
+const RECENT_LOG_LIMIT: usize = 1000;
+const REDACTED: &str = "[REDACTED]";
+
+/// Words that mark an object key as secret, matched case-insensitively against
+/// the end of each word of the key (`api_key`, `refreshToken`, `apikey`).
+/// Numbers under a plural form (`max_tokens`, `input_tokens`) are counts and
+/// are kept.
+const SECRET_KEY_PARTS: &[&str] = &["key", "token", "secret", "password", "credential", "cookie"];
+
+/// Inline secrets that may appear inside free-form strings (log details, errors).
+static SECRET_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r"AIza[0-9A-Za-z_\-]{35}",
+        r"sk-[A-Za-z0-9_\-]{20,}",
+        r"(?i)bearer\s+[A-Za-z0-9._\-]+",
+        r"gh[pousr]_[A-Za-z0-9]{20,}",
+        r"eyJ[A-Za-z0-9_\-]+\.[A-Za-z0-9_\-]+\.[A-Za-z0-9_\-]+",
+    ]
+    .iter()
+    .filter_map(|p| Regex::new(p).ok())
+    .collect()
+});
+
+/// Bundle contents as listed by `?preview=true`: (name, file, description).
+const SECTIONS: &[(&str, &str, &str)] = &[
+    ("app", "bundle.json", "App name, version and uptime"),
+    (
+        "host",
+        "bundle.json",
+        "Platform, architecture, CPU and memory usage",
+    ),
+    (
+        "settings",
+        "bundle.json",
+        "gh_settings row with secret values redacted",
+    ),
+    ("models", "bundle.json", "Cached Gemini model list"),
+    (
+        "schema_versions",
+        "bundle.json",
+        "Applied database migrations",
+    ),
+    ("recent_errors", "bundle.json", "Latest 50 failed A2A tasks"),
+    (
+        "crash_reports",
+        "bundle.json",
+        "Summaries of stored crash reports",
+    ),
+    ("audit_log", "bundle.json", "Latest 200 audit log entries"),
+    (
+        "logs",
+        "backend.log",
+        "Latest 1000 backend log lines, secrets redacted",
+    ),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct SupportBundleParams {
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Lowercase words of an object key, split at `_`, `-`, `.` and camelCase humps.
+fn key_words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in key.chars() {
+        let boundary = matches!(c, '_' | '-' | '.' | ' ') || (c.is_uppercase() && prev_lower);
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// `Some(plural)` when `key` names a secret; `plural` is set when the only
+/// match is a plural form such as `max_tokens`.
+fn secret_key(key: &str) -> Option<bool> {
+    let mut plural = None;
+    for word in key_words(key) {
+        for part in SECRET_KEY_PARTS {
+            if word.ends_with(part) {
+                return Some(false);
+            }
+            if word.strip_suffix('s').is_some_and(|w| w.ends_with(part)) {
+                plural = Some(true);
+            }
+        }
+    }
+    plural
+}
+
+/// Recursively strip secret-looking keys and inline credentials from a JSON value.
+/// Everything under a secret-looking key is replaced, whatever its type;
+/// only `null`, booleans (presence flags) and counts under plural keys are kept.
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                let kept = v.is_null() || v.is_boolean();
+                match secret_key(k) {
+                    Some(plural) if !kept && !(plural && v.is_number()) => {
+                        *v = Value::String(REDACTED.to_string());
+                    }
+                    _ => redact(v),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(s) => {
+            for re in SECRET_PATTERNS.iter() {
+                if re.is_match(s) {
+                    *s = re.replace_all(s, REDACTED).into_owned();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Build the full bundle document.
+pub async fn collect(state: &AppState) -> Value {
+    let settings: Value =
+        sqlx::query_scalar::<_, String>("SELECT row_to_json(s)::text FROM gh_settings s LIMIT 1")
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or(Value::Null);
+
+    let audit: Vec<Value> = sqlx::query_as::<_, (String, String, Option<String>)>(
+        "SELECT timestamp::text, action, details::text FROM gh_audit_log \
+         ORDER BY timestamp DESC LIMIT $1",
+    )
+    .bind(RECENT_AUDIT_LIMIT)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(ts, action, details)| {
+        json!({
+            "timestamp": ts,
+            "action": action,
+            "details": details.and_then(|d| serde_json::from_str::<Value>(&d).ok()),
+        })
+    })
+    .collect();
+
+    let errors: Vec<Value> = sqlx::query_as::<_, (String, String, String, String)>(
+        "SELECT id, agent_id, error_message, updated_at::text FROM gh_a2a_tasks \
+         WHERE error_message IS NOT NULL ORDER BY updated_at DESC LIMIT $1",
+    )
+    .bind(RECENT_ERROR_LIMIT)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(id, agent, error, at)| json!({ "task_id": id, "agent_id": agent, "error": error, "at": at }))
+    .collect();
+
+    let migrations: Vec<Value> = sqlx::query_as::<_, (i64, String)>(
+        "SELECT version, description FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(version, description)| json!({ "version": version, "description": description }))
+    .collect();
+
+    let models = {
+        let cache = state.model_cache.read().await;
+        serde_json::to_value(&cache.models).unwrap_or(Value::Null)
+    };
+
+    let host = {
+        let snap = state.system_monitor.read().await;
+        json!({
+            "platform": snap.platform,
+            "arch": std::env::consts::ARCH,
+            "cpu_usage_percent": snap.cpu_usage_percent,
+            "memory_used_mb": snap.memory_used_mb,
+            "memory_total_mb": snap.memory_total_mb,
+        })
+    };
+
+    let mut bundle = json!({
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+        "app": { "name": "GeminiHydra", "version": env!("CARGO_PKG_VERSION") },
+        "uptime_seconds": state.start_time.elapsed().as_secs(),
+        "host": host,
+        "settings": settings,
+        "models": models,
+        "schema_versions": migrations,
+        "recent_errors": errors,
+        "crash_reports": crate::crash_reports::summaries().await,
+        "audit_log": audit,
+    });
+    redact(&mut bundle);
+    bundle
+}
+
+/// Recent backend log lines, one redacted JSON entry per line.
+fn collect_logs(state: &AppState) -> String {
+    state
+        .log_buffer
+        .recent(RECENT_LOG_LIMIT, None, None)
+        .into_iter()
+        .filter_map(|entry| {
+            let mut line = serde_json::to_value(entry).ok()?;
+            redact(&mut line);
+            Some(line.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Preview of a bundle: its sections, the redacted `bundle.json` content and
+/// how many log lines `backend.log` would hold.
+fn manifest(bundle: Value, logs: &str) -> Value {
+    let sections: Vec<Value> = SECTIONS
+        .iter()
+        .map(|(name, file, description)| {
+            json!({ "name": name, "file": file, "description": description })
+        })
+        .collect();
+    json!({
+        "preview": true,
+        "format": "zip",
+        "sections": sections,
+        "bundle": bundle,
+        "log_lines": logs.lines().count(),
+    })
+}
+
+fn build_zip(bundle: &Value, logs: &str) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("bundle.json", options)?;
+    zip.write_all(
+        serde_json::to_string_pretty(bundle)
+            .unwrap_or_default()
+            .as_bytes(),
+    )?;
+    zip.start_file("backend.log", options)?;
+    zip.write_all(logs.as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// GET /api/admin/support-bundle — Download the bundle as a zip (or `?preview=true` for its contents).
+pub async fn support_bundle(
+    State(state): State<AppState>,
+    Query(params): Query<SupportBundleParams>,
+) -> Response {
+    let bundle = collect(&state).await;
+    let logs = collect_logs(&state);
+    if params.preview {
+        return Json(manifest(bundle, &logs)).into_response();
+    }
+
+    let archive = match build_zip(&bundle, &logs) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("support_bundle: failed to build zip: {}", e);
+            return api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to build support bundle",
+            )
+            .into_response();
+        }
+    };
+
+    crate::audit::log_audit(&state.db, "support_bundle_created", json!({}), None).await;
+
+    let filename = format!(
+        "attachment; filename=\"geminihydra-support-{}.zip\"",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let mut response = ([(header::CONTENT_TYPE, "application/zip")], archive).into_response();
+    if let Ok(val) = HeaderValue::from_str(&filename) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, val);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_strips_secret_keys() {
+        let mut v =
+            json!({ "api_key": "abc", "nested": { "refreshToken": "xyz" }, "theme": "dark" });
+        redact(&mut v);
+        assert_eq!(v["api_key"], REDACTED);
+        assert_eq!(v["nested"]["refreshToken"], REDACTED);
+        assert_eq!(v["theme"], "dark");
+    }
+
+    #[test]
+    fn redact_replaces_non_string_secrets() {
+        let mut v = json!({
+            "api_key": { "value": "abc", "expires": 3600 },
+            "tokens": ["a", "b"],
+            "nested": [{ "secret": 123456 }],
+            "password": null,
+            "has_api_key": true,
+        });
+        redact(&mut v);
+        assert_eq!(v["api_key"], REDACTED);
+        assert_eq!(v["tokens"], REDACTED);
+        assert_eq!(v["nested"][0]["secret"], REDACTED);
+        assert!(v["password"].is_null());
+        assert_eq!(v["has_api_key"], true);
+    }
+
+    #[test]
+    fn redact_keeps_token_counts() {
+        let mut v = json!({
+            "max_tokens": 8192,
+            "maxOutputTokens": 4096,
+            "session_token": 123456,
+            "apikey": "abc",
+        });
+        redact(&mut v);
+        assert_eq!(v["max_tokens"], 8192);
+        assert_eq!(v["maxOutputTokens"], 4096);
+        assert_eq!(v["session_token"], REDACTED);
+        assert_eq!(v["apikey"], REDACTED);
+    }
+
+    #[test]
+    fn redact_masks_inline_credentials() {
+        let mut v = json!(["call failed: Authorization: Bearer abc.def-123 rejected"]);
+        redact(&mut v);
+        assert_eq!(v[0], "call failed: Authorization: [REDACTED] rejected");
+    }
+
+    #[test]
+    fn manifest_lists_every_section() {
+        let m = manifest(json!({ "app": "GeminiHydra" }), "one\ntwo");
+        assert_eq!(m["bundle"]["app"], "GeminiHydra");
+        assert_eq!(m["log_lines"], 2);
+        let names: Vec<&str> = m["sections"]
+            .as_array()
+            .expect("sections array")
+            .iter()
+            .filter_map(|s| s["name"].as_str())
+            .collect();
+        assert!(names.contains(&"audit_log"));
+        assert!(names.contains(&"logs"));
+    }
+
+    #[test]
+    fn zip_contains_bundle_and_logs() {
+        let bytes = build_zip(&json!({ "app": "GeminiHydra" }), "line").expect("zip");
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).expect("read zip");
+        assert!(archive.by_name("bundle.json").is_ok());
+        assert!(archive.by_name("backend.log").is_ok());
+    }
+}
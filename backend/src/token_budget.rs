@@ -0,0 +1,189 @@
+// ---------------------------------------------------------------------------
+// token_budget.rs — Token counts and context-window fit before sending
+//
+// Lets the frontend warn about oversized prompts: `count` returns the token
+// count of a text, `estimate-fit` walks the history from newest to oldest
+// and reports how many messages fit into the model's input window after
+// the system prompt and an output reserve. Counts come from Gemini
+// `countTokens`; when the API is unreachable a chars/4 heuristic is used
+// and the response is flagged `estimated`. If the model's input limit
+// cannot be fetched, `limit_known` is false and no fit verdict is given.
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+type BudgetResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+const MAX_MESSAGES: usize = 200;
+const CHARS_PER_TOKEN: usize = 4;
+const DEFAULT_OUTPUT_RESERVE: u64 = 8192;
+/// Concurrent `countTokens` requests per fit estimate.
+const COUNT_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Deserialize)]
+pub struct CountRequest {
+    pub text: String,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BudgetMessage {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FitRequest {
+    pub messages: Vec<BudgetMessage>,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub system_prompt: String,
+    pub reserve_output_tokens: Option<u64>,
+}
+
+fn heuristic_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+fn text_contents(role: &str, text: &str) -> Vec<Value> {
+    let role = if role == "user" { "user" } else { "model" };
+    vec![json!({ "role": role, "parts": [{ "text": text }] })]
+}
+
+/// Token count for one text; falls back to the heuristic (second value `true`).
+async fn count(state: &AppState, model: &str, role: &str, text: &str) -> (u64, bool) {
+    if text.is_empty() {
+        return (0, false);
+    }
+    match crate::gemini_api::count_tokens(state, model, &text_contents(role, text)).await {
+        Ok(tokens) => (tokens, false),
+        Err(e) => {
+            tracing::debug!("token_budget: countTokens unavailable: {}", e);
+            (heuristic_tokens(text), true)
+        }
+    }
+}
+
+async fn resolve_model(state: &AppState, model: Option<String>) -> String {
+    match model.filter(|m| !m.is_empty()) {
+        Some(model) => model,
+        None => crate::model_registry::get_model_id(state, "chat").await,
+    }
+}
+
+/// Number of most-recent messages that fit into `budget`, and the tokens they use.
+fn newest_that_fit(counts: &[u64], budget: u64) -> (usize, u64) {
+    let mut used = 0u64;
+    let mut fit = 0;
+    for tokens in counts.iter().rev() {
+        match used.checked_add(*tokens) {
+            Some(next) if next <= budget => used = next,
+            _ => break,
+        }
+        fit += 1;
+    }
+    (fit, used)
+}
+
+/// POST /api/tokens/count — Token count of a text for a model.
+pub async fn count_text_tokens(
+    State(state): State<AppState>,
+    Json(req): Json<CountRequest>,
+) -> BudgetResult {
+    let model = resolve_model(&state, req.model).await;
+    let (tokens, estimated) = count(&state, &model, "user", &req.text).await;
+    Ok(Json(json!({
+        "model": model,
+        "tokens": tokens,
+        "estimated": estimated,
+    })))
+}
+
+/// POST /api/tokens/estimate-fit — How much of the history fits the model's input window.
+pub async fn estimate_prompt_fit(
+    State(state): State<AppState>,
+    Json(req): Json<FitRequest>,
+) -> BudgetResult {
+    if req.messages.len() > MAX_MESSAGES {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("at most {} messages per request", MAX_MESSAGES),
+        ));
+    }
+    let model = resolve_model(&state, req.model).await;
+    let input_limit = match crate::gemini_api::input_token_limit(&state, &model).await {
+        Ok(limit) => Some(limit),
+        Err(e) => {
+            tracing::debug!("token_budget: input limit unavailable for {}: {}", model, e);
+            None
+        }
+    };
+
+    let (system_tokens, system_estimated) = count(&state, &model, "user", &req.system_prompt).await;
+    let mut counted: Vec<(usize, (u64, bool))> = stream::iter(req.messages.iter().enumerate())
+        .map(|(i, m)| {
+            let state = &state;
+            let model = &model;
+            async move { (i, count(state, model, &m.role, &m.content).await) }
+        })
+        .buffer_unordered(COUNT_CONCURRENCY)
+        .collect()
+        .await;
+    counted.sort_unstable_by_key(|(i, _)| *i);
+    let counts: Vec<u64> = counted.iter().map(|(_, (tokens, _))| *tokens).collect();
+    let estimated = system_estimated || counted.iter().any(|(_, (_, est))| *est);
+
+    let reserve = req.reserve_output_tokens.unwrap_or(DEFAULT_OUTPUT_RESERVE);
+    let total = counts
+        .iter()
+        .fold(system_tokens, |acc, tokens| acc.saturating_add(*tokens));
+    let mut body = json!({
+        "model": model,
+        "input_token_limit": input_limit,
+        "limit_known": input_limit.is_some(),
+        "reserve_output_tokens": reserve,
+        "system_prompt_tokens": system_tokens,
+        "message_tokens": counts,
+        "total_tokens": total,
+        "estimated": estimated,
+    });
+
+    // Without the real window any verdict would be a guess, so leave it out.
+    if let Some(limit) = input_limit {
+        let budget = limit.saturating_sub(reserve.saturating_add(system_tokens));
+        let (fit, used) = newest_that_fit(&counts, budget);
+        body["messages_fit"] = json!(fit);
+        body["messages_truncated"] = json!(counts.len() - fit);
+        body["tokens_used"] = json!(used.saturating_add(system_tokens));
+        body["fits_entirely"] = json!(fit == counts.len());
+    }
+    Ok(Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heuristic_rounds_up() {
+        assert_eq!(heuristic_tokens(""), 0);
+        assert_eq!(heuristic_tokens("abcd"), 1);
+        assert_eq!(heuristic_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn newest_messages_are_kept_first() {
+        assert_eq!(newest_that_fit(&[50, 30, 20], 60), (2, 50));
+        assert_eq!(newest_that_fit(&[10, 10], 100), (2, 20));
+        assert_eq!(newest_that_fit(&[10, 500], 100), (0, 0));
+        assert_eq!(newest_that_fit(&[u64::MAX, 1], u64::MAX), (1, 1));
+    }
+}
@@ -15,9 +15,20 @@ pub fn build_tools(state: &crate::state::AppState) -> Value {
     state
         .tool_defs_cache
         .get_or_init(|| {
-            // GeminiHydra supports all shared tools.
+            // GeminiHydra supports all shared tools, plus the app-local code index,
+            // memory and project-scoped git tools.
             let tools = shared::all_tools();
-            shared::to_gemini_json(&tools)
+            let mut json = shared::to_gemini_json(&tools);
+            if let Some(arr) = json
+                .get_mut(0)
+                .and_then(|v| v.get_mut("function_declarations"))
+                .and_then(|v| v.as_array_mut())
+            {
+                arr.push(crate::code_index::tool_declaration());
+                arr.push(crate::memory_recall::tool_declaration());
+                arr.extend(crate::git::tool_declarations());
+            }
+            json
         })
         .clone()
 }
@@ -25,24 +36,26 @@ pub fn build_tools(state: &crate::state::AppState) -> Value {
 /// Build tools including dynamically discovered MCP tools.
 /// Native tools are cached (OnceLock), MCP tools merged at request time.
 /// MCP tools are placed FIRST — they are preferred over native equivalents.
+/// Tools disabled in the tool registry are left out, as is `remember` when
+/// no executing agent is known to remember for.
 pub async fn build_tools_with_mcp(state: &crate::state::AppState) -> serde_json::Value {
-    let native = build_tools(state);
-    let mcp_decls = state.mcp_client.build_gemini_tool_declarations().await;
-
-    if mcp_decls.is_empty() {
-        return native;
+    let mut disabled = crate::tool_registry::disabled_tools(state).await;
+    if !crate::tool_registry::in_execution_scope() {
+        disabled.insert(crate::memory_recall::TOOL_NAME.to_string());
     }
+    let mut result = build_tools(state);
+    let mcp_decls = state.mcp_client.build_gemini_tool_declarations().await;
 
     // MCP tools go FIRST — position advantage for model tool selection
-    let mut result = native.clone();
-    if let Some(arr) = result
-        .get_mut(0)
-        .and_then(|v| v.get_mut("function_declarations"))
-        .and_then(|v| v.as_array_mut())
+    if !mcp_decls.is_empty()
+        && let Some(arr) = result
+            .get_mut(0)
+            .and_then(|v| v.get_mut("function_declarations"))
+            .and_then(|v| v.as_array_mut())
     {
         let native_tools: Vec<serde_json::Value> = std::mem::take(arr);
         arr.extend(mcp_decls);
         arr.extend(native_tools);
     }
-    result
+    crate::tool_registry::filter_declarations(result, &disabled)
 }
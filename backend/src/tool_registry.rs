@@ -0,0 +1,658 @@
+// ---------------------------------------------------------------------------
+// tool_registry.rs — Permission tiers and enable/disable switches for tools
+//
+// One place that knows every tool the agents can call: the shared native
+// catalogue (tool_defs.rs) plus MCP tools discovered at runtime. Each tool
+// gets a permission tier derived from its name, and can be switched off or
+// bound to an approval rule in gh_tool_settings. Disabled tools are removed
+// from the declarations sent to Gemini and refused at dispatch time, for
+// chat, A2A and the MCP server alike; an `ask` tool call is held as a pending
+// approval (with the diff it would apply, for file writes) and refused until
+// the user approves that exact call. The executing agent is passed to
+// dispatch from a task-local execution scope, so app-local tools act for the
+// calling agent rather than one named by the model; executions outside any
+// scope have no agent, and tools that need one are neither offered nor run.
+// ---------------------------------------------------------------------------
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+/// How long a pending or granted approval for an `ask` tool call stays valid.
+const APPROVAL_TTL: Duration = Duration::from_secs(10 * 60);
+
+tokio::task_local! {
+    /// Agent of the execution running in the current scope, set by the
+    /// prepare step and read when its tool calls are dispatched.
+    static EXECUTION_AGENT: RefCell<Option<String>>;
+}
+
+/// Run `fut` as its own execution scope. An agent bound inside it, such as
+/// a delegate prepared inline by `call_agent`, never replaces the caller's.
+pub async fn execution_scope<F: Future>(fut: F) -> F::Output {
+    EXECUTION_AGENT.scope(RefCell::new(None), fut).await
+}
+
+/// Whether the current task runs inside an execution scope. Executions whose
+/// root task is owned by the shared crates (WebSocket streams, A2A workers)
+/// do not, so they have no executing agent.
+pub fn in_execution_scope() -> bool {
+    EXECUTION_AGENT.try_with(|_| ()).is_ok()
+}
+
+/// Record `agent_id` as the agent of the current execution; a no-op outside
+/// an execution scope.
+pub fn bind_execution_agent(agent_id: &str) {
+    let _ = EXECUTION_AGENT.try_with(|agent| *agent.borrow_mut() = Some(agent_id.to_string()));
+}
+
+/// The agent of the current execution, if one was bound in this scope.
+pub fn execution_agent() -> Option<String> {
+    EXECUTION_AGENT
+        .try_with(|agent| agent.borrow().clone())
+        .ok()
+        .flatten()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalRule {
+    /// Run without asking.
+    #[default]
+    Auto,
+    /// Every call needs a fresh approval from the user.
+    Ask,
+}
+
+impl ApprovalRule {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Ask => "ask",
+        }
+    }
+}
+
+/// Overrides stored in gh_tool_settings; tools without a row are enabled and `auto`.
+#[derive(Debug, Clone, Default)]
+pub struct ToolSettings {
+    pub disabled: HashSet<String>,
+    pub approval: HashMap<String, ApprovalRule>,
+}
+
+/// A call of an `ask` tool waiting for (or holding) the user's approval.
+#[derive(Debug, Clone)]
+struct PendingApproval {
+    tool: String,
+    args: Value,
+    /// What the call would change, for `write_file` / `edit_file`.
+    diff: Option<Value>,
+    requested: Instant,
+    approved: bool,
+}
+
+/// Lazily loaded tool settings plus the pending one-shot approvals, keyed by
+/// call fingerprint (tool name + arguments).
+#[derive(Debug, Default)]
+pub struct ToolSettingsState {
+    /// Bumped on every change so a load that raced with an update is discarded.
+    generation: AtomicU64,
+    settings: RwLock<Option<ToolSettings>>,
+    approvals: std::sync::Mutex<HashMap<String, PendingApproval>>,
+}
+
+pub type ToolSettingsCache = Arc<ToolSettingsState>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionTier {
+    /// Reads local files / code / git state.
+    Read,
+    /// Modifies the local filesystem or repository.
+    Write,
+    /// Runs arbitrary shell commands.
+    Execute,
+    /// Talks to remote services (web, GitHub, Vercel, Fly.io).
+    Network,
+    /// Delegates to another agent.
+    Agent,
+    /// Provided by an external MCP server, or not classified at all.
+    External,
+}
+
+/// Tools known to only read local state. Anything not listed in one of the
+/// tier tables is treated as `External`, so an unclassified tool is never
+/// mistaken for a harmless local one.
+pub const READ_TOOLS: &[&str] = &[
+    "list_directory",
+    "read_file",
+    "read_file_section",
+    "search_files",
+    "find_file",
+    "get_code_structure",
+    "diff_files",
+    "read_pdf",
+    "list_zip",
+    "git_status",
+    "git_log",
+    "git_diff",
+    crate::code_index::TOOL_NAME,
+    crate::git::STATUS_TOOL,
+];
+const WRITE_TOOLS: &[&str] = &[
+    "write_file",
+    "edit_file",
+    "extract_zip_file",
+    "git_commit",
+    "git_branch",
+    crate::git::STAGE_TOOL,
+    crate::git::COMMIT_TOOL,
+    crate::git::BRANCH_TOOL,
+    crate::memory_recall::TOOL_NAME,
+];
+const NETWORK_PREFIXES: &[&str] = &["fetch_", "crawl_", "web_", "github_", "vercel_", "fly_"];
+const NETWORK_TOOLS: &[&str] = &["analyze_image", "generate_image"];
+
+/// Permission tier of a tool, derived from its name. Fails closed: unknown
+/// names get the most restrictive tier.
+pub fn permission_tier(name: &str) -> PermissionTier {
+    if name.starts_with("mcp_") {
+        PermissionTier::External
+    } else if READ_TOOLS.contains(&name) {
+        PermissionTier::Read
+    } else if name == "execute_command" {
+        PermissionTier::Execute
+    } else if name == "call_agent" {
+        PermissionTier::Agent
+    } else if WRITE_TOOLS.contains(&name) {
+        PermissionTier::Write
+    } else if NETWORK_TOOLS.contains(&name) || NETWORK_PREFIXES.iter().any(|p| name.starts_with(p))
+    {
+        PermissionTier::Network
+    } else {
+        PermissionTier::External
+    }
+}
+
+/// Current tool settings (loaded once, refreshed after every update).
+/// Errors are not cached, so the next lookup retries the database.
+pub async fn tool_settings(state: &AppState) -> Result<ToolSettings, String> {
+    let cache = &state.tool_settings;
+    if let Some(cached) = cache.settings.read().await.as_ref() {
+        return Ok(cached.clone());
+    }
+    let generation = cache.generation.load(Ordering::Acquire);
+    let rows: Vec<(String, bool, String)> =
+        sqlx::query_as("SELECT name, enabled, approval FROM gh_tool_settings")
+            .fetch_all(&state.db)
+            .await
+            .map_err(|e| {
+                tracing::warn!("tool_registry: failed to load tool settings: {}", e);
+                "tool settings are unavailable".to_string()
+            })?;
+    let mut settings = ToolSettings::default();
+    for (name, enabled, approval) in rows {
+        if approval == "ask" {
+            settings.approval.insert(name.clone(), ApprovalRule::Ask);
+        }
+        if !enabled {
+            settings.disabled.insert(name);
+        }
+    }
+    let mut slot = cache.settings.write().await;
+    // An update landed while we were reading: our rows may predate it.
+    if cache.generation.load(Ordering::Acquire) == generation {
+        *slot = Some(settings.clone());
+    }
+    Ok(settings)
+}
+
+/// Currently disabled tool names, for filtering declarations. Degrades to
+/// none when the settings cannot be loaded; `execute_tool` still refuses
+/// every call in that case.
+pub async fn disabled_tools(state: &AppState) -> HashSet<String> {
+    tool_settings(state)
+        .await
+        .map(|settings| settings.disabled)
+        .unwrap_or_default()
+}
+
+/// Drop the cached settings so the next lookup reloads them.
+async fn invalidate(state: &AppState) {
+    let cache = &state.tool_settings;
+    cache.generation.fetch_add(1, Ordering::AcqRel);
+    *cache.settings.write().await = None;
+}
+
+/// Arguments serialised with object keys sorted, so the same call always
+/// gets the same fingerprint.
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|k| format!("{}:{}", Value::String(k.clone()), canonical_json(&map[k])))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Approval id of one call: a short hash of the tool name and its arguments.
+fn approval_id(name: &str, args: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(name.as_bytes());
+    hasher.update([0]);
+    hasher.update(canonical_json(args).as_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+fn approvals(state: &AppState) -> std::sync::MutexGuard<'_, HashMap<String, PendingApproval>> {
+    let mut approvals = state
+        .tool_settings
+        .approvals
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    approvals.retain(|_, pending| pending.requested.elapsed() < APPROVAL_TTL);
+    approvals
+}
+
+fn approval_json(id: &str, pending: &PendingApproval) -> Value {
+    json!({
+        "id": id,
+        "tool": pending.tool,
+        "args": pending.args,
+        "diff": pending.diff,
+        "approved": pending.approved,
+        "expires_in_secs": APPROVAL_TTL.saturating_sub(pending.requested.elapsed()).as_secs(),
+    })
+}
+
+fn approval_refusal(name: &str, id: &str, diff: Option<&Value>) -> String {
+    let change = diff
+        .and_then(|d| d["diff"].as_str())
+        .filter(|d| !d.is_empty())
+        .map(|d| format!("\nProposed change:\n{}", d))
+        .unwrap_or_default();
+    format!(
+        "Tool '{}' requires user approval (approval id {}). Ask the user to approve this \
+         exact call, then call it again with the same arguments.{}",
+        name, id, change
+    )
+}
+
+/// Consume the approval of this exact call, or register it as pending and
+/// announce it (with its diff) on the swarm channel.
+async fn check_approval(
+    state: &AppState,
+    name: &str,
+    args: &Value,
+    working_directory: &str,
+) -> Result<(), String> {
+    let id = approval_id(name, args);
+    {
+        let mut approvals = approvals(state);
+        if approvals.get(&id).is_some_and(|pending| pending.approved) {
+            approvals.remove(&id);
+            return Ok(());
+        }
+        if let Some(pending) = approvals.get(&id) {
+            return Err(approval_refusal(name, &id, pending.diff.as_ref()));
+        }
+    }
+    let pending = PendingApproval {
+        tool: name.to_string(),
+        args: args.clone(),
+        diff: crate::patch::tool_call_diff(state, name, args, working_directory).await,
+        requested: Instant::now(),
+        approved: false,
+    };
+    let mut event = approval_json(&id, &pending);
+    event["type"] = json!("tool-approval");
+    let _ = state.swarm_tx.send(jaskier_core::models::AgentMessage {
+        agent_id: "tool-approval".to_string(),
+        content: event.to_string(),
+        is_final: false,
+    });
+    let refusal = approval_refusal(name, &id, pending.diff.as_ref());
+    approvals(state).insert(id, pending);
+    Err(refusal)
+}
+
+/// Drop disabled tools from Gemini-format declarations (`[{ "function_declarations": [...] }]`).
+pub fn filter_declarations(mut tools: Value, disabled: &HashSet<String>) -> Value {
+    if disabled.is_empty() {
+        return tools;
+    }
+    if let Some(groups) = tools.as_array_mut() {
+        for group in groups {
+            if let Some(decls) = group
+                .get_mut("function_declarations")
+                .and_then(|d| d.as_array_mut())
+            {
+                decls.retain(|d| {
+                    d.get("name")
+                        .and_then(|n| n.as_str())
+                        .is_none_or(|n| !disabled.contains(n))
+                });
+            }
+        }
+    }
+    tools
+}
+
+/// Dispatch a tool call for `agent_id` (the executing agent, `None` outside
+/// an agent execution), refusing tools that are switched off. Fails closed:
+/// without settings no disabled switch or approval rule could be enforced.
+pub async fn execute_tool(
+    name: &str,
+    args: &Value,
+    state: &AppState,
+    working_directory: &str,
+    agent_id: Option<&str>,
+) -> Result<crate::context::ToolOutput, String> {
+    let settings = tool_settings(state).await.map_err(|e| {
+        format!(
+            "Tool '{}' cannot run right now: {}. Try again shortly.",
+            name, e
+        )
+    })?;
+    if settings.disabled.contains(name) {
+        return Err(format!(
+            "Tool '{}' is disabled in GeminiHydra settings. Choose a different tool.",
+            name
+        ));
+    }
+    if crate::offline::is_offline() && crate::offline::tier_needs_network(permission_tier(name)) {
+        return Err(format!(
+            "Tool '{}' needs network access: {}",
+            name,
+            crate::offline::OfflineMode
+        ));
+    }
+    // Last gate: a granted approval is consumed only by a call that then runs.
+    if settings.approval.get(name) == Some(&ApprovalRule::Ask) {
+        check_approval(state, name, args, working_directory).await?;
+    }
+    if name == crate::code_index::TOOL_NAME {
+        let text = crate::code_index::execute_lookup(state, args, working_directory).await?;
+        return Ok(crate::context::ToolOutput {
+            text,
+            inline_data: None,
+        });
+    }
+    if name == crate::memory_recall::TOOL_NAME {
+        let text = crate::memory_recall::execute_remember(state, args, agent_id, working_directory)
+            .await?;
+        return Ok(crate::context::ToolOutput {
+            text,
+            inline_data: None,
+        });
+    }
+    if crate::git::TOOL_NAMES.contains(&name) {
+        let text = crate::git::execute_tool(state, name, args, working_directory).await?;
+        return Ok(crate::context::ToolOutput {
+            text,
+            inline_data: None,
+        });
+    }
+    crate::tools::execute_tool(name, args, state, working_directory).await
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub description: String,
+    pub tier: PermissionTier,
+    pub enabled: bool,
+    pub approval: ApprovalRule,
+    /// `native` or `mcp`.
+    pub source: &'static str,
+}
+
+fn declarations(tools: &Value) -> impl Iterator<Item = &Value> {
+    tools
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|g| g.get("function_declarations").and_then(|d| d.as_array()))
+        .flatten()
+}
+
+/// GET /api/tools — Every tool with its permission tier and enabled state.
+pub async fn list_tools(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let settings = match tool_settings(&state).await {
+        Ok(settings) => settings,
+        Err(e) => return api_error(StatusCode::SERVICE_UNAVAILABLE, e),
+    };
+    let native = crate::tool_defs::build_tools(&state);
+    let mcp = Value::Array(state.mcp_client.build_gemini_tool_declarations().await);
+
+    let mut tools: Vec<ToolInfo> = Vec::new();
+    let sources = declarations(&native)
+        .map(|d| (d, "native"))
+        .chain(mcp.as_array().into_iter().flatten().map(|d| (d, "mcp")));
+    for (decl, source) in sources {
+        let Some(name) = decl.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        tools.push(ToolInfo {
+            name: name.to_string(),
+            description: decl
+                .get("description")
+                .and_then(|d| d.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            tier: permission_tier(name),
+            enabled: !settings.disabled.contains(name),
+            approval: settings.approval.get(name).copied().unwrap_or_default(),
+            source,
+        });
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "tools": tools, "disabled_count": settings.disabled.len() })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateToolRequest {
+    pub enabled: Option<bool>,
+    pub approval: Option<ApprovalRule>,
+}
+
+/// PATCH /api/tools/{name} — Enable/disable a tool or set its approval rule, for all agents.
+pub async fn update_tool(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateToolRequest>,
+) -> (StatusCode, Json<Value>) {
+    if req.enabled.is_none() && req.approval.is_none() {
+        return api_error(
+            StatusCode::BAD_REQUEST,
+            "either 'enabled' or 'approval' is required",
+        );
+    }
+    let row: (bool, String) = match sqlx::query_as(
+        "INSERT INTO gh_tool_settings (name, enabled, approval) \
+         VALUES ($1, COALESCE($2, TRUE), COALESCE($3, 'auto')) \
+         ON CONFLICT (name) DO UPDATE SET \
+           enabled = COALESCE($2, gh_tool_settings.enabled), \
+           approval = COALESCE($3, gh_tool_settings.approval), \
+           updated_at = NOW() \
+         RETURNING enabled, approval",
+    )
+    .bind(&name)
+    .bind(req.enabled)
+    .bind(req.approval.map(ApprovalRule::as_str))
+    .fetch_one(&state.db)
+    .await
+    {
+        Ok(row) => row,
+        Err(e) => {
+            tracing::error!("tool_registry: failed to update '{}': {}", name, e);
+            return api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error");
+        }
+    };
+    invalidate(&state).await;
+    let (enabled, approval) = row;
+
+    crate::audit::log_audit(
+        &state.db,
+        "tool_toggle",
+        json!({ "tool": name, "enabled": enabled, "approval": approval }),
+        None,
+    )
+    .await;
+    (
+        StatusCode::OK,
+        Json(json!({
+            "name": name,
+            "enabled": enabled,
+            "approval": approval,
+            "tier": permission_tier(&name),
+        })),
+    )
+}
+
+/// GET /api/tools/approvals — Calls of `ask` tools waiting for approval, with their diffs.
+pub async fn list_approvals(State(state): State<AppState>) -> Json<Value> {
+    let approvals: Vec<Value> = approvals(&state)
+        .iter()
+        .map(|(id, pending)| approval_json(id, pending))
+        .collect();
+    Json(json!({ "approvals": approvals }))
+}
+
+/// POST /api/tools/approvals/{id}/approve — Allow one pending call, with exactly its arguments.
+pub async fn approve_tool(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    let approved = approvals(&state).get_mut(&id).map(|pending| {
+        pending.approved = true;
+        approval_json(&id, pending)
+    });
+    let Some(approved) = approved else {
+        return api_error(
+            StatusCode::NOT_FOUND,
+            format!("no pending approval '{}'", id),
+        );
+    };
+    crate::audit::log_audit(
+        &state.db,
+        "tool_approve",
+        json!({ "id": id, "tool": approved["tool"], "args": approved["args"] }),
+        None,
+    )
+    .await;
+    (StatusCode::OK, Json(approved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_follow_tool_names() {
+        assert_eq!(permission_tier("read_file"), PermissionTier::Read);
+        assert_eq!(permission_tier("edit_file"), PermissionTier::Write);
+        assert_eq!(permission_tier("execute_command"), PermissionTier::Execute);
+        assert_eq!(permission_tier("fetch_webpage"), PermissionTier::Network);
+        assert_eq!(permission_tier("github_create_pr"), PermissionTier::Network);
+        assert_eq!(permission_tier("call_agent"), PermissionTier::Agent);
+        assert_eq!(permission_tier("mcp_fs_read"), PermissionTier::External);
+        assert_eq!(
+            permission_tier("git_commit_with_message"),
+            PermissionTier::Write
+        );
+        assert_eq!(permission_tier("git_project_status"), PermissionTier::Read);
+        assert_eq!(
+            permission_tier(crate::memory_recall::TOOL_NAME),
+            PermissionTier::Write
+        );
+    }
+
+    #[test]
+    fn unknown_tools_get_the_most_restrictive_tier() {
+        assert_eq!(permission_tier("upload_anywhere"), PermissionTier::External);
+        assert_eq!(permission_tier("read_file_v2"), PermissionTier::External);
+        assert!(crate::offline::tier_needs_network(permission_tier(
+            "upload_anywhere"
+        )));
+    }
+
+    #[tokio::test]
+    async fn agents_are_bound_only_inside_a_scope() {
+        bind_execution_agent("geralt");
+        assert!(!in_execution_scope());
+        assert_eq!(execution_agent(), None);
+
+        let (outer, inner) = execution_scope(async {
+            bind_execution_agent("geralt");
+            let inner = execution_scope(async {
+                bind_execution_agent("yennefer");
+                execution_agent()
+            })
+            .await;
+            (execution_agent(), inner)
+        })
+        .await;
+        assert_eq!(outer.as_deref(), Some("geralt"));
+        assert_eq!(inner.as_deref(), Some("yennefer"));
+    }
+
+    #[test]
+    fn approval_rules_deserialize_lowercase() {
+        let req: UpdateToolRequest =
+            serde_json::from_value(json!({ "approval": "ask" })).expect("valid request");
+        assert_eq!(req.approval, Some(ApprovalRule::Ask));
+        assert_eq!(req.enabled, None);
+        assert_eq!(ApprovalRule::default().as_str(), "auto");
+    }
+
+    #[test]
+    fn approval_ids_ignore_key_order_but_not_values() {
+        let a = json!({ "path": "a.txt", "content": "x" });
+        let b = json!({ "content": "x", "path": "a.txt" });
+        let c = json!({ "path": "a.txt", "content": "y" });
+        assert_eq!(approval_id("write_file", &a), approval_id("write_file", &b));
+        assert_ne!(approval_id("write_file", &a), approval_id("write_file", &c));
+        assert_ne!(approval_id("write_file", &a), approval_id("edit_file", &a));
+    }
+
+    #[test]
+    fn disabled_declarations_are_removed() {
+        let tools = json!([{ "function_declarations": [
+            { "name": "read_file" },
+            { "name": "execute_command" },
+        ] }]);
+        let disabled: HashSet<String> = ["execute_command".to_string()].into();
+        let filtered = filter_declarations(tools, &disabled);
+        let names: Vec<&str> = declarations(&filtered)
+            .filter_map(|d| d["name"].as_str())
+            .collect();
+        assert_eq!(names, vec!["read_file"]);
+    }
+}
@@ -0,0 +1,260 @@
+// ---------------------------------------------------------------------------
+// usage.rs — Token usage ledger and cost summary
+//
+// gh_agent_usage is filled by the shared streaming/execute pipeline and, via
+// `record_usage`, by the app-local Gemini calls (briefs, summaries,
+// structured output, regeneration). `GET /api/usage/summary` aggregates it
+// by day, model, agent, tier or project with an estimated USD cost based on
+// Gemini list prices.
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+/// USD per million (input, output) tokens, matched by substring, most specific first.
+const PRICING: [(&str, f64, f64); 4] = [
+    ("flash-lite", 0.10, 0.40),
+    ("flash", 0.30, 2.50),
+    ("pro", 1.25, 10.00),
+    ("gemini", 0.30, 2.50),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct SummaryParams {
+    /// `day`, `week`, `month` or `all` (default `month`).
+    #[serde(default = "default_period")]
+    pub period: String,
+    /// `day`, `model`, `agent`, `tier` or `project` (default `day`).
+    #[serde(default = "default_group_by")]
+    pub group_by: String,
+}
+
+fn default_period() -> String {
+    "month".to_string()
+}
+
+fn default_group_by() -> String {
+    "day".to_string()
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct UsageGroup {
+    pub key: String,
+    pub requests: i64,
+    pub failures: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    pub estimated_cost_usd: f64,
+}
+
+/// Estimated cost of a call; unknown (non-Gemini) models cost 0.
+pub fn estimate_cost_usd(model: &str, input_tokens: i64, output_tokens: i64) -> f64 {
+    let model = model.to_ascii_lowercase();
+    PRICING
+        .iter()
+        .find(|(pattern, _, _)| model.contains(pattern))
+        .map(|(_, input, output)| {
+            (input_tokens.max(0) as f64 * input + output_tokens.max(0) as f64 * output) / 1e6
+        })
+        .unwrap_or(0.0)
+}
+
+/// Store one call's `usageMetadata` in the ledger. Failures are only logged.
+pub async fn record_usage(
+    state: &AppState,
+    model: &str,
+    tier: &str,
+    usage_metadata: Option<&Value>,
+    latency_ms: u128,
+    success: bool,
+) {
+    let count = |field: &str| {
+        usage_metadata
+            .and_then(|u| u.get(field))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0) as i32
+    };
+    let (input, output) = (count("promptTokenCount"), count("candidatesTokenCount"));
+    let total = match count("totalTokenCount") {
+        0 => input + output,
+        t => t,
+    };
+    if let Err(e) = sqlx::query(
+        "INSERT INTO gh_agent_usage \
+         (agent_id, model, input_tokens, output_tokens, total_tokens, latency_ms, success, tier) \
+         VALUES (NULL, $1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(model)
+    .bind(input)
+    .bind(output)
+    .bind(total)
+    .bind(i32::try_from(latency_ms).unwrap_or(i32::MAX))
+    .bind(success)
+    .bind(tier)
+    .execute(&state.db)
+    .await
+    {
+        tracing::warn!("usage: failed to record usage: {}", e);
+    }
+}
+
+fn group_expr(group_by: &str) -> Option<&'static str> {
+    match group_by {
+        "day" => Some("to_char(date_trunc('day', u.created_at), 'YYYY-MM-DD')"),
+        "model" => Some("u.model"),
+        "agent" => Some("COALESCE(u.agent_id, 'background')"),
+        "tier" => Some("COALESCE(u.tier, 'unknown')"),
+        "project" => Some("COALESCE(p.name, 'none')"),
+        _ => None,
+    }
+}
+
+fn period_days(period: &str) -> Option<Option<i64>> {
+    match period {
+        "day" => Some(Some(1)),
+        "week" => Some(Some(7)),
+        "month" => Some(Some(30)),
+        "all" => Some(None),
+        _ => None,
+    }
+}
+
+/// Fold per-(group, model) rows into groups, pricing each model separately.
+fn fold_groups(rows: Vec<(String, String, i64, i64, i64, i64, i64)>) -> Vec<UsageGroup> {
+    let mut groups: Vec<UsageGroup> = Vec::new();
+    for (key, model, requests, failures, input, output, total) in rows {
+        let idx = match groups.iter().position(|g| g.key == key) {
+            Some(idx) => idx,
+            None => {
+                groups.push(UsageGroup {
+                    key,
+                    ..UsageGroup::default()
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[idx];
+        group.requests += requests;
+        group.failures += failures;
+        group.input_tokens += input;
+        group.output_tokens += output;
+        group.total_tokens += total;
+        group.estimated_cost_usd += estimate_cost_usd(&model, input, output);
+    }
+    groups
+}
+
+/// GET /api/usage/summary?period=month&group_by=model
+pub async fn usage_summary(
+    State(state): State<AppState>,
+    Query(params): Query<SummaryParams>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let (Some(expr), Some(days)) = (group_expr(&params.group_by), period_days(&params.period))
+    else {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "period must be day|week|month|all and group_by day|model|agent|tier|project",
+        ));
+    };
+
+    let sql = format!(
+        "SELECT {expr}, u.model, COUNT(*)::bigint, \
+                (COUNT(*) FILTER (WHERE NOT COALESCE(u.success, TRUE)))::bigint, \
+                COALESCE(SUM(u.input_tokens), 0)::bigint, \
+                COALESCE(SUM(u.output_tokens), 0)::bigint, \
+                COALESCE(SUM(u.total_tokens), 0)::bigint \
+         FROM gh_agent_usage u LEFT JOIN gh_projects p ON p.id = u.project_id \
+         WHERE ($1::bigint IS NULL OR u.created_at >= NOW() - make_interval(days => $1::int)) \
+         GROUP BY 1, 2 ORDER BY 1",
+        expr = expr
+    );
+    let rows: Vec<(String, String, i64, i64, i64, i64, i64)> = sqlx::query_as(&sql)
+        .bind(days)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("usage: summary query failed: {}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        })?;
+
+    let groups = fold_groups(rows);
+    let total_cost: f64 = groups.iter().map(|g| g.estimated_cost_usd).sum();
+    let total_tokens: i64 = groups.iter().map(|g| g.total_tokens).sum();
+    Ok(Json(json!({
+        "period": params.period,
+        "group_by": params.group_by,
+        "groups": groups,
+        "total_tokens": total_tokens,
+        "estimated_cost_usd": total_cost,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pricing_matches_most_specific_family() {
+        let lite = estimate_cost_usd("gemini-2.5-flash-lite", 1_000_000, 0);
+        let flash = estimate_cost_usd("gemini-2.5-flash", 1_000_000, 0);
+        let pro = estimate_cost_usd("gemini-2.5-pro", 0, 1_000_000);
+        assert!((lite - 0.10).abs() < 1e-9);
+        assert!((flash - 0.30).abs() < 1e-9);
+        assert!((pro - 10.0).abs() < 1e-9);
+        assert!(estimate_cost_usd("llama-3", 1_000, 1_000).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn groups_fold_across_models() {
+        let rows = vec![
+            (
+                "2026-10-13".to_string(),
+                "gemini-2.5-pro".to_string(),
+                2,
+                0,
+                1000,
+                500,
+                1500,
+            ),
+            (
+                "2026-10-13".to_string(),
+                "gemini-2.5-flash".to_string(),
+                3,
+                1,
+                300,
+                100,
+                400,
+            ),
+            (
+                "2026-10-14".to_string(),
+                "gemini-2.5-flash".to_string(),
+                1,
+                0,
+                10,
+                10,
+                20,
+            ),
+        ];
+        let groups = fold_groups(rows);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].requests, 5);
+        assert_eq!(groups[0].failures, 1);
+        assert_eq!(groups[0].total_tokens, 1900);
+        assert!(groups[0].estimated_cost_usd > groups[1].estimated_cost_usd);
+    }
+
+    #[test]
+    fn unknown_grouping_is_rejected() {
+        assert!(group_expr("model").is_some());
+        assert!(group_expr("1; DROP TABLE gh_agent_usage").is_none());
+        assert_eq!(period_days("week"), Some(Some(7)));
+        assert_eq!(period_days("year"), None);
+    }
+}
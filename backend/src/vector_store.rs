@@ -0,0 +1,735 @@
+// ---------------------------------------------------------------------------
+// vector_store.rs — Named vector collections on pgvector HNSW indexes
+//
+// Collections fix their dimension, distance metric and (optionally) the
+// embedding model; upserts and queries that name another model (or none)
+// are rejected so vectors from different models never mix in one index.
+// Every collection gets its own partial HNSW index, so queries never fall
+// back to a linear scan. Filtered
+// queries use pgvector's iterative scan (0.8+) to still return `top_k` rows;
+// on older pgvector they over-fetch candidates instead, and a very selective
+// filter can return fewer than `top_k`.
+//
+// Tables come from migration 052 and only exist when pgvector is installed;
+// without it every endpoint answers 503.
+//
+// Chunk metadata conventions used for citations and invalidation:
+// `source_path`, `line_start`/`line_end`, `mtime` (unix seconds, stamped
+// from the file on upsert when omitted) and `tags` (array of strings).
+// Relative source paths are resolved against the active project's root.
+// ---------------------------------------------------------------------------
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sqlx::Acquire;
+
+use crate::handlers::api_error;
+use crate::state::AppState;
+
+type VsResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+const MAX_DIMENSION: i32 = 2000;
+const MAX_TOP_K: i64 = 200;
+const MAX_UPSERT_BATCH: usize = 1000;
+/// pgvector's default `hnsw.ef_search`; raised per query so `top_k` is never capped.
+const MIN_EF_SEARCH: i64 = 40;
+/// Upper bound pgvector accepts for `hnsw.ef_search`.
+const MAX_EF_SEARCH: i64 = 1000;
+/// Candidates per requested row for filtered queries without iterative scans.
+const FILTER_OVERFETCH: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    pub dimension: i32,
+    #[serde(default = "default_metric")]
+    pub metric: String,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_metric() -> String {
+    "cosine".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VectorItem {
+    pub id: String,
+    pub embedding: Vec<f32>,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertRequest {
+    pub items: Vec<VectorItem>,
+    /// Embedding model that produced the vectors — must match the collection's.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryRequest {
+    pub embedding: Vec<f32>,
+    #[serde(default = "default_top_k")]
+    pub top_k: i64,
+    /// Optional JSONB containment filter, e.g. `{"source": "docs/API.md"}`.
+    #[serde(default)]
+    pub filter: Option<Value>,
+    /// Only chunks carrying all of these tags.
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// Only chunks whose `source_path` starts with this prefix.
+    #[serde(default)]
+    pub source_prefix: Option<String>,
+    /// Embedding model of the query vector — required when the collection has one.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct InvalidateRequest {
+    /// Report stale chunks without deleting them.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn default_top_k() -> i64 {
+    10
+}
+
+struct Collection {
+    id: i32,
+    dimension: i32,
+    metric: String,
+    model: Option<String>,
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+fn db_err(e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    let undefined_table = e
+        .as_database_error()
+        .and_then(|d| d.code())
+        .is_some_and(|c| c == "42P01");
+    if undefined_table {
+        return api_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "vector store unavailable (pgvector extension not installed)",
+        );
+    }
+    tracing::error!("vector_store: database error: {}", e);
+    api_error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+/// pgvector operator class and distance operator for a metric.
+fn metric_ops(metric: &str) -> Option<(&'static str, &'static str)> {
+    match metric {
+        "cosine" => Some(("vector_cosine_ops", "<=>")),
+        "l2" => Some(("vector_l2_ops", "<->")),
+        "ip" => Some(("vector_ip_ops", "<#>")),
+        _ => None,
+    }
+}
+
+/// Render an embedding as a pgvector text literal (`[0.1,0.2,...]`).
+fn to_pgvector(embedding: &[f32]) -> String {
+    let parts: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", parts.join(","))
+}
+
+fn check_embedding(embedding: &[f32], dimension: i32) -> Result<(), String> {
+    if embedding.len() != dimension as usize {
+        return Err(format!(
+            "embedding has {} dimensions, collection expects {}",
+            embedding.len(),
+            dimension
+        ));
+    }
+    if embedding.iter().any(|v| !v.is_finite()) {
+        return Err("embedding contains NaN or infinite values".to_string());
+    }
+    Ok(())
+}
+
+/// Reject vectors whose model is missing or differs from the collection's.
+fn check_model(
+    collection: &str,
+    expected: Option<&str>,
+    given: Option<&str>,
+) -> Result<(), String> {
+    match (expected, given) {
+        (None, _) => Ok(()),
+        (Some(expected), None) => Err(format!(
+            "collection '{}' holds '{}' embeddings, the request must name its model",
+            collection, expected
+        )),
+        (Some(expected), Some(given)) if expected != given => Err(format!(
+            "collection '{}' holds '{}' embeddings, refusing vectors from '{}'",
+            collection, expected, given
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// `path` as-is when absolute, otherwise joined onto the project root.
+fn resolve_source(root: Option<&str>, path: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(path);
+    match root {
+        Some(root) if path.is_relative() => std::path::Path::new(root).join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Root of the active project, used to resolve relative `source_path`s.
+async fn source_root(state: &AppState) -> Option<String> {
+    crate::projects::active_project(&state.db)
+        .await
+        .map(|p| p.root_path)
+}
+
+/// Modification time of a file in unix seconds.
+async fn file_mtime(path: &std::path::Path) -> Option<i64> {
+    let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+    let secs = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// Validate chunk metadata and stamp `mtime` from `source_path` when missing.
+async fn normalize_metadata(metadata: Option<Value>, root: Option<&str>) -> Result<Value, String> {
+    let mut metadata = metadata.unwrap_or_else(|| json!({}));
+    let Some(map) = metadata.as_object_mut() else {
+        return Err("metadata must be an object".to_string());
+    };
+    if let (Some(start), Some(end)) = (
+        map.get("line_start").and_then(|v| v.as_u64()),
+        map.get("line_end").and_then(|v| v.as_u64()),
+    ) && start > end
+    {
+        return Err(format!("line_start {} is after line_end {}", start, end));
+    }
+    if let Some(path) = map.get("source_path")
+        && !path.is_string()
+    {
+        return Err("source_path must be a string".to_string());
+    }
+    if let Some(tags) = map.get("tags")
+        && !tags
+            .as_array()
+            .is_some_and(|t| t.iter().all(|v| v.is_string()))
+    {
+        return Err("tags must be an array of strings".to_string());
+    }
+    if let Some(mtime) = map.get("mtime")
+        && !mtime.is_i64()
+    {
+        return Err("mtime must be an integer (unix seconds)".to_string());
+    }
+    if !map.contains_key("mtime")
+        && let Some(path) = map.get("source_path").and_then(|p| p.as_str())
+        && let Some(mtime) = file_mtime(&resolve_source(root, path)).await
+    {
+        map.insert("mtime".to_string(), json!(mtime));
+    }
+    Ok(metadata)
+}
+
+/// A chunk is stale when its file is gone, its mtime is unknown or it moved.
+fn is_stale(indexed: Option<i64>, current: Option<i64>) -> bool {
+    match (indexed, current) {
+        (Some(indexed), Some(current)) => indexed != current,
+        _ => true,
+    }
+}
+
+/// `path:start-end` (or just `path`) for chunks that carry a source.
+fn citation(metadata: &Value) -> Option<String> {
+    let path = metadata.get("source_path")?.as_str()?;
+    let start = metadata.get("line_start").and_then(|v| v.as_u64());
+    let end = metadata.get("line_end").and_then(|v| v.as_u64());
+    Some(match (start, end) {
+        (Some(s), Some(e)) if s != e => format!("{}:{}-{}", path, s, e),
+        (Some(s), _) => format!("{}:{}", path, s),
+        _ => path.to_string(),
+    })
+}
+
+async fn load_collection(
+    state: &AppState,
+    name: &str,
+) -> Result<Collection, (StatusCode, Json<Value>)> {
+    let row: Option<(i32, i32, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, dimension, metric, model FROM gh_vector_collections WHERE name = $1",
+    )
+    .bind(name)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_err)?;
+
+    row.map(|(id, dimension, metric, model)| Collection {
+        id,
+        dimension,
+        metric,
+        model,
+    })
+    .ok_or_else(|| {
+        api_error(
+            StatusCode::NOT_FOUND,
+            format!("collection '{}' not found", name),
+        )
+    })
+}
+
+// ── Handlers ─────────────────────────────────────────────────────────────────
+
+/// POST /api/vectors/collections — vs_create_collection
+pub async fn create_collection(
+    State(state): State<AppState>,
+    Json(body): Json<CreateCollectionRequest>,
+) -> VsResult {
+    let name = body.name.trim();
+    if name.is_empty() || name.len() > 128 {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "name must be 1-128 characters",
+        ));
+    }
+    if !(1..=MAX_DIMENSION).contains(&body.dimension) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("dimension must be between 1 and {}", MAX_DIMENSION),
+        ));
+    }
+    let Some((opclass, _)) = metric_ops(&body.metric) else {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "metric must be cosine, l2 or ip",
+        ));
+    };
+
+    // Row and index are created together so a failed DDL leaves no orphaned collection.
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO gh_vector_collections (name, dimension, metric, model) \
+         VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(name)
+    .bind(body.dimension)
+    .bind(&body.metric)
+    .bind(&body.model)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        if e.as_database_error()
+            .is_some_and(|d| d.is_unique_violation())
+        {
+            api_error(
+                StatusCode::CONFLICT,
+                format!("collection '{}' already exists", name),
+            )
+        } else {
+            db_err(e)
+        }
+    })?;
+
+    // id and dimension are integers validated above — safe to inline into DDL.
+    let ddl = format!(
+        "CREATE INDEX IF NOT EXISTS idx_gh_vec_hnsw_{id} ON gh_vector_items \
+         USING hnsw ((embedding::vector({dim})) {opclass}) WHERE collection_id = {id}",
+        id = id,
+        dim = body.dimension,
+        opclass = opclass,
+    );
+    sqlx::query(&ddl).execute(&mut *tx).await.map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+
+    Ok(Json(json!({
+        "id": id,
+        "name": name,
+        "dimension": body.dimension,
+        "metric": body.metric,
+        "model": body.model,
+    })))
+}
+
+/// GET /api/vectors/collections
+pub async fn list_collections(State(state): State<AppState>) -> VsResult {
+    let rows: Vec<(String, i32, String, Option<String>, i64)> = sqlx::query_as(
+        "SELECT c.name, c.dimension, c.metric, c.model, \
+         (SELECT COUNT(*) FROM gh_vector_items i WHERE i.collection_id = c.id) \
+         FROM gh_vector_collections c ORDER BY c.name",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_err)?;
+
+    let collections: Vec<Value> = rows
+        .into_iter()
+        .map(|(name, dimension, metric, model, count)| {
+            json!({ "name": name, "dimension": dimension, "metric": metric, "model": model, "count": count })
+        })
+        .collect();
+    Ok(Json(json!({ "collections": collections })))
+}
+
+/// DELETE /api/vectors/collections/{name}
+pub async fn delete_collection(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> VsResult {
+    let col = load_collection(&state, &name).await?;
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    sqlx::query(&format!("DROP INDEX IF EXISTS idx_gh_vec_hnsw_{}", col.id))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    sqlx::query("DELETE FROM gh_vector_collections WHERE id = $1")
+        .bind(col.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+    Ok(Json(json!({ "deleted": name })))
+}
+
+/// POST /api/vectors/collections/{name}/upsert — vs_upsert
+pub async fn upsert(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<UpsertRequest>,
+) -> VsResult {
+    let col = load_collection(&state, &name).await?;
+    if body.items.len() > MAX_UPSERT_BATCH {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("at most {} items per upsert", MAX_UPSERT_BATCH),
+        ));
+    }
+    check_model(&name, col.model.as_deref(), body.model.as_deref())
+        .map_err(|e| api_error(StatusCode::CONFLICT, e))?;
+    for item in &body.items {
+        check_embedding(&item.embedding, col.dimension).map_err(|e| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                format!("item '{}': {}", item.id, e),
+            )
+        })?;
+    }
+
+    let root = source_root(&state).await;
+    let mut metadata_by_item = Vec::with_capacity(body.items.len());
+    for item in &body.items {
+        let metadata = normalize_metadata(item.metadata.clone(), root.as_deref())
+            .await
+            .map_err(|e| {
+                api_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("item '{}': {}", item.id, e),
+                )
+            })?;
+        metadata_by_item.push(metadata.to_string());
+    }
+
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    for (item, metadata) in body.items.iter().zip(metadata_by_item) {
+        sqlx::query(
+            "INSERT INTO gh_vector_items (collection_id, id, embedding, content, metadata) \
+             VALUES ($1, $2, $3::vector, $4, $5::jsonb) \
+             ON CONFLICT (collection_id, id) DO UPDATE SET \
+             embedding = EXCLUDED.embedding, content = EXCLUDED.content, \
+             metadata = EXCLUDED.metadata, updated_at = NOW()",
+        )
+        .bind(col.id)
+        .bind(&item.id)
+        .bind(to_pgvector(&item.embedding))
+        .bind(&item.content)
+        .bind(metadata)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    }
+    tx.commit().await.map_err(db_err)?;
+
+    Ok(Json(json!({ "upserted": body.items.len() })))
+}
+
+/// POST /api/vectors/collections/{name}/query — vs_query (nearest neighbours)
+pub async fn query(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<QueryRequest>,
+) -> VsResult {
+    let col = load_collection(&state, &name).await?;
+    check_embedding(&body.embedding, col.dimension)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+    check_model(&name, col.model.as_deref(), body.model.as_deref())
+        .map_err(|e| api_error(StatusCode::CONFLICT, e))?;
+    let Some((_, op)) = metric_ops(&col.metric) else {
+        return Err(api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "collection has an unknown metric",
+        ));
+    };
+
+    let top_k = body.top_k.clamp(1, MAX_TOP_K);
+
+    // The ORDER BY expression must match the partial index expression exactly, and
+    // the collection id is inlined (like in the DDL) so the planner can prove the
+    // partial index predicate.
+    let expr = format!(
+        "embedding::vector({}) {} $1::vector({})",
+        col.dimension, op, col.dimension
+    );
+    let sql = format!(
+        "SELECT id, content, metadata::text, ({expr})::float8 AS distance \
+         FROM gh_vector_items \
+         WHERE collection_id = {id} AND ($3::jsonb IS NULL OR metadata @> $3::jsonb) \
+           AND ($4::text[] IS NULL OR (metadata->'tags') ?& $4) \
+           AND ($5::text IS NULL OR starts_with(metadata->>'source_path', $5)) \
+         ORDER BY {expr} LIMIT $2",
+        expr = expr,
+        id = col.id,
+    );
+
+    let tags = body.tags.as_ref().filter(|t| !t.is_empty());
+    let source_prefix = body.source_prefix.as_deref().filter(|p| !p.is_empty());
+    let filtered = body.filter.is_some() || tags.is_some() || source_prefix.is_some();
+
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    // Filters run on the HNSW candidates, so a filtered query may see fewer
+    // than top_k matches. pgvector >= 0.8 keeps scanning the index until
+    // enough rows pass; older versions only get a wider candidate list.
+    let iterative = filtered && enable_iterative_scan(&mut tx).await?;
+    let ef_search = if filtered && !iterative {
+        (top_k * FILTER_OVERFETCH).min(MAX_EF_SEARCH)
+    } else {
+        top_k
+    };
+    sqlx::query(&format!(
+        "SET LOCAL hnsw.ef_search = {}",
+        ef_search.max(MIN_EF_SEARCH)
+    ))
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    let rows: Vec<(String, String, String, f64)> = sqlx::query_as(&sql)
+        .bind(to_pgvector(&body.embedding))
+        .bind(top_k)
+        .bind(body.filter.as_ref().map(|f| f.to_string()))
+        .bind(tags)
+        .bind(source_prefix)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+
+    let matches: Vec<Value> = rows
+        .into_iter()
+        .map(|(id, content, metadata, distance)| {
+            let metadata = serde_json::from_str::<Value>(&metadata).unwrap_or(Value::Null);
+            json!({
+                "id": id,
+                "content": content,
+                "citation": citation(&metadata),
+                "metadata": metadata,
+                "distance": distance,
+            })
+        })
+        .collect();
+    Ok(Json(
+        json!({ "collection": name, "metric": col.metric, "matches": matches }),
+    ))
+}
+
+/// Turn on pgvector's iterative index scans for the rest of `tx`. Returns
+/// `false` on versions without them (before 0.8), where the setting is
+/// rejected; the savepoint keeps that error from aborting `tx`.
+async fn enable_iterative_scan(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<bool, (StatusCode, Json<Value>)> {
+    let mut savepoint = tx.begin().await.map_err(db_err)?;
+    match sqlx::query("SET LOCAL hnsw.iterative_scan = strict_order")
+        .execute(&mut *savepoint)
+        .await
+    {
+        Ok(_) => {
+            savepoint.commit().await.map_err(db_err)?;
+            Ok(true)
+        }
+        Err(e) => {
+            tracing::debug!("vector_store: iterative scan unavailable: {}", e);
+            savepoint.rollback().await.map_err(db_err)?;
+            Ok(false)
+        }
+    }
+}
+
+/// DELETE /api/vectors/collections/{name}/items/{id} — vs_delete
+pub async fn delete_item(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+) -> VsResult {
+    let col = load_collection(&state, &name).await?;
+    let result = sqlx::query("DELETE FROM gh_vector_items WHERE collection_id = $1 AND id = $2")
+        .bind(col.id)
+        .bind(&id)
+        .execute(&state.db)
+        .await
+        .map_err(db_err)?;
+    Ok(Json(json!({ "deleted": result.rows_affected() })))
+}
+
+/// POST /api/vectors/collections/{name}/invalidate-stale — Drop chunks whose
+/// source file changed (mtime differs) or disappeared since they were indexed.
+/// Chunks without a usable `mtime` cannot be verified and count as stale.
+pub async fn invalidate_stale(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    body: Option<Json<InvalidateRequest>>,
+) -> VsResult {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    let col = load_collection(&state, &name).await?;
+    // `mtime` is read as text: rows written before it was validated may hold
+    // non-integers, which would make a `::bigint` cast fail the whole query.
+    // Likewise only string `source_path`s are checked (not `null` or numbers).
+    let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, metadata->>'source_path', metadata->>'mtime' \
+         FROM gh_vector_items WHERE collection_id = $1 \
+           AND jsonb_typeof(metadata->'source_path') = 'string'",
+    )
+    .bind(col.id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_err)?;
+
+    let root = source_root(&state).await;
+    let checked = rows.len();
+    let mut stale = Vec::new();
+    for (id, path, indexed_mtime) in rows {
+        let indexed = indexed_mtime.and_then(|m| m.parse::<i64>().ok());
+        let current = file_mtime(&resolve_source(root.as_deref(), &path)).await;
+        if is_stale(indexed, current) {
+            stale.push(id);
+        }
+    }
+
+    let mut deleted = 0;
+    if !body.dry_run && !stale.is_empty() {
+        deleted =
+            sqlx::query("DELETE FROM gh_vector_items WHERE collection_id = $1 AND id = ANY($2)")
+                .bind(col.id)
+                .bind(&stale)
+                .execute(&state.db)
+                .await
+                .map_err(db_err)?
+                .rows_affected();
+    }
+    Ok(Json(json!({
+        "collection": name,
+        "checked": checked,
+        "stale": stale,
+        "deleted": deleted,
+        "dry_run": body.dry_run,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pgvector_literal_format() {
+        assert_eq!(to_pgvector(&[0.5, -1.0, 2.25]), "[0.5,-1,2.25]");
+    }
+
+    #[test]
+    fn embedding_dimension_is_enforced() {
+        assert!(check_embedding(&[0.1, 0.2, 0.3], 3).is_ok());
+        assert!(check_embedding(&[0.1, 0.2], 3).is_err());
+        assert!(check_embedding(&[0.1, f32::NAN, 0.3], 3).is_err());
+    }
+
+    #[test]
+    fn citations_link_to_line_spans() {
+        let span = json!({ "source_path": "src/lib.rs", "line_start": 10, "line_end": 42 });
+        assert_eq!(citation(&span).as_deref(), Some("src/lib.rs:10-42"));
+        let line = json!({ "source_path": "README.md", "line_start": 3 });
+        assert_eq!(citation(&line).as_deref(), Some("README.md:3"));
+        assert_eq!(citation(&json!({ "tags": ["x"] })), None);
+    }
+
+    #[tokio::test]
+    async fn metadata_is_validated() {
+        let bad_span = json!({ "line_start": 9, "line_end": 2 });
+        assert!(normalize_metadata(Some(bad_span), None).await.is_err());
+        assert!(
+            normalize_metadata(Some(json!({ "tags": [1] })), None)
+                .await
+                .is_err()
+        );
+        assert!(normalize_metadata(Some(json!([1, 2])), None).await.is_err());
+        for path in [json!(null), json!(42)] {
+            assert!(
+                normalize_metadata(Some(json!({ "source_path": path })), None)
+                    .await
+                    .is_err()
+            );
+        }
+        for mtime in [json!("1700000000"), json!(1.5)] {
+            assert!(
+                normalize_metadata(Some(json!({ "mtime": mtime })), None)
+                    .await
+                    .is_err()
+            );
+        }
+        let ok = normalize_metadata(None, None)
+            .await
+            .expect("empty metadata is fine");
+        assert_eq!(ok, json!({}));
+    }
+
+    #[test]
+    fn staleness_and_relative_sources() {
+        assert!(!is_stale(Some(10), Some(10)));
+        assert!(is_stale(Some(10), Some(11)));
+        assert!(is_stale(None, Some(10)));
+        assert!(is_stale(Some(10), None));
+        assert_eq!(
+            resolve_source(Some("/repo"), "src/lib.rs"),
+            std::path::Path::new("/repo").join("src/lib.rs")
+        );
+        assert_eq!(
+            resolve_source(None, "src/lib.rs"),
+            std::path::Path::new("src/lib.rs")
+        );
+    }
+
+    #[test]
+    fn model_is_required_for_model_bound_collections() {
+        assert!(check_model("docs", None, None).is_ok());
+        assert!(
+            check_model(
+                "docs",
+                Some("text-embedding-004"),
+                Some("text-embedding-004")
+            )
+            .is_ok()
+        );
+        assert!(check_model("docs", Some("text-embedding-004"), None).is_err());
+        assert!(check_model("docs", Some("text-embedding-004"), Some("other")).is_err());
+    }
+
+    #[test]
+    fn unknown_metric_is_rejected() {
+        assert!(metric_ops("cosine").is_some());
+        assert!(metric_ops("hamming").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+// ---------------------------------------------------------------------------
+// vertex.rs — Google Vertex AI as an alternative Gemini endpoint
+//
+// Enabled when `VERTEX_PROJECT_ID` is set. Requests then go to
+// `{location}-aiplatform.googleapis.com` and authenticate with an OAuth2
+// access token minted from the service-account JSON referenced by
+// `GOOGLE_APPLICATION_CREDENTIALS` (JWT bearer grant, RS256). Tokens are
+// cached per credentials file and project, and refreshed a few minutes
+// before they expire.
+//
+// Only requests whose URL this crate builds are routed to Vertex:
+// `/api/execute`, model listing and `gemini_api::generate_raw`. WebSocket
+// chat and A2A swarm calls build a Generative Language URL in the shared
+// crates, so they keep authenticating with the API key
+// (`use_api_key_credential`) and are refused by `ensure_api_key_route` when
+// no key is configured, rather than sending the Vertex token to that API.
+// ---------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use crate::state::AppState;
+
+const DEFAULT_LOCATION: &str = "us-central1";
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const TOKEN_LIFETIME_SECS: i64 = 3600;
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexConfig {
+    pub project_id: String,
+    pub location: String,
+    pub credentials_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+struct CachedToken {
+    token: String,
+    refresh_at: Instant,
+}
+
+/// Keyed by `(credentials_path, project_id)` so switching either never reuses
+/// a token minted for the other configuration.
+type TokenKey = (Option<String>, String);
+
+static TOKEN_CACHE: LazyLock<Mutex<HashMap<TokenKey, CachedToken>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Vertex configuration from the environment; `None` means the Generative
+/// Language API with an API key is used.
+pub fn config() -> Option<VertexConfig> {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    Some(VertexConfig {
+        project_id: env("VERTEX_PROJECT_ID")?,
+        location: env("VERTEX_LOCATION").unwrap_or_else(|| DEFAULT_LOCATION.to_string()),
+        credentials_path: env("GOOGLE_APPLICATION_CREDENTIALS"),
+    })
+}
+
+fn host(cfg: &VertexConfig) -> String {
+    if cfg.location == "global" {
+        "aiplatform.googleapis.com".to_string()
+    } else {
+        format!("{}-aiplatform.googleapis.com", cfg.location)
+    }
+}
+
+/// `.../publishers/google/models/{model}` on the regional (or global) host.
+pub fn model_url(cfg: &VertexConfig, model: &str) -> String {
+    format!(
+        "https://{}/v1/projects/{}/locations/{}/publishers/google/models/{}",
+        host(cfg),
+        cfg.project_id,
+        cfg.location,
+        model
+    )
+}
+
+/// `.../publishers/google/models/{model}:{method}` on the regional (or global) host.
+pub fn model_method_url(cfg: &VertexConfig, model: &str, method: &str) -> String {
+    format!("{}:{}", model_url(cfg, model), method)
+}
+
+/// Publisher model listing (`v1beta1/publishers/google/models`).
+pub fn models_url(cfg: &VertexConfig) -> String {
+    format!("https://{}/v1beta1/publishers/google/models", host(cfg))
+}
+
+/// Refuse a request bound for the Generative Language API while Vertex is
+/// configured without an API key to send there.
+pub fn ensure_api_key_route() -> Result<(), String> {
+    if config().is_some() && crate::gemini_api::api_key().is_none() {
+        return Err(
+            "Vertex AI serves /api/execute only; WebSocket chat and swarm \
+             requests use the Generative Language API and need GOOGLE_API_KEY \
+             or GEMINI_API_KEY"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Give a context whose request URL is built by the shared crates the API
+/// key instead of the Vertex token `resolve_api_credential` handed out.
+pub fn use_api_key_credential(ctx: &mut jaskier_core::context::ExecuteContext) {
+    if config().is_some() {
+        ctx.api_key = crate::gemini_api::api_key().unwrap_or_default();
+        ctx.is_oauth = false;
+    }
+}
+
+fn signed_assertion(sa: &ServiceAccount, now: i64) -> Result<String, String> {
+    let claims = json!({
+        "iss": sa.client_email,
+        "scope": SCOPE,
+        "aud": sa.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI),
+        "iat": now,
+        "exp": now + TOKEN_LIFETIME_SECS,
+    });
+    let key = EncodingKey::from_rsa_pem(sa.private_key.as_bytes())
+        .map_err(|e| format!("invalid service-account private key: {}", e))?;
+    jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)
+        .map_err(|e| format!("failed to sign token assertion: {}", e))
+}
+
+/// A valid OAuth2 access token for Vertex, minted or refreshed as needed.
+pub async fn access_token(state: &AppState, cfg: &VertexConfig) -> Result<String, String> {
+    crate::offline::ensure_online().map_err(|e| e.to_string())?;
+    let key: TokenKey = (cfg.credentials_path.clone(), cfg.project_id.clone());
+    let mut cache = TOKEN_CACHE.lock().await;
+    if let Some(cached) = cache.get(&key)
+        && Instant::now() < cached.refresh_at
+    {
+        return Ok(cached.token.clone());
+    }
+
+    let path = cfg
+        .credentials_path
+        .as_deref()
+        .ok_or("GOOGLE_APPLICATION_CREDENTIALS is not set")?;
+    let raw = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("cannot read service-account file: {}", e))?;
+    let sa: ServiceAccount =
+        serde_json::from_str(&raw).map_err(|e| format!("invalid service-account file: {}", e))?;
+    let assertion = signed_assertion(&sa, chrono::Utc::now().timestamp())?;
+
+    let res = tokio::time::timeout(
+        TOKEN_TIMEOUT,
+        state
+            .client
+            .post(sa.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI))
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send(),
+    )
+    .await
+    .map_err(|_| "token request timed out".to_string())?
+    .map_err(|e| format!("token request failed: {}", e))?;
+    let status = res.status();
+    let body: Value = res
+        .json()
+        .await
+        .map_err(|e| format!("invalid token response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!(
+            "token endpoint returned HTTP {}: {}",
+            status.as_u16(),
+            body.get("error_description")
+                .or_else(|| body.get("error"))
+                .and_then(|e| e.as_str())
+                .unwrap_or("unknown error")
+        ));
+    }
+    let token = body
+        .get("access_token")
+        .and_then(|t| t.as_str())
+        .ok_or("token response has no access_token")?
+        .to_string();
+    let expires_in = body
+        .get("expires_in")
+        .and_then(|e| e.as_u64())
+        .unwrap_or(TOKEN_LIFETIME_SECS as u64);
+
+    tracing::info!(
+        project = %cfg.project_id,
+        location = %cfg.location,
+        "vertex: access token refreshed"
+    );
+    cache.insert(
+        key,
+        CachedToken {
+            token: token.clone(),
+            refresh_at: Instant::now()
+                + Duration::from_secs(expires_in).saturating_sub(REFRESH_MARGIN),
+        },
+    );
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regional_and_global_urls() {
+        let mut cfg = VertexConfig {
+            project_id: "acme".to_string(),
+            location: "europe-west4".to_string(),
+            credentials_path: None,
+        };
+        assert_eq!(
+            model_method_url(&cfg, "gemini-2.5-pro", "generateContent"),
+            "https://europe-west4-aiplatform.googleapis.com/v1/projects/acme/locations/europe-west4/publishers/google/models/gemini-2.5-pro:generateContent"
+        );
+        cfg.location = "global".to_string();
+        assert!(
+            model_method_url(&cfg, "m", "countTokens").starts_with(
+                "https://aiplatform.googleapis.com/v1/projects/acme/locations/global/"
+            )
+        );
+        assert_eq!(
+            models_url(&cfg),
+            "https://aiplatform.googleapis.com/v1beta1/publishers/google/models"
+        );
+    }
+}
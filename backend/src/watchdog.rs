@@ -35,21 +35,18 @@ pub fn spawn(state: AppState) -> tokio::task::JoinHandle<()> {
     shared_handle
 }
 
-/// Check Google Generative Language API reachability.
-/// Uses a lightweight HEAD request to generativelanguage.googleapis.com (no tokens consumed).
-/// Skips if no credential is available (env var — Vault sets these).
+/// Check Google API reachability (Generative Language, or Vertex AI when configured).
+/// Uses a lightweight HEAD request against the model listing (no tokens consumed).
+/// Skips if no credential is available (env var — Vault sets these) or
+/// offline mode is on.
 async fn check_google_api(state: &AppState) -> bool {
-    // B13: credentials come from env vars (Vault sets these)
-    let has_env_key = std::env::var("GOOGLE_API_KEY")
-        .ok()
-        .filter(|k| !k.is_empty())
-        .is_some()
-        || std::env::var("GEMINI_API_KEY")
-            .ok()
-            .filter(|k| !k.is_empty())
-            .is_some();
+    if crate::offline::is_offline() {
+        tracing::debug!("watchdog: offline mode, skipping Google API reachability check");
+        return true;
+    }
 
-    if !has_env_key {
+    // B13: credentials come from env vars (Vault sets these)
+    if crate::vertex::config().is_none() && crate::gemini_api::api_key().is_none() {
         // No credential — skip check (not an error)
         tracing::debug!(
             "watchdog: no Google API credential configured, skipping reachability check"
@@ -62,7 +59,7 @@ async fn check_google_api(state: &AppState) -> bool {
         state
             .base
             .client
-            .head("https://generativelanguage.googleapis.com/v1beta/models")
+            .head(crate::gemini_api::list_models_url())
             .send(),
     )
     .await;
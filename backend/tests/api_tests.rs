@@ -1,67 +1,10 @@
+mod common;
+
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
-use http_body_util::BodyExt;
-use serde_json::Value;
-use sqlx::postgres::PgPoolOptions;
 use tower::ServiceExt;
 
-use gemini_hydra_backend::state::{AppState, LogRingBuffer};
-
-/// Helper: build a fresh AppState backed by a test Postgres database.
-/// Returns None when DATABASE_URL is not set (CI without DB).
-async fn try_test_state() -> Option<AppState> {
-    dotenvy::dotenv().ok();
-    let database_url = match std::env::var("DATABASE_URL") {
-        Ok(url) => url,
-        Err(_) => return None,
-    };
-    let pool = match PgPoolOptions::new()
-        .max_connections(2)
-        .acquire_timeout(std::time::Duration::from_secs(5))
-        .connect(&database_url)
-        .await
-    {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("DB unreachable ({}), skipping test", e);
-            return None;
-        }
-    };
-    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
-        eprintln!("Migrations failed ({}), skipping test", e);
-        return None;
-    }
-    let log_buffer = std::sync::Arc::new(LogRingBuffer::new(1000));
-    Some(AppState::new(pool.clone(), pool, log_buffer).await)
-}
-
-/// Convenience macro: skip the test when DATABASE_URL is absent.
-macro_rules! require_db {
-    () => {
-        match try_test_state().await {
-            Some(s) => s,
-            None => {
-                eprintln!("Skipping: DATABASE_URL not set");
-                return;
-            }
-        }
-    };
-}
-
-/// Helper: build a router from a test state.
-/// Uses `create_test_router` (no GovernorLayer) + `MockConnectInfo` for handler extractors.
-fn app(state: AppState) -> axum::Router {
-    use axum::extract::connect_info::MockConnectInfo;
-    gemini_hydra_backend::create_test_router(state).layer(MockConnectInfo(
-        std::net::SocketAddr::from(([127, 0, 0, 1], 3000)),
-    ))
-}
-
-/// Helper: collect a response body into a serde_json::Value.
-async fn body_json(response: axum::response::Response) -> Value {
-    let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    serde_json::from_slice(&bytes).unwrap()
-}
+use common::{app, body_json};
 
 // â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•
 //  GET /api/health
@@ -403,3 +346,60 @@ async fn unknown_route_returns_404() {
 
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+// â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•
+//  GET /api/capabilities
+// â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•â•
+
+#[tokio::test]
+async fn advertised_routes_are_routed() {
+    let state = require_db!();
+    let response = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/capabilities")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = body_json(response).await;
+    let groups = json["api"].as_array().expect("api should be an array");
+    assert!(!groups.is_empty());
+    for route in groups
+        .iter()
+        .flat_map(|group| group["routes"].as_array().expect("routes array"))
+    {
+        let route = route.as_str().expect("route should be a string");
+        let uri: String = route
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') {
+                    "00000000-0000-0000-0000-000000000000"
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        // No route registers TRACE: a routed path answers 405, an unknown one 404.
+        let response = app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .method("TRACE")
+                    .uri(&uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_ne!(
+            response.status(),
+            StatusCode::NOT_FOUND,
+            "{} is advertised but not routed",
+            route
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// Jaskier Shared Pattern -- backend integration test support
+// GeminiHydra v15 - Mock providers + router harness for integration tests
+//
+// `MockGemini` runs a local wiremock server that stands in for the Google
+// Generative Language API. The backend is pointed at it through
+// `GEMINI_API_BASE_URL`, so tests can drive real HTTP handlers end-to-end
+// without network access or credentials.
+
+#![allow(dead_code)]
+
+use axum::body::Body;
+use axum::http::Request;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use sqlx::postgres::PgPoolOptions;
+use tokio::sync::{Mutex, MutexGuard};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use gemini_hydra_backend::state::{AppState, LogRingBuffer};
+
+/// Env vars are process-global — tests that redirect the Gemini API hold this lock.
+static ENV_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Mock Gemini API. Keep the value alive for the duration of the test.
+pub struct MockGemini {
+    pub server: MockServer,
+    _env_guard: MutexGuard<'static, ()>,
+}
+
+impl MockGemini {
+    /// Start the mock server with default `models` and `generateContent` responses.
+    pub async fn start() -> Self {
+        let guard = ENV_LOCK.lock().await;
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/v1beta/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [{
+                    "name": "models/gemini-mock-flash",
+                    "displayName": "Gemini Mock Flash",
+                    "supportedGenerationMethods": ["generateContent", "countTokens"]
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        Self::mount_generate(&server, "mock response").await;
+
+        // SAFETY: guarded by ENV_LOCK; no other test thread reads these concurrently.
+        unsafe {
+            std::env::set_var("GEMINI_API_BASE_URL", server.uri());
+            std::env::set_var("GOOGLE_API_KEY", "mock-key");
+        }
+
+        Self {
+            server,
+            _env_guard: guard,
+        }
+    }
+
+    /// Mount a `generateContent` response returning `text` for any model.
+    pub async fn mount_generate(server: &MockServer, text: &str) {
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/v1beta/models/[^/]+:generateContent$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": text }] },
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": { "promptTokenCount": 4, "candidatesTokenCount": 2, "totalTokenCount": 6 }
+            })))
+            .mount(server)
+            .await;
+    }
+}
+
+impl Drop for MockGemini {
+    fn drop(&mut self) {
+        // SAFETY: still holding ENV_LOCK (guard drops after this body).
+        unsafe {
+            std::env::remove_var("GEMINI_API_BASE_URL");
+            std::env::remove_var("GOOGLE_API_KEY");
+        }
+    }
+}
+
+/// Build a fresh AppState backed by a test Postgres database.
+/// Returns None when DATABASE_URL is not set (CI without DB).
+pub async fn try_test_state() -> Option<AppState> {
+    dotenvy::dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL").ok()?;
+    let pool = match PgPoolOptions::new()
+        .max_connections(2)
+        .acquire_timeout(std::time::Duration::from_secs(5))
+        .connect(&database_url)
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("DB unreachable ({}), skipping test", e);
+            return None;
+        }
+    };
+    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
+        eprintln!("Migrations failed ({}), skipping test", e);
+        return None;
+    }
+    let log_buffer = std::sync::Arc::new(LogRingBuffer::new(1000));
+    Some(AppState::new(pool.clone(), pool, log_buffer).await)
+}
+
+/// Router without rate limiting + `MockConnectInfo` for handler extractors.
+pub fn app(state: AppState) -> axum::Router {
+    use axum::extract::connect_info::MockConnectInfo;
+    gemini_hydra_backend::create_test_router(state).layer(MockConnectInfo(
+        std::net::SocketAddr::from(([127, 0, 0, 1], 3000)),
+    ))
+}
+
+/// Collect a response body into a serde_json::Value.
+pub async fn body_json(response: axum::response::Response) -> Value {
+    let bytes = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body should be readable")
+        .to_bytes();
+    serde_json::from_slice(&bytes).expect("body should be JSON")
+}
+
+/// Send a request through the router and return `(status, json body)`.
+pub async fn send(app: axum::Router, request: Request<Body>) -> (axum::http::StatusCode, Value) {
+    let response = app.oneshot(request).await.expect("router should respond");
+    let status = response.status();
+    let bytes = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body should be readable")
+        .to_bytes();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+/// JSON POST helper.
+pub fn post_json(uri: &str, body: &Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid request")
+}
+
+/// Skip the test when DATABASE_URL is absent.
+#[macro_export]
+macro_rules! require_db {
+    () => {
+        match common::try_test_state().await {
+            Some(s) => s,
+            None => {
+                eprintln!("Skipping: DATABASE_URL not set");
+                return;
+            }
+        }
+    };
+}
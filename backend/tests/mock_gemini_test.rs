@@ -0,0 +1,58 @@
+// Jaskier Shared Pattern -- backend integration test
+// GeminiHydra v15 - Handlers driven end-to-end against the mock Gemini API
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::json;
+
+use common::MockGemini;
+
+#[tokio::test]
+async fn project_brief_uses_mocked_generate_content() {
+    let _mock = MockGemini::start().await;
+    let state = require_db!();
+
+    let workspace = std::env::temp_dir().join(format!("gh-brief-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&workspace).expect("create workspace");
+    std::fs::write(workspace.join("README.md"), "# Demo\nA test project.").expect("write readme");
+
+    let (status, json) = common::send(
+        common::app(state),
+        common::post_json(
+            "/api/files/brief",
+            &json!({ "path": workspace.to_string_lossy() }),
+        ),
+    )
+    .await;
+    std::fs::remove_dir_all(&workspace).ok();
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["model_generated"], true);
+    assert!(
+        json["summary"]
+            .as_str()
+            .expect("summary string")
+            .contains("mock response")
+    );
+}
+
+#[tokio::test]
+async fn gemini_models_lists_mocked_models() {
+    let _mock = MockGemini::start().await;
+    let state = require_db!();
+
+    let (status, json) = common::send(
+        common::app(state),
+        Request::builder()
+            .uri("/api/gemini/models")
+            .body(Body::empty())
+            .expect("valid request"),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    let models = json["models"].as_array().expect("models array");
+    assert_eq!(models.len(), 1);
+}